@@ -93,3 +93,29 @@ pub fn instance_kind(tcx: TyCtxt<'_>, inst: &Instance) -> OpaqueInstanceKind {
         is_reify_shim: matches!(kind, middle::ty::InstanceKind::ReifyShim(..)),
     }
 }
+
+/// Trait path, implementing type and method name of a trait method
+/// instance, for methods of an `impl` as well as provided methods of the
+/// trait itself; `None` for functions that are not trait methods. The name
+/// is looked up last: closures and other unnamed items have none.
+pub fn trait_method(tcx: TyCtxt<'_>, inst: &Instance) -> Option<(String, String, String)> {
+    let internal_inst = rustc_internal::internal(tcx, inst);
+    let def_id = internal_inst.def_id();
+    if let Some(impl_id) = tcx.impl_of_method(def_id) {
+        // the leading arguments of the method instance are those of the impl
+        let trait_ref = tcx
+            .impl_trait_ref(impl_id)?
+            .instantiate(tcx, internal_inst.args);
+        return Some((
+            tcx.def_path_str(trait_ref.def_id),
+            trait_ref.self_ty().to_string(),
+            tcx.opt_item_name(def_id)?.to_string(),
+        ));
+    }
+    let trait_id = tcx.trait_of_item(def_id)?;
+    Some((
+        tcx.def_path_str(trait_id),
+        internal_inst.args.type_at(0).to_string(),
+        tcx.opt_item_name(def_id)?.to_string(),
+    ))
+}
@@ -12,9 +12,9 @@
 //!
 //! | Module | Purpose |
 //! |--------|---------|
-//! | [`bridge`] | Stable-to-internal conversions (`Instance`, `InstanceKind`, unevaluated consts) |
+//! | [`bridge`] | Stable-to-internal conversions (`Instance`, `InstanceKind`, unevaluated consts, trait methods) |
 //! | [`mono_collect`] | Monomorphization collection and symbol naming |
-//! | [`output`] | Output filename resolution from the compiler session |
+//! | [`output`] | Output filename resolution, target triple and UB check setting from the compiler session |
 //! | [`spans`] | Span-to-source-location resolution |
 //! | [`types`] | Type queries: generics, signatures, discriminants, attributes |
 //!
@@ -25,6 +25,7 @@
 //! `DefId`, etc.) without requiring them to know which rustc crate the
 //! type actually lives in.
 
+pub extern crate rustc_attr;
 pub extern crate rustc_middle;
 pub extern crate rustc_monomorphize;
 pub extern crate rustc_session;
@@ -37,6 +38,9 @@ pub extern crate stable_mir;
 // serializing types that come from the compiler.
 pub extern crate serde;
 pub extern crate serde_json;
+// Likewise rustc's `tracing`, whose subscriber the driver installs from
+// `RUSTC_LOG`.
+pub extern crate tracing;
 
 /// Alias for `rustc_middle`; keeps import paths shorter.
 pub use rustc_middle as middle;
@@ -1,9 +1,10 @@
-//! Output filename resolution.
+//! Output filename resolution and session settings.
 //!
-//! Wraps `tcx.output_filenames().path(OutputType::Mir)` so that callers
-//! don't need to import `rustc_session` directly.
+//! Wraps `tcx.output_filenames().path(OutputType::Mir)`, the session's
+//! target spec and its UB check setting so that callers don't need to import
+//! `rustc_session` directly.
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use super::rustc_session::config::{OutFileName, OutputType};
 use super::TyCtxt;
@@ -16,9 +17,30 @@ pub enum OutputDest {
 
 /// Resolve the MIR output path from the compiler session, replacing
 /// the extension with the given one.
+///
+/// Device paths (`-o /dev/fd/3`, `-o /dev/stderr`, `/proc/self/fd/N` as
+/// produced by process substitution) are kept as they are, since they name
+/// a stream rather than a file to derive siblings from.
 pub fn mir_output_path(tcx: TyCtxt<'_>, extension: &str) -> OutputDest {
     match tcx.output_filenames(()).path(OutputType::Mir) {
         OutFileName::Stdout => OutputDest::Stdout,
+        OutFileName::Real(path) if is_stream(&path) => OutputDest::File(path),
         OutFileName::Real(path) => OutputDest::File(path.with_extension(extension)),
     }
 }
+
+/// Whether `path` names a device or file descriptor rather than a regular file
+pub fn is_stream(path: &Path) -> bool {
+    path.starts_with("/dev") || path.starts_with("/proc/self/fd")
+}
+
+/// Whether `UbChecks` evaluates to true in this compilation (`-Zub-checks`,
+/// which defaults to whether debug assertions are enabled)
+pub fn ub_checks(tcx: TyCtxt<'_>) -> bool {
+    tcx.sess.ub_checks()
+}
+
+/// Target triple the crate is compiled for, as LLVM names it
+pub fn target_triple(tcx: TyCtxt<'_>) -> String {
+    tcx.sess.target.llvm_target.to_string()
+}
@@ -1,22 +1,56 @@
 //! Span-to-source-location resolution.
 //!
-//! Wraps the `source_map().span_to_location_info()` internal API
-//! so that callers don't need to touch `rustc_span` directly.
+//! Wraps the `source_map().span_to_location_info()` internal API and span
+//! expansion data so that callers don't need to touch `rustc_span` directly.
+
+use std::fmt::Write;
 
 use super::internal;
 use super::rustc_span;
 use super::stable_mir;
 use super::TyCtxt;
+use rustc_span::hygiene::{ExpnKind, MacroKind};
 use stable_mir::ty::Span;
 
 /// Source location tuple: `(file, lo_line, lo_col, hi_line, hi_col)`.
 pub type SourceData = (String, usize, usize, usize, usize);
 
+/// Source file metadata tuple: `(crate, line_count, content_hash)`.
+pub type SourceFileMeta = (String, usize, String);
+
 /// Resolve a stable MIR span to a (file, lo_line, lo_col, hi_line, hi_col) tuple.
 pub fn resolve_span(tcx: TyCtxt<'_>, span: &Span) -> SourceData {
+    location_of(tcx, internal(tcx, span))
+}
+
+/// For a span produced by a macro expansion, the macro (`vec!`, `#[test]`,
+/// `#[derive(Debug)]`) and the location of the outermost call site, which is
+/// where the expansion appears in the user's source.
+pub fn macro_origin(tcx: TyCtxt<'_>, span: &Span) -> Option<(String, SourceData)> {
     let span_internal = internal(tcx, span);
+    let ExpnKind::Macro(kind, name) = span_internal.ctxt().outer_expn_data().kind else {
+        return None;
+    };
+    let macro_name = match kind {
+        MacroKind::Bang => format!("{}!", name),
+        MacroKind::Attr => format!("#[{}]", name),
+        MacroKind::Derive => format!("#[derive({})]", name),
+    };
+    Some((
+        macro_name,
+        location_of(tcx, span_internal.source_callsite()),
+    ))
+}
+
+/// The crate's root source file (`src/main.rs`, `src/lib.rs`), named as in
+/// [`SourceData`].
+pub fn crate_root_file(tcx: TyCtxt<'_>) -> String {
+    location_of(tcx, tcx.def_span(rustc_span::def_id::CRATE_DEF_ID)).0
+}
+
+fn location_of(tcx: TyCtxt<'_>, span: rustc_span::Span) -> SourceData {
     let (source_file, lo_line, lo_col, hi_line, hi_col) =
-        tcx.sess.source_map().span_to_location_info(span_internal);
+        tcx.sess.source_map().span_to_location_info(span);
     let file_name = match source_file {
         Some(sf) => sf
             .name
@@ -26,3 +60,29 @@ pub fn resolve_span(tcx: TyCtxt<'_>, span: &Span) -> SourceData {
     };
     (file_name, lo_line, lo_col, hi_line, hi_col)
 }
+
+/// The crate a source file (named as in [`SourceData`]) belongs to, its
+/// number of lines, and the hash of its contents as `algorithm:hex`
+/// (`sha256:9f86...`), so consumers can tell when the file changed.
+pub fn source_file_meta(tcx: TyCtxt<'_>, file: &str) -> Option<SourceFileMeta> {
+    let files = tcx.sess.source_map().files();
+    let sf = files.iter().find(|sf| {
+        sf.name
+            .display(rustc_span::FileNameDisplayPreference::Remapped)
+            .to_string()
+            == file
+    })?;
+    let hash = sf
+        .src_hash
+        .hash_bytes()
+        .iter()
+        .fold(String::new(), |mut hex, b| {
+            let _ = write!(hex, "{:02x}", b);
+            hex
+        });
+    Some((
+        tcx.crate_name(sf.cnum).to_string(),
+        sf.count_lines(),
+        format!("{:?}:{}", sf.src_hash.kind, hash).to_lowercase(),
+    ))
+}
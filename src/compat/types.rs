@@ -2,7 +2,8 @@
 //!
 //! Wraps `tcx.generics_of()`, `tcx.predicates_of()`, `tcx.fn_sig()`,
 //! `tcx.optimized_mir()`, `tcx.def_kind()`, `tcx.type_of()`,
-//! `tcx.has_attr()`, `adt.discriminants(tcx)`, and `tcx.fn_abi_of_fn_ptr()`.
+//! `tcx.has_attr()`, `tcx.codegen_fn_attrs()`, `adt.discriminants(tcx)`, and
+//! `tcx.fn_abi_of_fn_ptr()`.
 
 use super::middle;
 use super::middle::ty::{EarlyBinder, FnSig, GenericArgs, List, Ty, TypeFoldable, TypingEnv};
@@ -96,6 +97,36 @@ pub fn has_attr(
     tcx.has_attr(rustc_internal::internal(tcx, item), attr)
 }
 
+/// Codegen attributes of an item that shape its MIR and calls: `inline`,
+/// `inline(always)`, `inline(never)`, `cold`, `no_mangle`, `track_caller`
+/// and `naked`, in that order.
+pub fn codegen_attrs(tcx: TyCtxt<'_>, id: DefId) -> Vec<&'static str> {
+    use super::rustc_attr::InlineAttr;
+    use middle::middle::codegen_fn_attrs::CodegenFnAttrFlags;
+    if !tcx.def_kind(id).has_codegen_attrs() {
+        return vec![];
+    }
+    let attrs = tcx.codegen_fn_attrs(id);
+    let mut names = match attrs.inline {
+        InlineAttr::Hint => vec!["inline"],
+        InlineAttr::Always => vec!["inline(always)"],
+        InlineAttr::Never => vec!["inline(never)"],
+        InlineAttr::None => vec![],
+    };
+    let flags = [
+        (CodegenFnAttrFlags::COLD, "cold"),
+        (CodegenFnAttrFlags::NO_MANGLE, "no_mangle"),
+        (CodegenFnAttrFlags::TRACK_CALLER, "track_caller"),
+        (CodegenFnAttrFlags::NAKED, "naked"),
+    ];
+    for (flag, name) in flags {
+        if attrs.flags.contains(flag) {
+            names.push(name);
+        }
+    }
+    names
+}
+
 /// Collect discriminant values for an ADT (enum) by going through internals.
 pub fn adt_discriminants(tcx: TyCtxt<'_>, adt_def: stable_mir::ty::AdtDef) -> Vec<u128> {
     let adt_internal = rustc_internal::internal(tcx, adt_def);
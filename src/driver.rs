@@ -3,10 +3,11 @@
 //! 1.  the rustc compiler context is available
 //! 2.  the rustc `stable_mir` APIs are available
 //!
-//! It exports a single function:
+//! It exports two functions:
 //!
 //! ```rust,ignore
 //! stable_mir_driver(args: &Vec<String>, callback_fn: fn (TyCtxt) -> () )
+//! stable_mir_check(args: &Vec<String>, check_fn: fn (TyCtxt) -> bool ) -> bool
 //! ```
 //!
 //! Calling this function is essentially equivalent to the following macro call:
@@ -27,22 +28,55 @@ use rustc_interface::interface::Compiler;
 use rustc_middle::ty::TyCtxt;
 use rustc_smir::rustc_internal;
 
+/// The function run with the compiler context
+enum Callback {
+    /// Writes an output
+    Emit(fn(TyCtxt) -> ()),
+    /// Checks the crate, returning whether the check passed
+    Check(fn(TyCtxt) -> bool),
+}
+
 struct StableMirCallbacks {
-    callback_fn: fn(TyCtxt) -> (),
+    callback: Callback,
+    /// Whether the callback ran and its check, if any, passed
+    passed: bool,
 }
 
 impl rustc_driver::Callbacks for StableMirCallbacks {
     fn after_analysis(&mut self, _compiler: &Compiler, tcx: TyCtxt) -> Compilation {
-        let _ = rustc_internal::run(tcx, || (self.callback_fn)(tcx));
+        let passed = rustc_internal::run(tcx, || match self.callback {
+            Callback::Emit(callback_fn) => {
+                callback_fn(tcx);
+                true
+            }
+            Callback::Check(check_fn) => check_fn(tcx),
+        });
+        self.passed = passed.unwrap_or(false);
+        crate::timings::report();
 
         Compilation::Continue
     }
 }
 
-pub fn stable_mir_driver(args_outer: &[String], callback_fn: fn(TyCtxt) -> ()) {
-    let mut callbacks = StableMirCallbacks { callback_fn };
+fn run_driver(args_outer: &[String], callback: Callback) -> bool {
+    let mut callbacks = StableMirCallbacks {
+        callback,
+        passed: false,
+    };
     let early_dcx =
         rustc_session::EarlyDiagCtxt::new(rustc_session::config::ErrorOutputType::default());
     rustc_driver::init_rustc_env_logger(&early_dcx);
     let _ = rustc_driver::RunCompiler::new(args_outer, &mut callbacks).run();
+    callbacks.passed
+}
+
+pub fn stable_mir_driver(args_outer: &[String], callback_fn: fn(TyCtxt) -> ()) {
+    run_driver(args_outer, Callback::Emit(callback_fn));
+}
+
+/// Run `check_fn` like [`stable_mir_driver`] runs its callback; returns
+/// whether the check passed, once the compiler has finished, so the caller
+/// can set the exit status
+pub fn stable_mir_check(args_outer: &[String], check_fn: fn(TyCtxt) -> bool) -> bool {
+    run_driver(args_outer, Callback::Check(check_fn))
 }
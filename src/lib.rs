@@ -2,7 +2,9 @@
 pub mod compat;
 pub mod driver;
 pub mod mk_graph;
+pub mod pipeline;
 pub mod printer;
+pub mod timings;
 pub use compat::types::has_attr;
-pub use driver::stable_mir_driver;
+pub use driver::{stable_mir_check, stable_mir_driver};
 pub use printer::*;
@@ -1,18 +1,109 @@
 #![feature(rustc_private)]
-use stable_mir_json::driver::stable_mir_driver;
-use stable_mir_json::mk_graph::{emit_d2file, emit_dotfile};
+use stable_mir_json::compat::TyCtxt;
+use stable_mir_json::driver::{stable_mir_check, stable_mir_driver};
+use stable_mir_json::mk_graph::{
+    emit_annotated_sources, emit_arith_csv, emit_arith_markdown, emit_budget_check,
+    emit_copies_markdown, emit_counter_harness, emit_d2file, emit_dependency_graph, emit_dotfile,
+    emit_function_diff, emit_panics_json, emit_panics_markdown, emit_sarif, emit_skeleton,
+    emit_span_report, emit_summary_cards, emit_svgfile,
+};
 use stable_mir_json::printer::emit_smir;
 use std::env;
 
 fn main() {
     let mut args: Vec<String> = env::args().collect();
+    // `--redact`, `--skeleton`, `--simplified`, `--timings`, `--analyze`,
+    // `--items=<kinds>` and `--lang=<code>` combine with every output mode
+    loop {
+        match args.get(1) {
+            Some(arg) if arg == "--redact" => {
+                args.remove(1);
+                env::set_var("REDACT", "1");
+            }
+            Some(arg) if arg == "--skeleton" => {
+                args.remove(1);
+                env::set_var("SKELETON", "1");
+            }
+            Some(arg) if arg == "--simplified" => {
+                args.remove(1);
+                env::set_var("SIMPLIFIED", "1");
+            }
+            Some(arg) if arg == "--timings" => {
+                args.remove(1);
+                env::set_var("TIMINGS", "1");
+            }
+            Some(arg) if arg == "--analyze" => {
+                args.remove(1);
+                env::set_var("ANALYZE", "1");
+            }
+            Some(arg) if arg.starts_with("--items=") => {
+                let arg = args.remove(1);
+                env::set_var("ITEM_KINDS", &arg["--items=".len()..]);
+            }
+            Some(arg) if arg.starts_with("--lang=") => {
+                let arg = args.remove(1);
+                env::set_var("MIR_LANG", &arg["--lang=".len()..]);
+            }
+            _ => break,
+        }
+    }
+
+    // the skeleton replaces the full JSON output
+    let emit_json: fn(TyCtxt) = if env::var("SKELETON").is_ok() {
+        emit_skeleton
+    } else {
+        emit_smir
+    };
 
     match args.get(1) {
-        None => stable_mir_driver(&args, emit_smir), // backward compatibility
+        None => stable_mir_driver(&args, emit_json), // backward compatibility
         Some(arg) if arg == "--json" => {
             args.remove(1);
+            stable_mir_driver(&args, emit_json)
+        }
+        Some(arg) if arg == "--query-fn" => {
+            args.remove(1);
+            if args.len() < 2 {
+                eprintln!("--query-fn requires a function name");
+                std::process::exit(1);
+            }
+            env::set_var("QUERY_FN", args.remove(1));
+            stable_mir_driver(&args, emit_json)
+        }
+        Some(arg) if arg == "--passes" => {
+            args.remove(1);
+            if args.len() < 2 {
+                eprintln!("--passes requires a pass selector");
+                std::process::exit(1);
+            }
+            if env::var("SKELETON").is_ok() {
+                eprintln!("--passes cannot be combined with --skeleton");
+                std::process::exit(1);
+            }
+            env::set_var("MIR_PASSES", args.remove(1));
             stable_mir_driver(&args, emit_smir)
         }
+        Some(arg) if arg == "--check-budgets" => {
+            args.remove(1);
+            if args.len() < 2 {
+                eprintln!("--check-budgets requires a budgets file");
+                std::process::exit(1);
+            }
+            env::set_var("BUDGETS", args.remove(1));
+            if !stable_mir_check(&args, emit_budget_check) {
+                std::process::exit(1);
+            }
+        }
+        Some(arg) if arg == "--diff" => {
+            if args.len() < 5 {
+                eprintln!("--diff requires <old.smir.json> <new.smir.json> <function>");
+                std::process::exit(1);
+            }
+            if let Err(e) = emit_function_diff(&args[2], &args[3], &args[4]) {
+                eprintln!("{}", e);
+                std::process::exit(1);
+            }
+        }
         Some(arg) if arg == "--dot" => {
             args.remove(1);
             stable_mir_driver(&args, emit_dotfile)
@@ -21,6 +112,54 @@ fn main() {
             args.remove(1);
             stable_mir_driver(&args, emit_d2file)
         }
-        Some(_other) => stable_mir_driver(&args, emit_smir), // backward compatibility
+        Some(arg) if arg == "--svg" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_svgfile)
+        }
+        Some(arg) if arg == "--annotate" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_annotated_sources)
+        }
+        Some(arg) if arg == "--summary" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_summary_cards)
+        }
+        Some(arg) if arg == "--copies-md" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_copies_markdown)
+        }
+        Some(arg) if arg == "--deps" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_dependency_graph)
+        }
+        Some(arg) if arg == "--instrument" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_counter_harness)
+        }
+        Some(arg) if arg == "--sarif" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_sarif)
+        }
+        Some(arg) if arg == "--panics-md" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_panics_markdown)
+        }
+        Some(arg) if arg == "--panics-json" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_panics_json)
+        }
+        Some(arg) if arg == "--span-report" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_span_report)
+        }
+        Some(arg) if arg == "--arith-csv" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_arith_csv)
+        }
+        Some(arg) if arg == "--arith-md" => {
+            args.remove(1);
+            stable_mir_driver(&args, emit_arith_markdown)
+        }
+        Some(_other) => stable_mir_driver(&args, emit_json), // backward compatibility
     }
 }
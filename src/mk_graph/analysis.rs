@@ -0,0 +1,167 @@
+//! Derived facts about the crate's functions for the JSON output.
+//!
+//! The graph outputs and summary cards show block roles and function
+//! properties to readers; with `--analyze` (`ANALYZE` set) the same facts are
+//! written into `*.smir.json` under `analysis`, as a [`BlockAnalysis`] per
+//! block and a list of [`FunctionProperty`]s per function, so non-visual
+//! consumers do not have to derive them again from the MIR.
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Operand, Terminator, TerminatorKind};
+
+use super::cfg::{has_cycle, irreducible_regions, successors};
+use super::context::GraphContext;
+use super::limits::LabelLimits;
+use super::messages::msg;
+use super::skeleton::cleanup_blocks;
+use super::ubchecks::ub_check_switches;
+use super::util::CallCategory;
+use crate::printer::{BlockAnalysis, BlockRole, FunctionAnalysis, FunctionProperty, SmirJson};
+use crate::MonoItemKind;
+
+/// Whether `ANALYZE` is set
+pub fn analyze_enabled() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("ANALYZE").is_ok())
+}
+
+impl BlockRole {
+    /// Role of the block ending in `term`
+    pub fn of(term: &Terminator) -> Self {
+        use TerminatorKind::*;
+        match &term.kind {
+            Goto { .. } => BlockRole::Goto,
+            SwitchInt { .. } => BlockRole::SwitchInt,
+            Resume {} => BlockRole::Resume,
+            Abort {} => BlockRole::Abort,
+            Return {} => BlockRole::Return,
+            Unreachable {} => BlockRole::Unreachable,
+            Drop { .. } => BlockRole::Drop,
+            Call { .. } => BlockRole::Call,
+            Assert { .. } => BlockRole::Assert,
+            InlineAsm { .. } => BlockRole::InlineAsm,
+        }
+    }
+
+    /// The role as written in the outputs (`goto`, `switch_int`, ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            BlockRole::Goto => "goto",
+            BlockRole::SwitchInt => "switch_int",
+            BlockRole::Resume => "resume",
+            BlockRole::Abort => "abort",
+            BlockRole::Return => "return",
+            BlockRole::Unreachable => "unreachable",
+            BlockRole::Drop => "drop",
+            BlockRole::Call => "call",
+            BlockRole::Assert => "assert",
+            BlockRole::InlineAsm => "inline_asm",
+        }
+    }
+}
+
+impl FunctionProperty {
+    /// The property as written in the outputs (`recursive`, `loops`, ...)
+    pub fn name(self) -> &'static str {
+        match self {
+            FunctionProperty::Recursive => "recursive",
+            FunctionProperty::Loops => "loops",
+            FunctionProperty::Irreducible => "irreducible",
+            FunctionProperty::IndirectCalls => "indirect_calls",
+            FunctionProperty::MayPanic => "may_panic",
+        }
+    }
+
+    /// The property in the language of `MIR_LANG`, for readers
+    pub fn label(self) -> &'static str {
+        msg(match self {
+            FunctionProperty::Recursive => "property.recursive",
+            FunctionProperty::Loops => "property.loops",
+            FunctionProperty::Irreducible => "property.irreducible",
+            FunctionProperty::IndirectCalls => "property.indirect_calls",
+            FunctionProperty::MayPanic => "property.may_panic",
+        })
+    }
+}
+
+/// Block roles and properties of all functions with a body, by symbol name
+pub fn analyze(smir: &SmirJson) -> Vec<FunctionAnalysis> {
+    let ctx = GraphContext::with_limits(smir, LabelLimits::for_format("json"));
+    smir.items
+        .iter()
+        .filter_map(|item| match &item.mono_item_kind {
+            MonoItemKind::MonoItemFn {
+                body: Some(body), ..
+            } => Some(FunctionAnalysis {
+                symbol: item.symbol_name.clone(),
+                properties: function_properties(&ctx, &item.symbol_name, body),
+                blocks: block_analysis(&ctx, body),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Properties of the function with symbol name `symbol`: the call-related
+/// ones in order of the first block showing them, then the structural ones
+pub fn function_properties(ctx: &GraphContext, symbol: &str, body: &Body) -> Vec<FunctionProperty> {
+    let mut properties = vec![];
+    let mut add = |p: FunctionProperty| {
+        if !properties.contains(&p) {
+            properties.push(p);
+        }
+    };
+    let mut may_panic = false;
+    for block in &body.blocks {
+        match &block.terminator.kind {
+            TerminatorKind::Call { func, .. } => {
+                if matches!(func, Operand::Copy(_) | Operand::Move(_)) {
+                    add(FunctionProperty::IndirectCalls);
+                }
+                may_panic |= ctx.call_category(func) == Some(CallCategory::Panic);
+                if ctx
+                    .resolve_call_target(func)
+                    .is_some_and(|callee| ctx.recursion_note(symbol, &callee).is_some())
+                {
+                    add(FunctionProperty::Recursive);
+                }
+            }
+            TerminatorKind::Assert { .. } => may_panic = true,
+            _ => {}
+        }
+    }
+    let successors = successors(body);
+    if has_cycle(&successors) {
+        add(FunctionProperty::Loops);
+    }
+    if !irreducible_regions(&successors).is_empty() {
+        add(FunctionProperty::Irreducible);
+    }
+    if may_panic {
+        add(FunctionProperty::MayPanic);
+    }
+    properties
+}
+
+/// Role and flags of every block of `body`
+fn block_analysis(ctx: &GraphContext, body: &Body) -> Vec<BlockAnalysis> {
+    let cleanup = cleanup_blocks(body);
+    let switches = ub_check_switches(body);
+    body.blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, block)| BlockAnalysis {
+            role: BlockRole::of(&block.terminator),
+            cleanup: cleanup[idx],
+            panics: match &block.terminator.kind {
+                TerminatorKind::Call { func, .. } => {
+                    ctx.call_category(func) == Some(CallCategory::Panic)
+                }
+                TerminatorKind::Assert { .. } => true,
+                _ => false,
+            },
+            ub_check: switches.iter().any(|s| s.enabled == idx),
+        })
+        .collect()
+}
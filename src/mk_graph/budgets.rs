@@ -0,0 +1,414 @@
+//! Size and panic budgets for CI.
+//!
+//! `--check-budgets <file>` compares the crate's MIR against the budgets in
+//! a small TOML file and exits with status 1 when one is exceeded, so MIR
+//! growth and new panic paths fail a build like a failing test:
+//!
+//! ```toml
+//! [crate]                       # totals over all function bodies
+//! max_blocks = 5000
+//! max_statements = 20000
+//! must_not_panic = ["parse_header", "crc::update"]
+//!
+//! [functions]                   # every function
+//! max_blocks = 200
+//!
+//! [fn."codec::decode"]          # one function, overriding [functions]
+//! max_blocks = 400
+//! must_not_panic = true
+//! ```
+//!
+//! Functions are named by their path or a suffix of it (`crc::update`
+//! matches `mycrate::crc::update`). Only functions whose bodies lie in the
+//! crate's own source files are checked; `std` and `core` code instantiated
+//! for the crate is not its MIR to budget. Only integers, booleans and
+//! one-line string arrays are understood; a `#` starts a comment at the
+//! start of a line or after whitespace, so `main::{closure#0}` is a name.
+
+use std::collections::BTreeMap;
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::output::panics::{panic_sites, PanicSite};
+use crate::mk_graph::util::strip_comment;
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+/// Budgets of one scope; unset limits are not checked
+#[derive(Clone, Debug, Default)]
+pub struct Limits {
+    pub max_blocks: Option<usize>,
+    pub max_statements: Option<usize>,
+    pub must_not_panic: bool,
+}
+
+/// The budgets read from a `--check-budgets` file
+#[derive(Debug, Default)]
+pub struct Budgets {
+    /// Totals over all function bodies of the crate
+    pub krate: Limits,
+    /// Functions listed in `[crate] must_not_panic`
+    pub no_panic: Vec<String>,
+    /// Limits for every function
+    pub functions: Limits,
+    /// Limits for single functions, by name as written in the file
+    pub by_function: Vec<(String, Limits)>,
+}
+
+/// A budget that the crate exceeds
+pub struct Violation {
+    /// `crate` or the function's name
+    pub scope: String,
+    pub message: String,
+}
+
+impl Budgets {
+    /// Read the budgets file at `path`
+    pub fn from_file(path: &str) -> Self {
+        let text = std::fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read budgets file {}: {}", path, e));
+        Self::parse(&text).unwrap_or_else(|e| panic!("Budgets file {}: {}", path, e))
+    }
+
+    /// Parse the budgets file format, failing on the first malformed line
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut budgets = Self::default();
+        let mut section = String::new();
+        for (lineno, raw) in text.lines().enumerate() {
+            let err = |what: &str| format!("line {}: {}: {}", lineno + 1, what, raw.trim());
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                match section.strip_prefix("fn.") {
+                    Some(function) => budgets.by_function.push((
+                        function.trim().trim_matches('"').to_string(),
+                        Limits::default(),
+                    )),
+                    None if section == "crate" || section == "functions" => {}
+                    None => {
+                        return Err(err(
+                            "unknown section (expected crate, functions, or fn.<name>)",
+                        ))
+                    }
+                }
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                return Err(err("expected `key = value`"));
+            };
+            let (key, value) = (key.trim(), value.trim());
+            let limits = match section.as_str() {
+                "crate" => &mut budgets.krate,
+                "functions" => &mut budgets.functions,
+                _ => match budgets.by_function.last_mut() {
+                    Some((_, limits)) => limits,
+                    None => return Err(err("key outside of a section")),
+                },
+            };
+            match key {
+                "max_blocks" | "max_statements" => {
+                    let n = value.parse().map_err(|_| err("expected a number"))?;
+                    if key == "max_blocks" {
+                        limits.max_blocks = Some(n);
+                    } else {
+                        limits.max_statements = Some(n);
+                    }
+                }
+                "must_not_panic" if section == "crate" => {
+                    budgets.no_panic = string_list(value).ok_or_else(|| {
+                        err("expected a list of function names, e.g. [\"parse\", \"crc::update\"]")
+                    })?;
+                }
+                "must_not_panic" => {
+                    limits.must_not_panic =
+                        value.parse().map_err(|_| err("expected true or false"))?;
+                }
+                _ => {
+                    return Err(err(
+                        "unknown key (expected max_blocks, max_statements, or must_not_panic)",
+                    ))
+                }
+            }
+        }
+        Ok(budgets)
+    }
+
+    /// Limits for the function named `name`: `[functions]`, overridden by
+    /// the `[fn.<name>]` sections matching it and the crate's
+    /// `must_not_panic` list
+    fn limits_of(&self, name: &str) -> Limits {
+        let mut limits = self.functions.clone();
+        for (_, own) in self.by_function.iter().filter(|(f, _)| matches(name, f)) {
+            limits.max_blocks = own.max_blocks.or(limits.max_blocks);
+            limits.max_statements = own.max_statements.or(limits.max_statements);
+            limits.must_not_panic |= own.must_not_panic;
+        }
+        limits.must_not_panic |= self.no_panic.iter().any(|f| matches(name, f));
+        limits
+    }
+
+    /// The budgets `smir` exceeds, crate totals first, then by function
+    pub fn check(&self, smir: &SmirJson) -> Vec<Violation> {
+        let ctx = GraphContext::from_smir(smir);
+        let local_files: Vec<&str> = smir
+            .source_files
+            .iter()
+            .filter(|f| f.krate == smir.name)
+            .map(|f| f.file.as_str())
+            .collect();
+        let bodies: Vec<(&str, usize, usize)> = smir
+            .items
+            .iter()
+            .filter_map(|item| match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    name,
+                    body: Some(body),
+                    ..
+                } if ctx
+                    .source_data(&body.span)
+                    .is_some_and(|(file, ..)| local_files.contains(&file.as_str())) =>
+                {
+                    Some((
+                        name.as_str(),
+                        body.blocks.len(),
+                        body.blocks.iter().map(|b| b.statements.len()).sum(),
+                    ))
+                }
+                _ => None,
+            })
+            .collect();
+        let mut sites: BTreeMap<String, Vec<PanicSite>> = BTreeMap::new();
+        for site in panic_sites(smir, "md") {
+            sites.entry(site.function.clone()).or_default().push(site);
+        }
+
+        let mut violations = vec![];
+        let total_blocks: usize = bodies.iter().map(|(_, b, _)| b).sum();
+        let total_statements: usize = bodies.iter().map(|(_, _, s)| s).sum();
+        exceeded(
+            &mut violations,
+            "crate",
+            "blocks",
+            total_blocks,
+            self.krate.max_blocks,
+        );
+        exceeded(
+            &mut violations,
+            "crate",
+            "statements",
+            total_statements,
+            self.krate.max_statements,
+        );
+
+        for (name, blocks, statements) in &bodies {
+            let limits = self.limits_of(name);
+            exceeded(&mut violations, name, "blocks", *blocks, limits.max_blocks);
+            exceeded(
+                &mut violations,
+                name,
+                "statements",
+                *statements,
+                limits.max_statements,
+            );
+            if limits.must_not_panic {
+                if let Some(sites) = sites.get(*name) {
+                    let listed: Vec<String> = sites
+                        .iter()
+                        .map(|s| format!("bb{} {}", s.block, s.kind))
+                        .collect();
+                    violations.push(Violation {
+                        scope: name.to_string(),
+                        message: format!(
+                            "must not panic, has {} panic sites ({})",
+                            sites.len(),
+                            listed.join(", ")
+                        ),
+                    });
+                }
+            }
+        }
+
+        // a misspelled name would silently check nothing
+        let names = self
+            .by_function
+            .iter()
+            .map(|(f, _)| f)
+            .chain(&self.no_panic);
+        for pattern in names {
+            if !bodies.iter().any(|(name, ..)| matches(name, pattern)) {
+                violations.push(Violation {
+                    scope: pattern.clone(),
+                    message: "has a budget but matches no function of the crate".to_string(),
+                });
+            }
+        }
+        violations
+    }
+}
+
+/// Whether function `name` is the function `pattern` names: the same path,
+/// or a path ending in `::pattern`
+fn matches(name: &str, pattern: &str) -> bool {
+    name == pattern
+        || name
+            .strip_suffix(pattern)
+            .is_some_and(|prefix| prefix.ends_with("::"))
+}
+
+fn exceeded(
+    violations: &mut Vec<Violation>,
+    scope: &str,
+    what: &str,
+    value: usize,
+    max: Option<usize>,
+) {
+    if let Some(max) = max.filter(|max| value > *max) {
+        violations.push(Violation {
+            scope: scope.to_string(),
+            message: format!("{} {} > max_{} {}", value, what, what, max),
+        });
+    }
+}
+
+/// The strings of a one-line array such as `["a", "b"]`; commas inside the
+/// strings (`"convert::<A, B>"`) are part of them
+fn string_list(value: &str) -> Option<Vec<String>> {
+    let mut rest = value.strip_prefix('[')?.strip_suffix(']')?.trim();
+    let mut strings = vec![];
+    while !rest.is_empty() {
+        let (s, after) = basic_string(rest.strip_prefix('"')?)?;
+        strings.push(s);
+        let after = after.trim_start();
+        rest = match after.strip_prefix(',') {
+            Some(after) => after.trim_start(),
+            None if after.is_empty() => after,
+            None => return None,
+        };
+    }
+    Some(strings)
+}
+
+/// The contents of a string whose opening quote precedes `text`, with `\"`
+/// and `\\` unescaped, and the text after its closing quote
+fn basic_string(text: &str) -> Option<(String, &str)> {
+    let mut s = String::new();
+    let mut chars = text.char_indices();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '"' => return Some((s, &text[i + 1..])),
+            '\\' => match chars.next()? {
+                (_, c @ ('"' | '\\')) => s.push(c),
+                _ => return None,
+            },
+            c => s.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_all_sections() {
+        let budgets = Budgets::parse(
+            "[crate]   # totals\n\
+             max_blocks = 5000\n\
+             must_not_panic = [\"parse\", \"crc::update\"]\n\
+             \n\
+             [functions]\n\
+             max_statements = 80\n\
+             \n\
+             [fn.\"main::{closure#0}\"]\n\
+             max_blocks = 4\n\
+             must_not_panic = true\n",
+        )
+        .unwrap();
+        assert_eq!(budgets.krate.max_blocks, Some(5000));
+        assert_eq!(budgets.krate.max_statements, None);
+        assert_eq!(budgets.no_panic, ["parse", "crc::update"]);
+        assert_eq!(budgets.functions.max_statements, Some(80));
+        let (name, limits) = &budgets.by_function[0];
+        assert_eq!(name, "main::{closure#0}");
+        assert_eq!(limits.max_blocks, Some(4));
+        assert!(limits.must_not_panic);
+    }
+
+    #[test]
+    fn parse_names_the_malformed_line() {
+        for (text, error) in [
+            ("[crates]", "line 1: unknown section"),
+            ("max_blocks = 1", "line 1: key outside of a section"),
+            ("[crate]\nmax_blocks = many", "line 2: expected a number"),
+            ("[crate]\nmax_block = 1", "line 2: unknown key"),
+            ("[functions]\nmust_not_panic = yes", "line 2: expected true"),
+            ("[crate]\nmust_not_panic = parse", "line 2: expected a list"),
+            ("[crate]\nmax_blocks", "line 2: expected `key = value`"),
+        ] {
+            let message = Budgets::parse(text).unwrap_err();
+            assert!(message.starts_with(error), "{:?}: {}", text, message);
+        }
+    }
+
+    #[test]
+    fn limits_of_overrides_functions_with_matching_sections() {
+        let budgets = Budgets::parse(
+            "[crate]\n\
+             must_not_panic = [\"crc::update\"]\n\
+             [functions]\n\
+             max_blocks = 200\n\
+             max_statements = 900\n\
+             [fn.\"codec::decode\"]\n\
+             max_blocks = 400\n",
+        )
+        .unwrap();
+        let decode = budgets.limits_of("app::codec::decode");
+        assert_eq!(decode.max_blocks, Some(400));
+        assert_eq!(decode.max_statements, Some(900));
+        assert!(!decode.must_not_panic);
+        let update = budgets.limits_of("app::crc::update");
+        assert_eq!(update.max_blocks, Some(200));
+        assert!(update.must_not_panic);
+    }
+
+    #[test]
+    fn string_list_reads_quoted_strings() {
+        assert_eq!(string_list("[]"), Some(vec![]));
+        assert_eq!(
+            string_list(r#"[ "a" , "convert::<A, B>" ]"#),
+            Some(vec!["a".to_string(), "convert::<A, B>".to_string()])
+        );
+        assert_eq!(string_list(r#"["a\"b"]"#), Some(vec![r#"a"b"#.to_string()]));
+        assert_eq!(string_list(r#"["a\\"]"#), Some(vec![r"a\".to_string()]));
+        assert_eq!(string_list(r#"["a",]"#), Some(vec!["a".to_string()]));
+    }
+
+    #[test]
+    fn string_list_rejects_malformed_arrays() {
+        for value in [
+            r#""a""#,
+            r#"["a""#,
+            r#"["a" "b"]"#,
+            r#"[a]"#,
+            r#"["a]"#,
+            r#"["a\n"]"#,
+        ] {
+            assert_eq!(string_list(value), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn matches_whole_trailing_path_segments() {
+        assert!(matches("app::crc::update", "app::crc::update"));
+        assert!(matches("app::crc::update", "crc::update"));
+        assert!(matches("app::crc::update", "update"));
+        assert!(matches("<app::Crc as app::Hasher>::update", "update"));
+        assert!(!matches("app::crc::update", "rc::update"));
+        assert!(!matches("app::crc::try_update", "update"));
+        assert!(!matches("app::crc::update", "crc"));
+        assert!(!matches("update", "crc::update"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Layout checks of transmutes and raw pointer casts.
+//!
+//! A `transmute` reinterprets bytes and a pointer cast reinterprets the
+//! pointee, so both are only sound if the layouts involved agree. Using the
+//! layouts recorded in the type metadata, [`cast_findings`] flags
+//! - `cast-size`: a transmute between types of different sizes,
+//! - `cast-align`: a pointer cast (or pointer transmute) to a pointee with a
+//!   stricter alignment than the source pointee,
+//! - `cast-fat-to-thin`: a cast from a pointer to an unsized pointee (slice,
+//!   `str`, trait object) to a thin pointer, dropping the length or vtable.
+//!
+//! Types without a recorded layout are not checked. Primitive alignments are
+//! taken to equal their sizes, which holds on the common 64-bit targets.
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, CastKind, Rvalue, StatementKind};
+use stable_mir::ty::{RigidTy, Ty};
+
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+use super::taint::Finding;
+
+/// Layout mismatches of the transmutes and pointer casts in `body`
+pub fn cast_findings(body: &Body, ctx: &GraphContext) -> Vec<Finding> {
+    let mut findings = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        for stmt in &block.statements {
+            let StatementKind::Assign(_, Rvalue::Cast(kind, op, target)) = &stmt.kind else {
+                continue;
+            };
+            if !matches!(kind, CastKind::Transmute | CastKind::PtrToPtr) {
+                continue;
+            }
+            let Ok(source) = op.ty(body.locals()) else {
+                continue;
+            };
+            let mut flag = |rule: &'static str, message: String| {
+                findings.push(Finding {
+                    rule,
+                    message,
+                    block: idx,
+                    span: stmt.span,
+                })
+            };
+            let name = |ty: Ty| ctx.types.get_name(ty);
+
+            if matches!(kind, CastKind::Transmute) {
+                if let (Some(from), Some(to)) =
+                    (ctx.types.size_of(source), ctx.types.size_of(*target))
+                {
+                    if from != to {
+                        flag(
+                            "cast-size",
+                            fill(
+                                msg("casts.size"),
+                                &[&name(source), &from, &name(*target), &to],
+                            ),
+                        );
+                    }
+                }
+            }
+            let (Some(from), Some(to)) = (pointee(source), pointee(*target)) else {
+                continue;
+            };
+            if is_unsized(from) && !is_unsized(to) {
+                flag(
+                    "cast-fat-to-thin",
+                    fill(msg("casts.fat_to_thin"), &[&name(source), &name(*target)]),
+                );
+            }
+            if let (Some(from_align), Some(to_align)) = (align_of(ctx, from), align_of(ctx, to)) {
+                if to_align > from_align {
+                    flag(
+                        "cast-align",
+                        fill(
+                            msg("casts.align"),
+                            &[&name(source), &from_align, &name(*target), &to_align],
+                        ),
+                    );
+                }
+            }
+        }
+    }
+    findings
+}
+
+/// Pointee of a raw pointer or reference type
+fn pointee(ty: Ty) -> Option<Ty> {
+    match ty.kind().rigid()? {
+        RigidTy::RawPtr(pointee, _) | RigidTy::Ref(_, pointee, _) => Some(*pointee),
+        _ => None,
+    }
+}
+
+/// Whether pointers to `ty` are fat
+fn is_unsized(ty: Ty) -> bool {
+    matches!(
+        ty.kind().rigid(),
+        Some(RigidTy::Slice(_) | RigidTy::Str | RigidTy::Dynamic(..))
+    )
+}
+
+/// Alignment of `ty` in bytes, from its layout or (for primitives) its size
+fn align_of(ctx: &GraphContext, ty: Ty) -> Option<usize> {
+    match ctx.types.get_layout(ty) {
+        Some(layout) => Some(layout.align),
+        None => ctx.types.size_of(ty),
+    }
+}
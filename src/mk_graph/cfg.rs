@@ -0,0 +1,241 @@
+//! Structural properties of a body's control-flow graph.
+//!
+//! Only regular control flow is considered: cleanup (unwind) edges are left
+//! out, since they do not form the loops of the source program.
+
+use crate::compat::stable_mir;
+use stable_mir::mir::Body;
+
+use super::util::{terminator_edges, EdgeKind};
+
+/// Successors of every block over the regular (non-cleanup) control flow
+pub fn successors(body: &Body) -> Vec<Vec<usize>> {
+    body.blocks
+        .iter()
+        .map(|b| {
+            terminator_edges(&b.terminator)
+                .into_iter()
+                .filter(|e| e.kind != EdgeKind::Cleanup)
+                .map(|e| e.target)
+                .collect()
+        })
+        .collect()
+}
+
+/// The irreducible regions of a control-flow graph, as sorted block lists.
+///
+/// A CFG is reducible when every loop has a single entry block (its header)
+/// that dominates the rest of the loop. Structured source code always lowers
+/// to a reducible CFG, and many consumers (loop-based lowerings to other IRs,
+/// structural analyses) rely on it.
+///
+/// Loops are found as in Steensgaard's loop nesting forest: every cyclic
+/// strongly connected component is a loop, its entries are the blocks with a
+/// predecessor outside it, and the loops nested in it are the components that
+/// remain once the edges into its entries are removed. A region is a loop
+/// with more than one entry, so a reducible loop around an irreducible one is
+/// not reported, only the inner, multi-entry part.
+pub fn irreducible_regions(successors: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let n = successors.len();
+    if n == 0 {
+        return vec![];
+    }
+    let succ = |b: usize| successors[b].iter().copied().filter(move |s| *s < n);
+    let live = reachable(n, 0, |b| succ(b).collect());
+    let mut preds: Vec<Vec<usize>> = vec![vec![]; n];
+    for b in (0..n).filter(|b| live[*b]) {
+        for s in succ(b) {
+            preds[s].push(b);
+        }
+    }
+
+    let mut regions = vec![];
+    // blocks to search for loops, and the entries of the loop they form,
+    // whose incoming edges from within are left out
+    let mut work: Vec<(Vec<usize>, Vec<usize>)> =
+        vec![((0..n).filter(|b| live[*b]).collect(), vec![])];
+    while let Some((blocks, entries)) = work.pop() {
+        let mut inside = vec![false; n];
+        for &b in &blocks {
+            inside[b] = true;
+        }
+        let inner = |b: usize| -> Vec<usize> {
+            succ(b)
+                .filter(|s| inside[*s] && !entries.contains(s))
+                .collect()
+        };
+        for component in components(n, &blocks, inner) {
+            let cyclic = component.len() > 1 || inner(component[0]).contains(&component[0]);
+            if !cyclic {
+                continue;
+            }
+            let mut in_component = vec![false; n];
+            for &b in &component {
+                in_component[b] = true;
+            }
+            let component_entries: Vec<usize> = component
+                .iter()
+                .copied()
+                .filter(|b| *b == 0 || preds[*b].iter().any(|p| !in_component[*p]))
+                .collect();
+            if component_entries.len() > 1 {
+                regions.push(component.clone());
+            }
+            work.push((component, component_entries));
+        }
+    }
+    regions.sort();
+    regions
+}
+
+/// The strongly connected components of the subgraph on `blocks` (Kosaraju),
+/// each sorted; `next` gives the successors of a block within the subgraph
+fn components(n: usize, blocks: &[usize], next: impl Fn(usize) -> Vec<usize>) -> Vec<Vec<usize>> {
+    let mut preds: Vec<Vec<usize>> = vec![vec![]; n];
+    for &b in blocks {
+        for s in next(b) {
+            preds[s].push(b);
+        }
+    }
+    // postorder of a depth-first search over the subgraph
+    let mut seen = vec![false; n];
+    let mut postorder = vec![];
+    for &root in blocks {
+        if seen[root] {
+            continue;
+        }
+        seen[root] = true;
+        let mut stack = vec![(root, next(root), 0usize)];
+        while let Some((block, succs, i)) = stack.last_mut() {
+            match succs.get(*i) {
+                Some(&s) => {
+                    *i += 1;
+                    if !seen[s] {
+                        seen[s] = true;
+                        let succs = next(s);
+                        stack.push((s, succs, 0));
+                    }
+                }
+                None => {
+                    postorder.push(*block);
+                    stack.pop();
+                }
+            }
+        }
+    }
+    // blocks reaching each root in reverse postorder form its component
+    let mut assigned = vec![false; n];
+    let mut result = vec![];
+    for &root in postorder.iter().rev() {
+        if assigned[root] {
+            continue;
+        }
+        assigned[root] = true;
+        let mut component = vec![root];
+        let mut stack = vec![root];
+        while let Some(b) = stack.pop() {
+            for &p in &preds[b] {
+                if !assigned[p] {
+                    assigned[p] = true;
+                    component.push(p);
+                    stack.push(p);
+                }
+            }
+        }
+        component.sort_unstable();
+        result.push(component);
+    }
+    result
+}
+
+/// Whether the control-flow graph reachable from `bb0` has a cycle
+pub fn has_cycle(successors: &[Vec<usize>]) -> bool {
+    if successors.is_empty() {
+        return false;
+    }
+    // 0 = unvisited, 1 = on the DFS stack, 2 = done
+    let mut state = vec![0u8; successors.len()];
+    let mut stack = vec![(0usize, 0usize)];
+    state[0] = 1;
+    while let Some((block, next)) = stack.pop() {
+        match successors[block].get(next) {
+            Some(&succ) if succ < successors.len() => {
+                stack.push((block, next + 1));
+                match state[succ] {
+                    0 => {
+                        state[succ] = 1;
+                        stack.push((succ, 0));
+                    }
+                    1 => return true,
+                    _ => {}
+                }
+            }
+            Some(_) => stack.push((block, next + 1)),
+            None => state[block] = 2,
+        }
+    }
+    false
+}
+
+fn reachable(n: usize, start: usize, next: impl Fn(usize) -> Vec<usize>) -> Vec<bool> {
+    let mut seen = vec![false; n];
+    let mut stack = vec![start];
+    seen[start] = true;
+    while let Some(b) = stack.pop() {
+        for s in next(b) {
+            if !seen[s] {
+                seen[s] = true;
+                stack.push(s);
+            }
+        }
+    }
+    seen
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn structured_loops_are_reducible() {
+        // while loop
+        assert!(irreducible_regions(&[vec![1], vec![2, 3], vec![1], vec![]]).is_empty());
+        // nested loops and a self loop
+        let nested = [
+            vec![1],
+            vec![2, 4],
+            vec![3, 2],
+            vec![2, 1],
+            vec![4, 5],
+            vec![],
+        ];
+        assert!(irreducible_regions(&nested).is_empty());
+        assert!(irreducible_regions(&[]).is_empty());
+    }
+
+    #[test]
+    fn two_entry_cycle_is_irreducible() {
+        assert_eq!(
+            irreducible_regions(&[vec![1, 2], vec![2], vec![1]]),
+            [vec![1, 2]]
+        );
+    }
+
+    #[test]
+    fn region_is_the_multi_entry_part_of_a_loop() {
+        let cfg = [vec![1], vec![2, 3], vec![3, 4], vec![2], vec![1, 5], vec![]];
+        assert_eq!(irreducible_regions(&cfg), [vec![2, 3]]);
+    }
+
+    #[test]
+    fn irreducible_loops_inside_irreducible_loops() {
+        // {1, 2, 3, 4} is entered at 1 and 2; inside it, {3, 4} at 3 and 4
+        let cfg = [vec![1, 2], vec![2], vec![1, 3, 4], vec![4], vec![3, 1]];
+        assert_eq!(irreducible_regions(&cfg), [vec![1, 2, 3, 4], vec![3, 4]]);
+    }
+
+    #[test]
+    fn unreachable_blocks_are_ignored() {
+        assert!(irreducible_regions(&[vec![], vec![2], vec![1], vec![1]]).is_empty());
+    }
+}
@@ -0,0 +1,341 @@
+//! One-line summaries of what a basic block does.
+//!
+//! [`block_summary`] groups consecutive statements into operation clusters
+//! (arithmetic, borrows, aggregate construction, casts, copies), keeps the
+//! clusters with the most statements, and describes them together with the
+//! terminator: "computes `a * b + c` with overflow checks, then calls `log`".
+//! Arithmetic clusters are folded back into expressions over the source-level
+//! names of the locals, through the temporaries MIR introduces for them.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    AggregateKind, AssertMessage, BasicBlock, BinOp, Body, Local, Operand, Place, ProjectionElem,
+    Rvalue, StatementKind, TerminatorKind, UnOp, VarDebugInfoContents,
+};
+use stable_mir::CrateDef;
+
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+use super::util::project;
+
+/// Most clusters a summary describes besides the terminator
+const MAX_CLUSTERS: usize = 2;
+
+/// Consecutive statements doing the same kind of operation
+enum Cluster {
+    /// Arithmetic: the expressions whose results are not consumed within the
+    /// cluster, and whether any operation is overflow-checked
+    Compute {
+        exprs: Vec<(Local, String)>,
+        consumed: HashSet<Local>,
+        checked: bool,
+    },
+    Borrow(Vec<String>),
+    Build(Vec<String>),
+    Cast(Vec<String>),
+    Copy(usize),
+    Discriminant(String),
+}
+
+impl Cluster {
+    fn same_kind(&self, other: &Cluster) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+
+    fn describe(&self) -> String {
+        let quoted = |items: &[String]| {
+            let mut seen: Vec<String> = vec![];
+            for item in items {
+                let item = format!("`{}`", item);
+                if !seen.contains(&item) {
+                    seen.push(item);
+                }
+            }
+            seen.join(", ")
+        };
+        match self {
+            Cluster::Compute {
+                exprs,
+                consumed,
+                checked,
+            } => {
+                let roots: Vec<String> = exprs
+                    .iter()
+                    .filter(|(local, _)| !consumed.contains(local))
+                    .map(|(_, expr)| expr.clone())
+                    .collect();
+                let template = if *checked {
+                    msg("summary.computes_checked")
+                } else {
+                    msg("summary.computes")
+                };
+                fill(template, &[&quoted(&roots)])
+            }
+            Cluster::Borrow(places) => fill(msg("summary.borrows"), &[&quoted(places)]),
+            Cluster::Build(kinds) => fill(msg("summary.builds"), &[&quoted(kinds)]),
+            Cluster::Cast(operands) => fill(msg("summary.casts"), &[&quoted(operands)]),
+            Cluster::Copy(1) => msg("summary.copies_one").to_string(),
+            Cluster::Copy(n) => fill(msg("summary.copies"), &[n]),
+            Cluster::Discriminant(place) => {
+                fill(msg("summary.discriminant"), &[&format!("`{}`", place)])
+            }
+        }
+    }
+}
+
+/// Summary of block `idx` of `body`, or `None` for blocks that only jump on
+pub fn block_summary(ctx: &GraphContext, body: &Body, idx: usize) -> Option<String> {
+    let block = body.blocks.get(idx)?;
+    let names = Names::new(body);
+    // clusters with their statement counts, in block order
+    let mut clusters: Vec<(Cluster, usize)> = vec![];
+    // arithmetic results by local, carried across copies of them
+    let mut exprs: HashMap<Local, String> = HashMap::new();
+
+    for stmt in &block.statements {
+        let StatementKind::Assign(dest, rvalue) = &stmt.kind else {
+            continue;
+        };
+        let next = match rvalue {
+            Rvalue::BinaryOp(op, a, b) | Rvalue::CheckedBinaryOp(op, a, b) => {
+                let checked = matches!(rvalue, Rvalue::CheckedBinaryOp(..));
+                let mut used = vec![];
+                let expr = format!(
+                    "{} {} {}",
+                    names.operand(ctx, a, &exprs, &mut used),
+                    binop_symbol(*op),
+                    names.operand(ctx, b, &exprs, &mut used)
+                );
+                exprs.insert(dest.local, expr.clone());
+                Cluster::Compute {
+                    exprs: vec![(dest.local, expr)],
+                    consumed: used.into_iter().collect(),
+                    checked,
+                }
+            }
+            Rvalue::UnaryOp(op, a) => {
+                let mut used = vec![];
+                let symbol = match op {
+                    UnOp::Not => "!",
+                    UnOp::Neg => "-",
+                    UnOp::PtrMetadata => "metadata of ",
+                };
+                let expr = format!("{}{}", symbol, names.operand(ctx, a, &exprs, &mut used));
+                exprs.insert(dest.local, expr.clone());
+                Cluster::Compute {
+                    exprs: vec![(dest.local, expr)],
+                    consumed: used.into_iter().collect(),
+                    checked: false,
+                }
+            }
+            // moving a result out of its checked pair keeps the expression
+            Rvalue::Use(Operand::Copy(src) | Operand::Move(src))
+                if exprs.contains_key(&src.local) && is_result(src) =>
+            {
+                let expr = exprs[&src.local].clone();
+                exprs.insert(dest.local, expr.clone());
+                Cluster::Compute {
+                    exprs: vec![(dest.local, expr)],
+                    consumed: HashSet::from([src.local]),
+                    checked: false,
+                }
+            }
+            Rvalue::Use(Operand::Copy(_) | Operand::Move(_)) | Rvalue::CopyForDeref(_) => {
+                Cluster::Copy(1)
+            }
+            Rvalue::Ref(_, _, place) | Rvalue::AddressOf(_, place) => {
+                Cluster::Borrow(vec![names.place(place)])
+            }
+            Rvalue::Aggregate(kind, _) => Cluster::Build(vec![aggregate_name(ctx, kind)]),
+            Rvalue::Cast(_, op, _) => {
+                Cluster::Cast(vec![names.operand(ctx, op, &exprs, &mut vec![])])
+            }
+            Rvalue::Discriminant(place) => Cluster::Discriminant(names.place(place)),
+            _ => continue,
+        };
+        match clusters.last_mut() {
+            Some((last, count)) if last.same_kind(&next) => {
+                merge(last, next);
+                *count += 1;
+            }
+            _ => clusters.push((next, 1)),
+        }
+    }
+
+    // keep the largest clusters, in block order
+    if clusters.len() > MAX_CLUSTERS {
+        let mut sizes: Vec<usize> = clusters.iter().map(|(_, n)| *n).collect();
+        sizes.sort_unstable_by(|a, b| b.cmp(a));
+        let threshold = sizes[MAX_CLUSTERS - 1];
+        let mut kept = 0;
+        clusters.retain(|(_, n)| {
+            let keep = *n >= threshold && kept < MAX_CLUSTERS;
+            kept += keep as usize;
+            keep
+        });
+    }
+    let mut parts: Vec<String> = clusters.iter().map(|(c, _)| c.describe()).collect();
+    if let Some(step) = terminator_summary(ctx, block, &names) {
+        parts.push(step);
+    }
+    let (first, rest) = parts.split_first()?;
+    Some(rest.iter().fold(first.clone(), |summary, part| {
+        fill(msg("summary.then"), &[&summary, part])
+    }))
+}
+
+/// Fold `next` into `last`, a cluster of the same kind
+fn merge(last: &mut Cluster, next: Cluster) {
+    match (last, next) {
+        (
+            Cluster::Compute {
+                exprs,
+                consumed,
+                checked,
+            },
+            Cluster::Compute {
+                exprs: more,
+                consumed: used,
+                checked: more_checked,
+            },
+        ) => {
+            exprs.extend(more);
+            consumed.extend(used);
+            *checked |= more_checked;
+        }
+        (Cluster::Borrow(items), Cluster::Borrow(more))
+        | (Cluster::Build(items), Cluster::Build(more))
+        | (Cluster::Cast(items), Cluster::Cast(more)) => items.extend(more),
+        (Cluster::Copy(n), Cluster::Copy(more)) => *n += more,
+        (last, next) => *last = next,
+    }
+}
+
+fn terminator_summary(ctx: &GraphContext, block: &BasicBlock, names: &Names) -> Option<String> {
+    use TerminatorKind::*;
+    let quoted = |s: String| format!("`{}`", s);
+    match &block.terminator.kind {
+        Call { func, .. } => {
+            let callee = ctx
+                .resolve_call_target(func)
+                .map(|name| ctx.short_fn_name(&name))
+                .unwrap_or_else(|| names.operand(ctx, func, &HashMap::new(), &mut vec![]));
+            Some(fill(msg("summary.calls"), &[&quoted(callee)]))
+        }
+        SwitchInt { discr, .. } => {
+            let on = names.operand(ctx, discr, &HashMap::new(), &mut vec![]);
+            Some(fill(msg("summary.branches"), &[&quoted(on)]))
+        }
+        Drop { place, .. } => Some(fill(msg("summary.drops"), &[&quoted(names.place(place))])),
+        // already described as the overflow checks of the computation
+        Assert {
+            msg: AssertMessage::Overflow(..) | AssertMessage::OverflowNeg(_),
+            ..
+        } => None,
+        Assert { cond, .. } => {
+            let on = names.operand(ctx, cond, &HashMap::new(), &mut vec![]);
+            Some(fill(msg("summary.asserts"), &[&quoted(on)]))
+        }
+        Return {} => Some(msg("summary.returns").to_string()),
+        Unreachable {} => Some(msg("summary.unreachable").to_string()),
+        _ => None,
+    }
+}
+
+/// Whether `place` is a local or the result field of a checked operation
+fn is_result(place: &Place) -> bool {
+    matches!(
+        place.projection.as_slice(),
+        [] | [ProjectionElem::Field(0, _)]
+    )
+}
+
+fn binop_symbol(op: BinOp) -> &'static str {
+    use BinOp::*;
+    match op {
+        Add | AddUnchecked => "+",
+        Sub | SubUnchecked => "-",
+        Mul | MulUnchecked => "*",
+        Div => "/",
+        Rem => "%",
+        BitXor => "^",
+        BitAnd => "&",
+        BitOr => "|",
+        Shl | ShlUnchecked => "<<",
+        Shr | ShrUnchecked => ">>",
+        Eq => "==",
+        Lt => "<",
+        Le => "<=",
+        Ne => "!=",
+        Ge => ">=",
+        Gt => ">",
+        Cmp => "<=>",
+        Offset => "offset",
+    }
+}
+
+/// Short name of what an aggregate builds: the ADT name, or `tuple`, `array`, ...
+pub fn aggregate_name(ctx: &GraphContext, kind: &AggregateKind) -> String {
+    match kind {
+        AggregateKind::Adt(def, ..) => ctx.short_fn_name(&def.name()),
+        AggregateKind::Array(_) => "array".to_string(),
+        AggregateKind::Tuple => "tuple".to_string(),
+        AggregateKind::Closure(..) => "closure".to_string(),
+        AggregateKind::Coroutine(..) => "coroutine".to_string(),
+        AggregateKind::RawPtr(..) => "raw pointer".to_string(),
+    }
+}
+
+/// Source-level names of the locals of a body
+struct Names(HashMap<Local, String>);
+
+impl Names {
+    fn new(body: &Body) -> Self {
+        Names(
+            body.var_debug_info
+                .iter()
+                .filter_map(|info| match &info.value {
+                    VarDebugInfoContents::Place(p) if p.projection.is_empty() => {
+                        Some((p.local, info.name.clone()))
+                    }
+                    _ => None,
+                })
+                .collect(),
+        )
+    }
+
+    /// A place, with its local by name where it has one
+    fn place(&self, place: &Place) -> String {
+        let local = match self.0.get(&place.local) {
+            Some(name) => name.clone(),
+            None => format!("_{}", place.local),
+        };
+        project(local, &place.projection)
+    }
+
+    /// An operand, with arithmetic results substituted from `exprs`; locals
+    /// substituted that way are added to `used`
+    fn operand(
+        &self,
+        ctx: &GraphContext,
+        op: &Operand,
+        exprs: &HashMap<Local, String>,
+        used: &mut Vec<Local>,
+    ) -> String {
+        match op {
+            Operand::Copy(place) | Operand::Move(place) => {
+                match exprs.get(&place.local) {
+                    // a named local stands for itself
+                    Some(expr) if is_result(place) && !self.0.contains_key(&place.local) => {
+                        used.push(place.local);
+                        format!("({})", expr)
+                    }
+                    _ => self.place(place),
+                }
+            }
+            Operand::Constant(_) => ctx.render_operand(op),
+        }
+    }
+}
@@ -1,18 +1,33 @@
 //! Graph context for rendering MIR with type and allocation information.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::compat::stable_mir;
+use stable_mir::mir::visit::{Location, MirVisitor};
 use stable_mir::mir::{
-    BorrowKind, ConstOperand, Mutability, NonDivergingIntrinsic, Operand, Rvalue, Statement,
-    StatementKind, Terminator, TerminatorKind,
+    BasicBlock, Body, BorrowKind, ConstOperand, LocalDecl, Mutability, NonDivergingIntrinsic,
+    Operand, Rvalue, Statement, StatementKind, Terminator, TerminatorKind, VarDebugInfoContents,
 };
-use stable_mir::ty::{ConstantKind, IndexedVal, MirConst, Ty};
+use stable_mir::target::Endian;
+use stable_mir::ty::{Allocation, ConstantKind, IndexedVal, MirConst, RigidTy, Span, Ty};
+use stable_mir::CrateDef;
+
+use crate::compat::spans::SourceData;
 
 use crate::printer::SmirJson;
+use crate::printer::{FnSymType, Item};
+use crate::timings::time;
+use crate::MonoItemKind;
 
-use super::index::{AllocIndex, LayoutInfo, TypeEntry, TypeIndex, TypeKind};
-use super::util::{function_string, short_fn_name, GraphLabelString};
+use super::decode::{format_scalar, read_uint, ValueDecoder, MAX_NUMERIC_BYTES};
+use super::index::{AllocIndex, AllocKind, LayoutInfo, TypeEntry, TypeIndex, TypeKind};
+use super::limits::LabelLimits;
+use super::messages::{fill, msg};
+use super::skeleton::skeleton_enabled;
+use super::util::{
+    callee_path, cast_kind_name, cast_note, function_string, last_segments, project, short_fn_name,
+    unwind_note, CallCategory, GraphLabelString,
+};
 
 // =============================================================================
 // GraphContext
@@ -23,25 +38,113 @@ pub struct GraphContext {
     pub allocs: AllocIndex,
     pub types: TypeIndex,
     pub functions: HashMap<Ty, String>,
+    /// Names of the static items in the crate (targets for use edges)
+    pub statics: HashSet<String>,
+    /// Display names of functions by symbol name: the end of their path,
+    /// disambiguated where short names collide
+    short_names: HashMap<String, String>,
+    /// For each function on a call cycle, all members of that cycle (sorted)
+    recursion: HashMap<String, Vec<String>>,
+    /// Source locations of the spans recorded in the crate, by span index
+    spans: HashMap<usize, SourceData>,
+    /// Debug names of the parameters of each function with a body, by symbol
+    /// name (empty for parameters without one)
+    params: HashMap<String, Vec<String>>,
+    /// How much of values and label lines to show
+    pub limits: LabelLimits,
+    /// Whether `UbChecks()` is true in the build the MIR comes from
+    pub ub_checks: bool,
 }
 
 impl GraphContext {
+    /// Context with the default label limits
     pub fn from_smir(smir: &SmirJson) -> Self {
-        let types = TypeIndex::from_types(&smir.types);
-        let allocs = AllocIndex::from_alloc_infos(&smir.allocs, &types);
+        Self::with_limits(smir, LabelLimits::default())
+    }
+
+    /// Context with the label limits of one output format (see
+    /// [`LabelLimits::for_format`])
+    pub fn with_limits(smir: &SmirJson, limits: LabelLimits) -> Self {
+        let types = time("index types", || {
+            TypeIndex::from_types(
+                &smir.types,
+                smir.machine.pointer_width.bytes(),
+                matches!(smir.machine.endian, Endian::Big),
+            )
+        });
+        let allocs = time("index allocs", || {
+            AllocIndex::from_alloc_infos(&smir.allocs, &types, &limits)
+        });
         let functions: HashMap<Ty, String> = smir
             .functions
             .iter()
             .map(|(k, v)| (k.0, function_string(v.clone())))
             .collect();
+        let statics: HashSet<String> = smir
+            .items
+            .iter()
+            .filter_map(|i| match &i.mono_item_kind {
+                MonoItemKind::MonoItemStatic { name, .. } => Some(name.clone()),
+                _ => None,
+            })
+            .collect();
+
+        let short_names =
+            disambiguated_short_names(smir.functions.iter().filter_map(|(k, sym)| match sym {
+                FnSymType::NormalSym(symbol) => {
+                    let (def, _) = k.0.kind().fn_def()?;
+                    Some((symbol.clone(), def.name()))
+                }
+                _ => None,
+            }));
+        let recursion = recursion_cycles(&smir.items, &functions);
+        let spans = smir.spans.iter().cloned().collect();
+        let params = smir
+            .items
+            .iter()
+            .filter_map(|i| match &i.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    body: Some(body), ..
+                } => Some((i.symbol_name.clone(), param_names(body))),
+                _ => None,
+            })
+            .collect();
 
         Self {
             allocs,
             types,
             functions,
+            statics,
+            short_names,
+            recursion,
+            spans,
+            params,
+            limits,
+            ub_checks: smir.ub_checks,
         }
     }
 
+    /// Source location of a span as `file:line`, if it was recorded
+    pub fn source_location(&self, span: &Span) -> Option<String> {
+        self.source_data(span)
+            .map(|(file, line, ..)| format!("{}:{}", file, line))
+    }
+
+    /// Recorded `(file, lo_line, lo_col, hi_line, hi_col)` of a span
+    pub fn source_data(&self, span: &Span) -> Option<&SourceData> {
+        self.spans.get(&span.to_index())
+    }
+
+    /// Short display name of a function given by symbol name or path: the
+    /// last segment of its path, or the last two when another function shares
+    /// that segment (`a::foo` vs `b::foo`)
+    pub fn short_fn_name(&self, name: &str) -> String {
+        self.short_names
+            .get(name)
+            .cloned()
+            .unwrap_or_else(|| short_fn_name(name))
+    }
+
     /// Render a constant operand with alloc information
     pub fn render_const(&self, const_: &MirConst) -> String {
         let ty = const_.ty();
@@ -55,18 +158,51 @@ impl GraphContext {
                         .provenance
                         .ptrs
                         .iter()
-                        .map(|(_offset, prov)| self.allocs.describe(prov.0.to_index() as u64))
+                        .map(|(offset, prov)| {
+                            let id = prov.0.to_index() as u64;
+                            // the pointer bytes hold the offset into the target alloc
+                            let target = read_uint(
+                                &alloc.bytes,
+                                *offset,
+                                self.types.pointer_width(),
+                                self.types.big_endian(),
+                            )
+                            .unwrap_or(0) as usize;
+                            if target == 0 {
+                                self.allocs.describe(id)
+                            } else {
+                                format!(
+                                    "{} in {}",
+                                    self.allocs.describe_ptr(id, target, &self.types),
+                                    self.allocs.describe(id)
+                                )
+                            }
+                        })
                         .collect();
                     format!("const [{}]", alloc_refs.join(", "))
                 } else {
                     // Inline constant - try to show value
                     let bytes = &alloc.bytes;
+                    let decoded =
+                        ValueDecoder::new(&self.types, bytes, &[], &self.limits).decode(ty);
                     // Convert Option<u8> to concrete bytes
                     let concrete_bytes: Vec<u8> = bytes.iter().filter_map(|&b| b).collect();
-                    if concrete_bytes.len() <= 8 && !concrete_bytes.is_empty() {
+                    if let Some(value) = decoded {
+                        match self.types.get(ty).map(|e| &e.kind) {
+                            // aggregates carry their shape (and name) in the value itself,
+                            // floats their suffix
+                            Some(TypeKind::Primitive(RigidTy::Float(_))) => {
+                                format!("const {}", value)
+                            }
+                            Some(TypeKind::Primitive(_)) => format!("const {}_{}", value, ty_name),
+                            _ => format!("const {}", value),
+                        }
+                    } else if concrete_bytes.len() <= MAX_NUMERIC_BYTES
+                        && !concrete_bytes.is_empty()
+                    {
                         format!(
                             "const {}_{}",
-                            super::util::bytes_to_u64_le(&concrete_bytes),
+                            super::util::bytes_to_u64(&concrete_bytes, self.types.big_endian()),
                             ty_name
                         )
                     } else {
@@ -78,7 +214,7 @@ impl GraphContext {
                 // Function pointers, unit type, etc.
                 if ty.kind().is_fn() {
                     if let Some(name) = self.functions.get(&ty) {
-                        format!("const fn {}", short_fn_name(name))
+                        format!("const fn {}", self.short_fn_name(name))
                     } else {
                         format!("const {}", ty_name)
                     }
@@ -92,6 +228,20 @@ impl GraphContext {
         }
     }
 
+    /// Render a `SwitchInt` branch value according to the discriminant type,
+    /// so that `-1i8` is not shown as 255 and `bool` switches read `false`/`true`
+    pub fn render_switch_value(&self, discr_ty: Option<Ty>, value: u128) -> String {
+        discr_ty
+            .and_then(|ty| match &self.types.get(ty)?.kind {
+                TypeKind::Primitive(rigid) => {
+                    let size = self.types.size_of(ty)?;
+                    format_scalar(rigid, value, size)
+                }
+                _ => None,
+            })
+            .unwrap_or_else(|| value.to_string())
+    }
+
     /// Render an operand with context
     pub fn render_operand(&self, op: &Operand) -> String {
         match op {
@@ -127,11 +277,127 @@ impl GraphContext {
         }
     }
 
+    /// Category of a call's callee (user, std, intrinsic, panic, alloc)
+    pub fn call_category(&self, func: &Operand) -> Option<CallCategory> {
+        if self
+            .resolve_call_target(func)
+            .is_some_and(|f| f.starts_with("Intr: "))
+        {
+            return Some(CallCategory::Intrinsic);
+        }
+        callee_path(func).map(|path| CallCategory::from_path(&path))
+    }
+
+    /// Names of the static items a block refers to (through alloc provenance)
+    pub fn static_refs(&self, block: &BasicBlock) -> Vec<String> {
+        let mut collector = StaticRefCollector {
+            allocs: &self.allocs,
+            names: vec![],
+        };
+        collector.visit_basic_block(block);
+        collector
+            .names
+            .into_iter()
+            .filter(|name| self.statics.contains(name))
+            .collect()
+    }
+
+    /// Label lines for a static item: its final value and initializer MIR
+    /// (only its name under `SKELETON`)
+    pub fn static_lines(
+        &self,
+        name: &str,
+        allocation: Option<&Allocation>,
+        body: Option<&Body>,
+    ) -> Vec<String> {
+        let mut lines = vec![format!("static {}", name)];
+        if skeleton_enabled() {
+            return lines;
+        }
+        if let Some(alloc) = allocation {
+            let provenance: Vec<(usize, u64)> = alloc
+                .provenance
+                .ptrs
+                .iter()
+                .map(|(offset, prov)| (*offset, prov.0.to_index() as u64))
+                .collect();
+            // the initializer's return place has the static's type
+            let value = body
+                .and_then(|b| {
+                    ValueDecoder::new(&self.types, &alloc.bytes, &provenance, &self.limits)
+                        .decode(b.ret_local().ty)
+                })
+                .unwrap_or_else(|| format!("<{} bytes>", alloc.bytes.len()));
+            lines.push(format!("= {}", value));
+        }
+        if let Some(body) = body {
+            for (idx, block) in body.blocks.iter().enumerate() {
+                lines.push(format!("bb{}:", idx));
+                for stmt in &block.statements {
+                    lines.push(format!("  {}", self.render_stmt(stmt, body.locals())));
+                }
+                lines.push(format!("  {}", self.render_terminator(&block.terminator)));
+            }
+        }
+        lines
+    }
+
+    /// Describe a call from `caller` to `callee` (both symbol names) that
+    /// closes a recursion cycle, or `None` for an ordinary call
+    pub fn recursion_note(&self, caller: &str, callee: &str) -> Option<String> {
+        let cycle = self.recursion.get(caller)?;
+        if !cycle.iter().any(|c| c == callee) {
+            return None;
+        }
+        let others: Vec<String> = cycle
+            .iter()
+            .filter(|c| *c != caller)
+            .map(|c| self.short_fn_name(c))
+            .collect();
+        if others.is_empty() {
+            Some(msg("recursion.self").to_string())
+        } else {
+            Some(fill(msg("recursion.cycle"), &[&others.join(", ")]))
+        }
+    }
+
+    /// Which argument of a call goes to which callee parameter:
+    /// `arg0 = _3 (x), arg1 = const 5_usize (limit)`, with the parameter names
+    /// taken from the callee's body where the crate has it; empty under
+    /// `SKELETON`
+    pub fn call_arg_mapping(&self, func: &Operand, args: &[Operand]) -> String {
+        if skeleton_enabled() {
+            return String::new();
+        }
+        let params = self
+            .resolve_call_target(func)
+            .and_then(|callee| self.params.get(&callee));
+        args.iter()
+            .enumerate()
+            .map(|(i, arg)| {
+                let value = match arg {
+                    Operand::Copy(place) | Operand::Move(place) => {
+                        project(format!("_{}", place.local), &place.projection)
+                    }
+                    Operand::Constant(ConstOperand { const_, .. }) => self.render_const(const_),
+                };
+                match params
+                    .and_then(|p| p.get(i))
+                    .filter(|name| !name.is_empty())
+                {
+                    Some(name) => format!("arg{} = {} ({})", i, value, name),
+                    None => format!("arg{} = {}", i, value),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
     /// Render statement with context for alloc/type information
-    pub fn render_stmt(&self, s: &Statement) -> String {
+    pub fn render_stmt(&self, s: &Statement, locals: &[LocalDecl]) -> String {
         use StatementKind::*;
         match &s.kind {
-            Assign(p, v) => format!("{} <- {}", p.label(), self.render_rvalue(v)),
+            Assign(p, v) => format!("{} <- {}", p.label(), self.render_rvalue(v, locals)),
             FakeRead(_cause, p) => format!("Fake-Read {}", p.label()),
             SetDiscriminant {
                 place,
@@ -159,7 +425,7 @@ impl GraphContext {
     }
 
     /// Render rvalue with context
-    pub fn render_rvalue(&self, v: &Rvalue) -> String {
+    pub fn render_rvalue(&self, v: &Rvalue, locals: &[LocalDecl]) -> String {
         use Rvalue::*;
         match v {
             AddressOf(mutability, p) => match mutability {
@@ -176,7 +442,20 @@ impl GraphContext {
                 self.render_operand(op1),
                 self.render_operand(op2)
             ),
-            Cast(kind, op, _ty) => format!("Cast-{:?} {}", kind, self.render_operand(op)),
+            Cast(kind, op, ty) => {
+                let source = match op.ty(locals) {
+                    Ok(src) => format!(": {}", self.types.get_name(src)),
+                    Err(_) => String::new(),
+                };
+                format!(
+                    "{} {}{} as {} [{}]",
+                    cast_kind_name(kind),
+                    self.render_operand(op),
+                    source,
+                    self.types.get_name(*ty),
+                    cast_note(kind)
+                )
+            }
             CheckedBinaryOp(binop, op1, op2) => {
                 format!(
                     "chkd-{:?}({}, {})",
@@ -223,6 +502,14 @@ impl GraphContext {
 
     /// Render terminator with context for alloc/type information
     pub fn render_terminator(&self, term: &Terminator) -> String {
+        let text = self.render_terminator_kind(term);
+        match unwind_note(term) {
+            Some(note) => format!("{} ({})", text, note),
+            None => text,
+        }
+    }
+
+    fn render_terminator_kind(&self, term: &Terminator) -> String {
         use TerminatorKind::*;
         match &term.kind {
             Goto { .. } => "Goto".to_string(),
@@ -240,7 +527,7 @@ impl GraphContext {
             } => {
                 let fn_name = self
                     .resolve_call_target(func)
-                    .map(|n| short_fn_name(&n))
+                    .map(|n| self.short_fn_name(&n))
                     .unwrap_or_else(|| "?".to_string());
                 let arg_str = args
                     .iter()
@@ -403,3 +690,198 @@ impl GraphContext {
         lines
     }
 }
+
+// =============================================================================
+// Name Disambiguation
+// =============================================================================
+
+/// Map each function's symbol name to the last segment of its path, widened
+/// to the last two segments when another function's path shares that segment
+/// (`a::foo` vs `b::foo`); instances of one generic function share a path and
+/// do not widen each other
+fn disambiguated_short_names(
+    paths: impl Iterator<Item = (String, String)>,
+) -> HashMap<String, String> {
+    let paths: Vec<(String, String)> = paths.collect();
+    let mut by_short: HashMap<String, HashSet<&str>> = HashMap::new();
+    for (_, path) in &paths {
+        by_short
+            .entry(short_fn_name(path))
+            .or_default()
+            .insert(path.as_str());
+    }
+    paths
+        .iter()
+        .map(|(symbol, path)| {
+            let short = short_fn_name(path);
+            let display = if by_short[&short].len() > 1 {
+                last_segments(path, 2)
+            } else {
+                short
+            };
+            (symbol.clone(), display)
+        })
+        .collect()
+}
+
+/// Debug names of a body's parameters, in order (empty where there is none)
+fn param_names(body: &Body) -> Vec<String> {
+    (1..=body.arg_locals().len())
+        .map(|local| {
+            body.var_debug_info
+                .iter()
+                .find_map(|info| match &info.value {
+                    VarDebugInfoContents::Place(p)
+                        if p.local == local && p.projection.is_empty() =>
+                    {
+                        Some(info.name.clone())
+                    }
+                    _ => None,
+                })
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+// =============================================================================
+// Recursion Detection
+// =============================================================================
+
+/// Find the call cycles among the crate's function items: the strongly
+/// connected components of the direct-call graph with more than one member,
+/// or a single member calling itself
+fn recursion_cycles(
+    items: &[Item],
+    functions: &HashMap<Ty, String>,
+) -> HashMap<String, Vec<String>> {
+    let mut calls: HashMap<&str, Vec<&str>> = HashMap::new();
+    for item in items {
+        let MonoItemKind::MonoItemFn {
+            body: Some(body), ..
+        } = &item.mono_item_kind
+        else {
+            continue;
+        };
+        let callees = calls.entry(item.symbol_name.as_str()).or_default();
+        for block in &body.blocks {
+            if let TerminatorKind::Call {
+                func: Operand::Constant(ConstOperand { const_, .. }),
+                ..
+            } = &block.terminator.kind
+            {
+                if let Some(callee) = functions.get(&const_.ty()) {
+                    callees.push(callee.as_str());
+                }
+            }
+        }
+    }
+
+    let mut tarjan = Tarjan {
+        calls: &calls,
+        index: HashMap::new(),
+        lowlink: HashMap::new(),
+        stack: vec![],
+        on_stack: HashSet::new(),
+        next_index: 0,
+        components: vec![],
+    };
+    let mut roots: Vec<&str> = calls.keys().copied().collect();
+    roots.sort_unstable();
+    for root in roots {
+        if !tarjan.index.contains_key(root) {
+            tarjan.visit(root);
+        }
+    }
+
+    let mut cycles = HashMap::new();
+    for mut component in tarjan.components {
+        let is_cycle = component.len() > 1
+            || calls
+                .get(component[0])
+                .is_some_and(|cs| cs.contains(&component[0]));
+        if !is_cycle {
+            continue;
+        }
+        component.sort_unstable();
+        let members: Vec<String> = component.iter().map(|s| s.to_string()).collect();
+        for member in &members {
+            cycles.insert(member.clone(), members.clone());
+        }
+    }
+    cycles
+}
+
+/// Tarjan's strongly connected components over the call graph
+struct Tarjan<'a> {
+    calls: &'a HashMap<&'a str, Vec<&'a str>>,
+    index: HashMap<&'a str, usize>,
+    lowlink: HashMap<&'a str, usize>,
+    stack: Vec<&'a str>,
+    on_stack: HashSet<&'a str>,
+    next_index: usize,
+    components: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Tarjan<'a> {
+    fn visit(&mut self, node: &'a str) {
+        self.index.insert(node, self.next_index);
+        self.lowlink.insert(node, self.next_index);
+        self.next_index += 1;
+        self.stack.push(node);
+        self.on_stack.insert(node);
+
+        let calls = self.calls;
+        let callees: &'a [&'a str] = calls.get(node).map_or(&[], |cs| cs.as_slice());
+        for &callee in callees {
+            if !self.index.contains_key(callee) {
+                self.visit(callee);
+                let low = self.lowlink[node].min(self.lowlink[callee]);
+                self.lowlink.insert(node, low);
+            } else if self.on_stack.contains(callee) {
+                let low = self.lowlink[node].min(self.index[callee]);
+                self.lowlink.insert(node, low);
+            }
+        }
+
+        if self.lowlink[node] == self.index[node] {
+            let mut component = vec![];
+            while let Some(member) = self.stack.pop() {
+                self.on_stack.remove(member);
+                component.push(member);
+                if member == node {
+                    break;
+                }
+            }
+            self.components.push(component);
+        }
+    }
+}
+
+// =============================================================================
+// Static References
+// =============================================================================
+
+/// Collects the names of statics whose allocations are pointed to by constants
+struct StaticRefCollector<'a> {
+    allocs: &'a AllocIndex,
+    names: Vec<String>,
+}
+
+impl MirVisitor for StaticRefCollector<'_> {
+    fn visit_mir_const(&mut self, constant: &MirConst, loc: Location) {
+        if let ConstantKind::Allocated(alloc) = constant.kind() {
+            for (_, prov) in &alloc.provenance.ptrs {
+                if let Some(AllocKind::Static { name }) = self
+                    .allocs
+                    .get(prov.0.to_index() as u64)
+                    .map(|entry| &entry.kind)
+                {
+                    if !self.names.contains(name) {
+                        self.names.push(name.clone());
+                    }
+                }
+            }
+        }
+        self.super_mir_const(constant, loc);
+    }
+}
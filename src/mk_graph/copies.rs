@@ -0,0 +1,89 @@
+//! Detection of large values copied repeatedly within a block.
+//!
+//! Every `Use(Copy)` or `Use(Move)` of a whole local is a memcpy of the
+//! value's size. When a large value is passed from local to local several
+//! times in one block (`_3 = move _2; _4 = copy _3; _5 = move _4`), the
+//! copies are usually avoidable by borrowing or by restructuring the code, so
+//! [`copy_chains`] reports them as optimization hints.
+
+use std::collections::HashMap;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Local, Operand, Rvalue, StatementKind};
+
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+
+/// Smallest value size, in bytes, whose repeated copies are reported
+pub const LARGE_VALUE_BYTES: usize = 64;
+
+/// Copies of one value within a block
+pub struct CopyChain {
+    pub block: usize,
+    /// Local holding the value before the first copy
+    pub root: Local,
+    /// Type name of the value
+    pub ty: String,
+    pub size: usize,
+    /// Number of `Copy`/`Move` assignments of the value
+    pub copies: usize,
+}
+
+impl CopyChain {
+    /// `128-byte value copied 3 times in bb4`
+    pub fn message(&self) -> String {
+        fill(
+            msg("copies.in_block"),
+            &[&self.size, &self.copies, &self.block],
+        )
+    }
+
+    /// Label note for the block: `_2: 128-byte value copied 3 times`
+    pub fn note(&self) -> String {
+        fill(
+            msg("copies.of_local"),
+            &[&self.root, &self.size, &self.copies],
+        )
+    }
+}
+
+/// Values of at least [`LARGE_VALUE_BYTES`] copied more than once within a
+/// block of `body`
+pub fn copy_chains(body: &Body, ctx: &GraphContext) -> Vec<CopyChain> {
+    let mut chains = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        // the local each copied value came from, and the copies per origin
+        let mut origin: HashMap<Local, Local> = HashMap::new();
+        let mut copies: Vec<(Local, usize)> = vec![];
+        for stmt in &block.statements {
+            let StatementKind::Assign(dest, Rvalue::Use(Operand::Copy(src) | Operand::Move(src))) =
+                &stmt.kind
+            else {
+                continue;
+            };
+            if !dest.projection.is_empty() || !src.projection.is_empty() {
+                continue;
+            }
+            let root = origin.get(&src.local).copied().unwrap_or(src.local);
+            origin.insert(dest.local, root);
+            match copies.iter_mut().find(|(r, _)| *r == root) {
+                Some((_, n)) => *n += 1,
+                None => copies.push((root, 1)),
+            }
+        }
+        for (root, n) in copies {
+            let ty = body.locals()[root].ty;
+            match ctx.types.size_of(ty) {
+                Some(size) if n > 1 && size >= LARGE_VALUE_BYTES => chains.push(CopyChain {
+                    block: idx,
+                    root,
+                    ty: ctx.types.get_name(ty),
+                    size,
+                    copies: n,
+                }),
+                _ => {}
+            }
+        }
+    }
+    chains
+}
@@ -0,0 +1,51 @@
+//! Block execution counts fed back from an instrumented build.
+//!
+//! The harness written by `--instrument` (see
+//! [`super::output::instrument`]) dumps one `<symbol> <block> <count>` line
+//! per counter. Naming that file in `BLOCK_COUNTS` annotates every counted
+//! block in the graph outputs with how often it ran.
+
+use std::collections::HashMap;
+
+use super::messages::{fill, msg};
+
+/// Execution counts from the file named in `BLOCK_COUNTS`, by symbol and
+/// block; empty if the variable is not set
+pub fn block_counts() -> &'static HashMap<(String, usize), u64> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<HashMap<(String, usize), u64>> = OnceLock::new();
+    VAR.get_or_init(|| {
+        let Ok(path) = std::env::var("BLOCK_COUNTS") else {
+            return HashMap::new();
+        };
+        let text = std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read BLOCK_COUNTS file {}: {}", path, e));
+        text.lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .filter_map(|(lineno, line)| {
+                let mut fields = line.split_whitespace();
+                let parsed = (|| {
+                    let symbol = fields.next()?.to_string();
+                    let block = fields.next()?.parse().ok()?;
+                    let count = fields.next()?.parse().ok()?;
+                    Some(((symbol, block), count))
+                })();
+                if parsed.is_none() {
+                    eprintln!(
+                        "BLOCK_COUNTS line {}: expected `<symbol> <block> <count>`: {}",
+                        lineno + 1,
+                        line
+                    );
+                }
+                parsed
+            })
+            .collect()
+    })
+}
+
+/// Label note with the execution count of `block` of function `symbol`
+pub fn count_note(symbol: &str, block: usize) -> Option<String> {
+    let count = block_counts().get(&(symbol.to_string(), block))?;
+    Some(fill(msg("counts.executed"), &[count]))
+}
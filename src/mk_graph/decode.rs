@@ -0,0 +1,590 @@
+//! Type-directed decoding of allocation bytes into readable values.
+//!
+//! [`ValueDecoder`] walks the layout information collected in [`TypeIndex`]
+//! (element strides, field offsets, enum tags) down to primitive leaves, so
+//! that the bytes of a constant render as `[1, 2, 3]` or `(true, 7)` instead
+//! of a byte count.
+
+use crate::compat::stable_mir;
+use stable_mir::ty::{FloatTy, RigidTy, Ty};
+
+use super::index::{TypeIndex, TypeKind, VariantLayout};
+use super::limits::LabelLimits;
+
+/// Maximum width of an integer that is decoded into a number
+pub const MAX_NUMERIC_BYTES: usize = 8;
+
+/// Decodes the bytes of one allocation (or inline constant) according to its type.
+pub struct ValueDecoder<'a> {
+    types: &'a TypeIndex,
+    bytes: &'a [Option<u8>],
+    /// Pointer positions within `bytes`: (byte offset, target alloc id)
+    provenance: &'a [(usize, u64)],
+    /// Element and nesting limits (keep labels from exploding)
+    limits: &'a LabelLimits,
+}
+
+impl<'a> ValueDecoder<'a> {
+    pub fn new(
+        types: &'a TypeIndex,
+        bytes: &'a [Option<u8>],
+        provenance: &'a [(usize, u64)],
+        limits: &'a LabelLimits,
+    ) -> Self {
+        Self {
+            types,
+            bytes,
+            provenance,
+            limits,
+        }
+    }
+
+    /// Decode the whole byte range as a value of type `ty`.
+    /// Returns `None` when the type or layout is not known well enough.
+    pub fn decode(&self, ty: Ty) -> Option<String> {
+        self.decode_at(ty, 0, 0)
+    }
+
+    /// Decode the whole byte range as pointer-sized words, for allocations
+    /// whose type layout is not known: `&alloc3`, `[&alloc3+8, 16]`. Returns
+    /// `None` unless the range is a whole number of words and every pointer
+    /// in it starts on a word.
+    pub fn decode_words(&self) -> Option<String> {
+        let width = self.types.pointer_width();
+        if width == 0 || self.bytes.is_empty() || self.bytes.len() % width != 0 {
+            return None;
+        }
+        if self.provenance.iter().any(|(o, _)| o % width != 0) {
+            return None;
+        }
+        let count = self.bytes.len() / width;
+        let mut words: Vec<String> = (0..count.min(self.limits.decoded_elems))
+            .map(|i| {
+                let offset = i * width;
+                self.pointer_at(offset)
+                    .or_else(|| self.read_uint(offset, width).map(|v| v.to_string()))
+                    .unwrap_or_else(|| "?".to_string())
+            })
+            .collect();
+        if count == 1 {
+            return words.pop();
+        }
+        if count > self.limits.decoded_elems {
+            words.push(format!("..{} more", count - self.limits.decoded_elems));
+        }
+        Some(format!("[{}]", words.join(", ")))
+    }
+
+    /// The pointer stored at `offset`, if provenance says there is one there
+    fn pointer_at(&self, offset: usize) -> Option<String> {
+        let (_, alloc_id) = self.provenance.iter().find(|(o, _)| *o == offset)?;
+        // the pointer bytes hold the offset into the target allocation, in
+        // the target's pointer width and byte order
+        match self.read_uint(offset, self.types.pointer_width()) {
+            Some(target) if target > 0 => Some(format!("&alloc{}+{}", alloc_id, target)),
+            _ => Some(format!("&alloc{}", alloc_id)),
+        }
+    }
+
+    fn decode_at(&self, ty: Ty, offset: usize, depth: usize) -> Option<String> {
+        if depth > self.limits.decode_depth {
+            return Some("..".to_string());
+        }
+        if let Some(ptr) = self.pointer_at(offset) {
+            return Some(ptr);
+        }
+        let entry = self.types.get(ty)?;
+        match &entry.kind {
+            TypeKind::Primitive(rigid) => self.decode_primitive(rigid, offset),
+            TypeKind::Array { elem_ty, len } => {
+                let elem_size = self.types.size_of(*elem_ty)?;
+                let count = match len {
+                    Some(n) => *n as usize,
+                    None if elem_size > 0 => self.bytes.len().checked_sub(offset)? / elem_size,
+                    None => 0,
+                };
+                let mut elems: Vec<String> = (0..count.min(self.limits.decoded_elems))
+                    .map(|i| {
+                        self.decode_at(*elem_ty, offset + i * elem_size, depth + 1)
+                            .unwrap_or_else(|| "?".to_string())
+                    })
+                    .collect();
+                if count > self.limits.decoded_elems {
+                    elems.push(format!("..{} more", count - self.limits.decoded_elems));
+                }
+                Some(format!("[{}]", elems.join(", ")))
+            }
+            TypeKind::Tuple { fields } => {
+                let layout = entry.layout.as_ref()?;
+                let elems = self.decode_fields(fields, &layout.field_offsets, offset, depth)?;
+                if fields.len() == 1 {
+                    Some(format!("({},)", elems.join(", ")))
+                } else {
+                    Some(format!("({})", elems.join(", ")))
+                }
+            }
+            TypeKind::Struct { fields } => {
+                let tys: Vec<Ty> = fields.iter().map(|f| f.ty).collect();
+                let offsets = fields
+                    .iter()
+                    .map(|f| f.offset)
+                    .collect::<Option<Vec<_>>>()?;
+                let elems = self.decode_fields(&tys, &offsets, offset, depth)?;
+                if elems.is_empty() {
+                    Some(entry.name.clone())
+                } else {
+                    Some(format!("{}({})", entry.name, elems.join(", ")))
+                }
+            }
+            TypeKind::Enum { variants } => {
+                let layout = entry.layout.as_ref()?;
+                let variant_idx = match &layout.variants {
+                    VariantLayout::Single(idx) => *idx,
+                    VariantLayout::Multiple { tag, .. } => {
+                        let tag = tag.as_ref()?;
+                        let raw = self.read_uint(offset + tag.offset, tag.size)?;
+                        tag.variant(raw, variants)?
+                    }
+                };
+                let variant = variants.get(variant_idx)?;
+                let tys: Vec<Ty> = variant.fields.iter().map(|f| f.ty).collect();
+                let offsets = layout.variant_field_offsets(variant_idx);
+                let elems = self.decode_fields(&tys, offsets, offset, depth)?;
+                if elems.is_empty() {
+                    Some(format!("{} as variant {}", entry.name, variant_idx))
+                } else {
+                    Some(format!(
+                        "{} as variant {}({})",
+                        entry.name,
+                        variant_idx,
+                        elems.join(", ")
+                    ))
+                }
+            }
+            TypeKind::Ptr { .. } | TypeKind::Ref { .. } => {
+                // pointers with provenance were handled above
+                let width = self.types.pointer_width();
+                let raw = self.read_uint(offset, width)?;
+                if raw == 0 {
+                    Some("null".to_string())
+                } else {
+                    Some(format!("{:#x}", raw))
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn decode_fields(
+        &self,
+        tys: &[Ty],
+        offsets: &[usize],
+        base: usize,
+        depth: usize,
+    ) -> Option<Vec<String>> {
+        tys.iter()
+            .enumerate()
+            .map(|(i, ty)| {
+                let field_offset = offsets.get(i)?;
+                Some(
+                    self.decode_at(*ty, base + field_offset, depth + 1)
+                        .unwrap_or_else(|| "?".to_string()),
+                )
+            })
+            .collect()
+    }
+
+    fn decode_primitive(&self, rigid: &RigidTy, offset: usize) -> Option<String> {
+        let size = super::index::primitive_size(rigid, self.types.pointer_width())?;
+        let raw = self.read_uint(offset, size)?;
+        format_scalar(rigid, raw, size)
+    }
+
+    fn read_uint(&self, offset: usize, size: usize) -> Option<u128> {
+        read_uint(self.bytes, offset, size, self.types.big_endian())
+    }
+}
+
+/// Read `size` initialized bytes at `offset` as an unsigned integer in the
+/// given byte order.
+pub fn read_uint(
+    bytes: &[Option<u8>],
+    offset: usize,
+    size: usize,
+    big_endian: bool,
+) -> Option<u128> {
+    if size > 16 {
+        return None;
+    }
+    let bytes = bytes
+        .get(offset..offset.checked_add(size)?)?
+        .iter()
+        .copied()
+        .collect::<Option<Vec<u8>>>()?;
+    let fold = |acc: u128, b: &u8| (acc << 8) | *b as u128;
+    if big_endian {
+        Some(bytes.iter().fold(0, fold))
+    } else {
+        Some(bytes.iter().rev().fold(0, fold))
+    }
+}
+
+/// Format the raw bits of a `size`-byte scalar of primitive type `rigid`:
+/// signed integers are sign-extended, `bool` and `char` shown as literals,
+/// and `f32`/`f64` formatted so that the text parses back to the same value.
+pub fn format_scalar(rigid: &RigidTy, raw: u128, size: usize) -> Option<String> {
+    match rigid {
+        RigidTy::Bool => match raw {
+            0 => Some("false".to_string()),
+            1 => Some("true".to_string()),
+            _ => None,
+        },
+        RigidTy::Char => char::from_u32(raw as u32).map(|c| format!("{:?}", c)),
+        RigidTy::Int(_) => {
+            let value = sign_extend(raw, size);
+            let digits = format_int(value.unsigned_abs());
+            Some(if value < 0 {
+                format!("-{}", digits)
+            } else {
+                digits
+            })
+        }
+        RigidTy::Uint(_) => Some(format_int(raw)),
+        RigidTy::Float(FloatTy::F32) => {
+            let value = f32::from_bits(raw as u32);
+            Some(format_float(
+                format!("{:?}", value),
+                value.is_finite(),
+                "f32",
+            ))
+        }
+        RigidTy::Float(FloatTy::F64) => {
+            let value = f64::from_bits(raw as u64);
+            Some(format_float(
+                format!("{:?}", value),
+                value.is_finite(),
+                "f64",
+            ))
+        }
+        // no host type for f16/f128 to format with
+        RigidTy::Float(_) => None,
+        _ => Some(format!("{}", raw)),
+    }
+}
+
+/// How integer values are written in labels, selected by `MIR_INT_FORMAT`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IntFormat {
+    /// `1000000` (default)
+    Dec,
+    /// `1_000_000`
+    Grouped,
+    /// `0xF_4240`
+    Hex,
+    /// `0b1111_0100_0010_0100_0000`
+    Bin,
+}
+
+impl IntFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "dec" => Some(IntFormat::Dec),
+            "grouped" => Some(IntFormat::Grouped),
+            "hex" => Some(IntFormat::Hex),
+            "bin" => Some(IntFormat::Bin),
+            _ => None,
+        }
+    }
+
+    /// Write the magnitude of an integer in this format
+    fn write(self, value: u128) -> String {
+        match self {
+            IntFormat::Dec => value.to_string(),
+            IntFormat::Grouped => group_digits(&value.to_string(), 3),
+            IntFormat::Hex => format!("0x{}", group_digits(&format!("{:X}", value), 4)),
+            IntFormat::Bin => format!("0b{}", group_digits(&format!("{:b}", value), 4)),
+        }
+    }
+}
+
+pub fn int_format() -> IntFormat {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<IntFormat> = OnceLock::new();
+    *VAR.get_or_init(|| match std::env::var("MIR_INT_FORMAT") {
+        Ok(name) => IntFormat::from_name(&name).unwrap_or_else(|| {
+            panic!(
+                "MIR_INT_FORMAT={} is not one of dec, grouped, hex, bin",
+                name
+            )
+        }),
+        Err(_) => IntFormat::Dec,
+    })
+}
+
+/// Write the magnitude of an integer in the selected [`IntFormat`]
+fn format_int(value: u128) -> String {
+    int_format().write(value)
+}
+
+/// Separate groups of `n` digits with `_`, counting from the right
+fn group_digits(digits: &str, n: usize) -> String {
+    let mut out = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i) % n == 0 {
+            out.push('_');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Suffix the `Debug` text of a float (`1.5f64`, `-0.0f32`); non-finite
+/// values stay as `NaN`, `inf` and `-inf`.
+fn format_float(text: String, finite: bool, suffix: &str) -> String {
+    if finite {
+        format!("{}{}", text, suffix)
+    } else {
+        text
+    }
+}
+
+/// Reinterpret the low `size` bytes of `raw` as a two's complement integer.
+fn sign_extend(raw: u128, size: usize) -> i128 {
+    if size == 0 || size >= 16 {
+        return raw as i128;
+    }
+    let shift = 128 - (size * 8) as u32;
+    ((raw << shift) as i128) >> shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mk_graph::index::TypeEntry;
+    use stable_mir::ty::{IndexedVal, IntTy, UintTy};
+
+    fn init(bytes: &[u8]) -> Vec<Option<u8>> {
+        bytes.iter().copied().map(Some).collect()
+    }
+
+    /// Type 0 is `[elem; len]`, type 1 its element type
+    fn array_of(elem: UintTy, len: u64, pointer_width: usize, big_endian: bool) -> TypeIndex {
+        let mut types = TypeIndex::new(pointer_width, big_endian);
+        let entry = |kind| TypeEntry {
+            name: String::new(),
+            kind,
+            layout: None,
+        };
+        types.insert(
+            Ty::to_val(0),
+            entry(TypeKind::Array {
+                elem_ty: Ty::to_val(1),
+                len: Some(len),
+            }),
+        );
+        types.insert(
+            Ty::to_val(1),
+            entry(TypeKind::Primitive(RigidTy::Uint(elem))),
+        );
+        types
+    }
+
+    fn decode(types: &TypeIndex, bytes: &[Option<u8>], provenance: &[(usize, u64)]) -> String {
+        let limits = LabelLimits::default();
+        ValueDecoder::new(types, bytes, provenance, &limits)
+            .decode(Ty::to_val(0))
+            .unwrap()
+    }
+
+    #[test]
+    fn read_uint_in_either_byte_order() {
+        let bytes = init(&[0x01, 0x02, 0x03, 0x04]);
+        assert_eq!(read_uint(&bytes, 0, 4, false), Some(0x0403_0201));
+        assert_eq!(read_uint(&bytes, 0, 4, true), Some(0x0102_0304));
+        assert_eq!(read_uint(&bytes, 1, 2, false), Some(0x0302));
+        assert_eq!(read_uint(&bytes, 1, 2, true), Some(0x0203));
+        assert_eq!(read_uint(&bytes, 0, 0, false), Some(0));
+    }
+
+    #[test]
+    fn read_uint_needs_initialized_bytes_in_range() {
+        let mut bytes = init(&[0xff; 17]);
+        assert_eq!(read_uint(&bytes, 1, 16, false), Some(u128::MAX));
+        assert_eq!(read_uint(&bytes, 0, 17, false), None);
+        assert_eq!(read_uint(&bytes, 14, 4, false), None);
+        assert_eq!(read_uint(&bytes, usize::MAX, 2, false), None);
+        bytes[2] = None;
+        assert_eq!(read_uint(&bytes, 0, 4, false), None);
+        assert_eq!(read_uint(&bytes, 3, 4, false), Some(0xffff_ffff));
+    }
+
+    #[test]
+    fn decode_follows_the_target_byte_order() {
+        let bytes = init(&[0, 0, 1, 0, 0, 0, 0, 2]);
+        let little = array_of(UintTy::U32, 2, 8, false);
+        let big = array_of(UintTy::U32, 2, 8, true);
+        assert_eq!(decode(&little, &bytes, &[]), "[65536, 33554432]");
+        assert_eq!(decode(&big, &bytes, &[]), "[256, 2]");
+    }
+
+    #[test]
+    fn decode_reads_usize_in_the_target_pointer_width() {
+        let bytes = init(&[1, 0, 0, 0, 2, 0, 0, 0]);
+        assert_eq!(
+            decode(&array_of(UintTy::Usize, 2, 4, false), &bytes, &[]),
+            "[1, 2]"
+        );
+        assert_eq!(
+            decode(&array_of(UintTy::Usize, 1, 8, false), &bytes, &[]),
+            "[8589934593]"
+        );
+        assert_eq!(
+            decode(&array_of(UintTy::Usize, 2, 8, false), &bytes, &[]),
+            "[8589934593, ?]"
+        );
+    }
+
+    #[test]
+    fn decode_shows_pointers_by_target_allocation() {
+        let bytes = init(&[0, 0, 0, 0, 8, 0, 0, 0, 3, 0, 0, 0]);
+        let types = array_of(UintTy::Usize, 3, 4, false);
+        assert_eq!(decode(&types, &bytes, &[(4, 9)]), "[0, &alloc9+8, 3]");
+        let limits = LabelLimits::default();
+        let words = ValueDecoder::new(&types, &bytes, &[(4, 9)], &limits);
+        assert_eq!(words.decode_words().as_deref(), Some("[0, &alloc9+8, 3]"));
+        let misaligned = ValueDecoder::new(&types, &bytes, &[(2, 9)], &limits);
+        assert_eq!(misaligned.decode_words(), None);
+    }
+
+    #[test]
+    fn sign_extend_by_width() {
+        for (raw, size, value) in [
+            (0xff, 1, -1),
+            (0x7f, 1, 127),
+            (0x80, 1, -128),
+            (0x1ff, 1, -1),
+            (0xfffe, 2, -2),
+            (0x8000_0000, 4, i32::MIN as i128),
+            (u64::MAX as u128, 8, -1),
+            (u128::MAX, 16, -1),
+            (5, 0, 5),
+        ] {
+            assert_eq!(
+                sign_extend(raw, size),
+                value,
+                "{:#x} in {} bytes",
+                raw,
+                size
+            );
+        }
+    }
+
+    #[test]
+    fn format_scalar_by_type() {
+        for (rigid, raw, size, text) in [
+            (RigidTy::Int(IntTy::I8), 0xff, 1, Some("-1")),
+            (RigidTy::Int(IntTy::I32), 0xffff_ff85, 4, Some("-123")),
+            (RigidTy::Int(IntTy::I64), 42, 8, Some("42")),
+            (
+                RigidTy::Int(IntTy::I128),
+                1 << 127,
+                16,
+                Some("-170141183460469231731687303715884105728"),
+            ),
+            (RigidTy::Uint(UintTy::U8), 0xff, 1, Some("255")),
+            (RigidTy::Bool, 0, 1, Some("false")),
+            (RigidTy::Bool, 1, 1, Some("true")),
+            (RigidTy::Bool, 2, 1, None),
+            (RigidTy::Char, 'x' as u128, 4, Some("'x'")),
+            (RigidTy::Char, '\n' as u128, 4, Some("'\\n'")),
+            (RigidTy::Char, 0xd800, 4, None),
+            (
+                RigidTy::Float(FloatTy::F32),
+                1.5f32.to_bits() as u128,
+                4,
+                Some("1.5f32"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F32),
+                0.1f32.to_bits() as u128,
+                4,
+                Some("0.1f32"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F32),
+                (-0.0f32).to_bits() as u128,
+                4,
+                Some("-0.0f32"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F64),
+                0.1f64.to_bits() as u128,
+                8,
+                Some("0.1f64"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F64),
+                1e300f64.to_bits() as u128,
+                8,
+                Some("1e300f64"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F64),
+                f64::NAN.to_bits() as u128,
+                8,
+                Some("NaN"),
+            ),
+            (
+                RigidTy::Float(FloatTy::F32),
+                f32::NEG_INFINITY.to_bits() as u128,
+                4,
+                Some("-inf"),
+            ),
+            (RigidTy::Float(FloatTy::F16), 0, 2, None),
+        ] {
+            assert_eq!(
+                format_scalar(&rigid, raw, size).as_deref(),
+                text,
+                "{:#x} as {:?}",
+                raw,
+                rigid
+            );
+        }
+    }
+
+    #[test]
+    fn int_format_names() {
+        assert_eq!(IntFormat::from_name("dec"), Some(IntFormat::Dec));
+        assert_eq!(IntFormat::from_name("grouped"), Some(IntFormat::Grouped));
+        assert_eq!(IntFormat::from_name("hex"), Some(IntFormat::Hex));
+        assert_eq!(IntFormat::from_name("bin"), Some(IntFormat::Bin));
+        assert_eq!(IntFormat::from_name("HEX"), None);
+        assert_eq!(IntFormat::from_name(""), None);
+    }
+
+    #[test]
+    fn int_formats_write_magnitudes() {
+        for (format, value, text) in [
+            (IntFormat::Dec, 1_000_000, "1000000"),
+            (IntFormat::Grouped, 1_000_000, "1_000_000"),
+            (IntFormat::Grouped, 999, "999"),
+            (IntFormat::Hex, 1_000_000, "0xF_4240"),
+            (IntFormat::Hex, 0xffff, "0xFFFF"),
+            (IntFormat::Hex, 0, "0x0"),
+            (IntFormat::Bin, 1_000_000, "0b1111_0100_0010_0100_0000"),
+            (IntFormat::Bin, 5, "0b101"),
+        ] {
+            assert_eq!(format.write(value), text, "{} as {:?}", value, format);
+        }
+    }
+
+    #[test]
+    fn group_digits_from_the_right() {
+        assert_eq!(group_digits("1000000", 3), "1_000_000");
+        assert_eq!(group_digits("100000", 3), "100_000");
+        assert_eq!(group_digits("10", 3), "10");
+        assert_eq!(group_digits("", 3), "");
+        assert_eq!(group_digits("DEADBEEF", 4), "DEAD_BEEF");
+        assert_eq!(group_digits("1DEADBEEF", 4), "1_DEAD_BEEF");
+    }
+}
@@ -0,0 +1,93 @@
+//! Detection of duplicated block bodies.
+//!
+//! Lowering a `match` often repeats the same tail code in every arm, which
+//! shows up as several blocks with identical statements and terminators.
+//! [`duplicate_groups`] finds these blocks by their rendered text, so they can
+//! be noted and colored alike in the graphs; they are candidates for tail
+//! merging. Blocks without statements are not reported, since an empty `Goto`
+//! or `Return` block says nothing about the code that was duplicated.
+
+use std::collections::BTreeMap;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::Body;
+
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+use super::util::terminator_targets;
+
+/// Blocks of one body with the same statements and terminator
+pub struct DuplicateGroup {
+    /// Block indices, ascending
+    pub blocks: Vec<usize>,
+    /// Whether the blocks also continue to the same successors, so that one
+    /// block could replace all of them
+    pub exact: bool,
+}
+
+/// Groups of duplicated blocks in `body`, ordered by their first block
+pub fn duplicate_groups(body: &Body, ctx: &GraphContext) -> Vec<DuplicateGroup> {
+    let mut by_text: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (idx, block) in body.blocks.iter().enumerate() {
+        if block.statements.is_empty() {
+            continue;
+        }
+        let mut text: Vec<String> = block
+            .statements
+            .iter()
+            .map(|s| ctx.render_stmt(s, body.locals()))
+            .collect();
+        text.push(ctx.render_terminator(&block.terminator));
+        by_text.entry(text.join("\n")).or_default().push(idx);
+    }
+
+    let mut groups: Vec<DuplicateGroup> = by_text
+        .into_values()
+        .filter(|blocks| blocks.len() > 1)
+        .map(|blocks| {
+            let targets = |b: usize| terminator_targets(&body.blocks[b].terminator);
+            let exact = blocks.iter().all(|b| targets(*b) == targets(blocks[0]));
+            DuplicateGroup { blocks, exact }
+        })
+        .collect();
+    groups.sort_by_key(|g| g.blocks[0]);
+    groups
+}
+
+/// Label notes naming the other members of each block's group
+pub fn duplicate_notes(groups: &[DuplicateGroup]) -> BTreeMap<usize, Vec<String>> {
+    let mut notes: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for group in groups {
+        for block in &group.blocks {
+            let others: Vec<String> = group
+                .blocks
+                .iter()
+                .filter(|b| *b != block)
+                .map(|b| format!("bb{}", b))
+                .collect();
+            let template = if group.exact {
+                "duplicate.exact"
+            } else {
+                "duplicate.similar"
+            };
+            notes
+                .entry(*block)
+                .or_default()
+                .push(fill(msg(template), &[&others.join(", ")]));
+        }
+    }
+    notes
+}
+
+/// Fill color shared by the blocks of duplicate group `group`
+pub fn duplicate_color(group: usize) -> &'static str {
+    const PALETTE: &[&str] = &[
+        "lightblue",
+        "palegoldenrod",
+        "thistle",
+        "lightcyan",
+        "wheat",
+        "lavender",
+    ];
+    PALETTE[group % PALETTE.len()]
+}
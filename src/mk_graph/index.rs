@@ -3,13 +3,18 @@
 use std::collections::HashMap;
 
 use crate::compat::stable_mir;
-use stable_mir::abi::{FieldsShape, LayoutShape};
+use stable_mir::abi::{
+    FieldsShape, IntegerLength, LayoutShape, Primitive, Scalar, TagEncoding, VariantsShape,
+};
 use stable_mir::mir::alloc::GlobalAlloc;
-use stable_mir::ty::{IndexedVal, Ty};
+use stable_mir::ty::{IndexedVal, RigidTy, Ty};
 use stable_mir::CrateDef;
 
 use crate::printer::{AllocInfo, TypeMetadata};
 
+use super::decode::{read_uint, ValueDecoder, MAX_NUMERIC_BYTES};
+use super::limits::LabelLimits;
+
 // =============================================================================
 // Index Structures
 // =============================================================================
@@ -38,6 +43,10 @@ pub enum AllocKind {
 /// Index for looking up type information
 pub struct TypeIndex {
     by_id: HashMap<u64, TypeEntry>,
+    /// Target pointer width in bytes (size of `usize`, `isize`, and thin pointers)
+    pointer_width: usize,
+    /// Whether the target stores the most significant byte first
+    big_endian: bool,
 }
 
 /// Detailed type information for rendering
@@ -50,7 +59,7 @@ pub struct TypeEntry {
 /// Simplified type kind for display
 #[derive(Clone)]
 pub enum TypeKind {
-    Primitive,
+    Primitive(RigidTy),
     Struct {
         fields: Vec<FieldInfo>,
     },
@@ -100,6 +109,39 @@ pub struct LayoutInfo {
     pub size: usize,
     pub align: usize,
     pub field_offsets: Vec<usize>,
+    pub variants: VariantLayout,
+}
+
+/// Variant layout of a type (only interesting for enums)
+#[derive(Clone)]
+pub enum VariantLayout {
+    /// Only one variant is ever present (structs, single-variant enums)
+    Single(usize),
+    /// Several variants, told apart by a tag; field offsets are per variant
+    Multiple {
+        tag: Option<TagInfo>,
+        variant_field_offsets: Vec<Vec<usize>>,
+    },
+}
+
+/// Location of an enum tag; the tag value is the discriminant unless
+/// `niche` is set
+#[derive(Clone)]
+pub struct TagInfo {
+    pub offset: usize,
+    pub size: usize,
+    pub niche: Option<NicheInfo>,
+}
+
+/// How a niche-encoded tag stores the variant: variants `first..=last`
+/// store `niche_start + (variant - first)`, wrapping at the tag's size, and
+/// any other value means `untagged_variant`
+#[derive(Clone)]
+pub struct NicheInfo {
+    pub untagged_variant: usize,
+    pub first: usize,
+    pub last: usize,
+    pub niche_start: u128,
 }
 
 // =============================================================================
@@ -119,10 +161,14 @@ impl AllocIndex {
         }
     }
 
-    pub fn from_alloc_infos(allocs: &[AllocInfo], type_index: &TypeIndex) -> Self {
+    pub fn from_alloc_infos(
+        allocs: &[AllocInfo],
+        type_index: &TypeIndex,
+        limits: &LabelLimits,
+    ) -> Self {
         let mut index = Self::new();
         for info in allocs {
-            let entry = AllocEntry::from_alloc_info(info, type_index);
+            let entry = AllocEntry::from_alloc_info(info, type_index, limits);
             index.by_id.insert(entry.alloc_id, entry);
         }
         index
@@ -143,6 +189,23 @@ impl AllocIndex {
             None => format!("alloc{}", id),
         }
     }
+
+    /// Describe a pointer to byte `offset` of alloc `id`: `&alloc5+16`, or a
+    /// field path such as `&FOO.1[2]` when the pointee's layout is known
+    pub fn describe_ptr(&self, id: u64, offset: usize, types: &TypeIndex) -> String {
+        let entry = self.get(id);
+        let root = match entry.map(|e| &e.kind) {
+            Some(AllocKind::Static { name }) => name.clone(),
+            _ => format!("alloc{}", id),
+        };
+        if offset == 0 {
+            return format!("&{}", root);
+        }
+        match entry.and_then(|e| types.field_path(e.ty, offset)) {
+            Some(path) => format!("&{}{}", root, path),
+            None => format!("&{}+{}", root, offset),
+        }
+    }
 }
 
 // =============================================================================
@@ -150,7 +213,7 @@ impl AllocIndex {
 // =============================================================================
 
 impl AllocEntry {
-    pub fn from_alloc_info(info: &AllocInfo, type_index: &TypeIndex) -> Self {
+    pub fn from_alloc_info(info: &AllocInfo, type_index: &TypeIndex, limits: &LabelLimits) -> Self {
         let alloc_id = info.alloc_id().to_index() as u64;
         let ty = info.ty();
         let ty_name = type_index.get_name(ty);
@@ -158,30 +221,54 @@ impl AllocEntry {
         let (kind, description) = match info.global_alloc() {
             GlobalAlloc::Memory(alloc) => {
                 let bytes = &alloc.bytes;
-                let is_str = ty_name.contains("str");
+                let is_str = ty_name.contains("str")
+                    || matches!(
+                        type_index.get(ty).map(|e| &e.kind),
+                        Some(TypeKind::Primitive(RigidTy::Str))
+                    );
 
                 // Convert Option<u8> bytes to actual bytes for display
                 let concrete_bytes: Vec<u8> = bytes.iter().filter_map(|&b| b).collect();
 
+                let provenance: Vec<(usize, u64)> = alloc
+                    .provenance
+                    .ptrs
+                    .iter()
+                    .map(|(offset, prov)| (*offset, prov.0.to_index() as u64))
+                    .collect();
+                let decoder = ValueDecoder::new(type_index, bytes, &provenance, limits);
+                // without a known layout, pointers and pointer-sized values
+                // are still read at the target's width and byte order
+                let decoded = decoder.decode(ty).or_else(|| {
+                    (!provenance.is_empty() || bytes.len() == type_index.pointer_width())
+                        .then(|| decoder.decode_words())
+                        .flatten()
+                });
+                // small values of unknown type as one integer, only when every
+                // byte is initialized (so no byte is shifted into a gap)
+                let numeric = if !bytes.is_empty() && bytes.len() <= MAX_NUMERIC_BYTES {
+                    read_uint(bytes, 0, bytes.len(), type_index.big_endian())
+                } else {
+                    None
+                };
+
                 let desc = if is_str && concrete_bytes.iter().all(|b| b.is_ascii()) {
                     let s: String = concrete_bytes
                         .iter()
-                        .take(20)
+                        .take(limits.string_preview)
                         .map(|&b| b as char)
                         .collect::<String>()
                         .escape_default()
                         .to_string();
-                    if concrete_bytes.len() > 20 {
+                    if concrete_bytes.len() > limits.string_preview {
                         format!("\"{}...\" ({} bytes)", s, concrete_bytes.len())
                     } else {
                         format!("\"{}\"", s)
                     }
-                } else if concrete_bytes.len() <= 8 && !concrete_bytes.is_empty() {
-                    format!(
-                        "{} = {}",
-                        ty_name,
-                        super::util::bytes_to_u64_le(&concrete_bytes)
-                    )
+                } else if let Some(value) = decoded {
+                    format!("{} = {}", ty_name, value)
+                } else if let Some(value) = numeric {
+                    format!("{} = {}", ty_name, value)
                 } else {
                     format!("{} ({} bytes)", ty_name, bytes.len())
                 };
@@ -238,26 +325,57 @@ impl AllocEntry {
 
 impl Default for TypeIndex {
     fn default() -> Self {
-        Self::new()
+        Self::new(std::mem::size_of::<usize>(), cfg!(target_endian = "big"))
     }
 }
 
 impl TypeIndex {
-    pub fn new() -> Self {
+    pub fn new(pointer_width: usize, big_endian: bool) -> Self {
         Self {
             by_id: HashMap::new(),
+            pointer_width,
+            big_endian,
         }
     }
 
-    pub fn from_types(types: &[(Ty, TypeMetadata)]) -> Self {
-        let mut index = Self::new();
+    pub fn from_types(
+        types: &[(Ty, TypeMetadata)],
+        pointer_width: usize,
+        big_endian: bool,
+    ) -> Self {
+        let mut index = Self::new(pointer_width, big_endian);
         for (ty, metadata) in types {
-            let entry = TypeEntry::from_metadata(metadata, *ty);
-            index.by_id.insert(ty.to_index() as u64, entry);
+            index.insert(*ty, TypeEntry::from_metadata(metadata, *ty));
         }
         index
     }
 
+    pub fn insert(&mut self, ty: Ty, entry: TypeEntry) {
+        self.by_id.insert(ty.to_index() as u64, entry);
+    }
+
+    /// Target pointer width in bytes
+    pub fn pointer_width(&self) -> usize {
+        self.pointer_width
+    }
+
+    /// Whether integers in allocation bytes are big-endian
+    pub fn big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Size of a type in bytes, from its layout or (for primitives) its kind
+    pub fn size_of(&self, ty: Ty) -> Option<usize> {
+        let entry = self.get(ty)?;
+        if let Some(layout) = &entry.layout {
+            return Some(layout.size);
+        }
+        match &entry.kind {
+            TypeKind::Primitive(rigid) => primitive_size(rigid, self.pointer_width),
+            _ => None,
+        }
+    }
+
     pub fn get(&self, ty: Ty) -> Option<&TypeEntry> {
         self.by_id.get(&(ty.to_index() as u64))
     }
@@ -275,6 +393,52 @@ impl TypeIndex {
             .and_then(|e| e.layout.as_ref())
     }
 
+    /// Projection path (`.1[2]`) from a value of type `ty` to the field or
+    /// element starting at byte `offset`; fields are named by index since the
+    /// type metadata carries no field names. `None` if the layout is unknown,
+    /// the offset points into the middle of a primitive or past the end of an
+    /// array.
+    pub fn field_path(&self, ty: Ty, offset: usize) -> Option<String> {
+        let mut path = String::new();
+        let (mut ty, mut offset) = (ty, offset);
+        while offset > 0 {
+            let entry = self.get(ty)?;
+            let (step, field_ty, field_offset) = match &entry.kind {
+                TypeKind::Array { elem_ty, len } => {
+                    let elem_size = self.size_of(*elem_ty).filter(|s| *s > 0)?;
+                    let idx = offset / elem_size;
+                    if len.is_some_and(|len| idx as u64 >= len) {
+                        return None;
+                    }
+                    (format!("[{}]", idx), *elem_ty, idx * elem_size)
+                }
+                TypeKind::Struct { fields } => {
+                    let (idx, field, field_offset) = fields
+                        .iter()
+                        .enumerate()
+                        .filter_map(|(i, f)| Some((i, f, f.offset?)))
+                        .find(|(_, f, start)| {
+                            *start <= offset && offset < start + self.size_of(f.ty).unwrap_or(0)
+                        })?;
+                    (format!(".{}", idx), field.ty, field_offset)
+                }
+                TypeKind::Tuple { fields } => {
+                    let offsets = &entry.layout.as_ref()?.field_offsets;
+                    let (idx, field_offset) = offsets.iter().enumerate().find(|(i, start)| {
+                        let size = fields.get(*i).and_then(|f| self.size_of(*f)).unwrap_or(0);
+                        **start <= offset && offset < **start + size
+                    })?;
+                    (format!(".{}", idx), *fields.get(idx)?, *field_offset)
+                }
+                _ => return None,
+            };
+            path.push_str(&step);
+            ty = field_ty;
+            offset -= field_offset;
+        }
+        Some(path)
+    }
+
     /// Iterate over all type entries
     pub fn iter(&self) -> impl Iterator<Item = (u64, &TypeEntry)> {
         self.by_id.iter().map(|(&id, entry)| (id, entry))
@@ -288,9 +452,11 @@ impl TypeIndex {
 impl TypeEntry {
     pub fn from_metadata(metadata: &TypeMetadata, ty: Ty) -> Self {
         let (name, kind, layout) = match metadata {
-            TypeMetadata::PrimitiveType(rigid) => {
-                (format!("{:?}", rigid), TypeKind::Primitive, None)
-            }
+            TypeMetadata::PrimitiveType(rigid) => (
+                format!("{:?}", rigid),
+                TypeKind::Primitive(rigid.clone()),
+                None,
+            ),
             TypeMetadata::StructType {
                 name,
                 fields,
@@ -478,10 +644,66 @@ impl LayoutInfo {
             FieldsShape::Arbitrary { offsets } => offsets.iter().map(|o| o.bytes()).collect(),
         };
 
+        let variants = match &shape.variants {
+            VariantsShape::Single { index } => VariantLayout::Single(index.to_index()),
+            VariantsShape::Multiple {
+                tag,
+                tag_encoding,
+                tag_field,
+                variants,
+            } => {
+                let niche = match tag_encoding {
+                    TagEncoding::Direct => None,
+                    TagEncoding::Niche {
+                        untagged_variant,
+                        niche_variants,
+                        niche_start,
+                    } => Some(NicheInfo {
+                        untagged_variant: untagged_variant.to_index(),
+                        first: niche_variants.start().to_index(),
+                        last: niche_variants.end().to_index(),
+                        niche_start: *niche_start,
+                    }),
+                };
+                // pointer niches (`Option<&T>`) are left undecoded
+                let tag = field_offsets
+                    .get(*tag_field)
+                    .copied()
+                    .zip(scalar_int_size(tag))
+                    .map(|(offset, size)| TagInfo {
+                        offset,
+                        size,
+                        niche,
+                    });
+                VariantLayout::Multiple {
+                    tag,
+                    variant_field_offsets: variants
+                        .iter()
+                        .map(|v| Self::from_shape(v).field_offsets)
+                        .collect(),
+                }
+            }
+        };
+
         Self {
             size: shape.size.bytes(),
             align: shape.abi_align as usize,
             field_offsets,
+            variants,
+        }
+    }
+
+    /// Field offsets of the given variant (falls back to the shared offsets)
+    pub fn variant_field_offsets(&self, variant: usize) -> &[usize] {
+        match &self.variants {
+            VariantLayout::Multiple {
+                variant_field_offsets,
+                ..
+            } => variant_field_offsets
+                .get(variant)
+                .map(Vec::as_slice)
+                .unwrap_or(&[]),
+            VariantLayout::Single(_) => &self.field_offsets,
         }
     }
 
@@ -490,3 +712,186 @@ impl LayoutInfo {
         self.field_offsets.get(index).copied()
     }
 }
+
+impl TagInfo {
+    /// Index of the variant whose tag value is `raw`, among `variants`
+    pub fn variant(&self, raw: u128, variants: &[VariantInfo]) -> Option<usize> {
+        let mask = if self.size >= 16 {
+            u128::MAX
+        } else {
+            (1u128 << (self.size * 8)) - 1
+        };
+        match &self.niche {
+            None => variants.iter().position(|v| v.discriminant & mask == raw),
+            Some(niche) => {
+                let relative = raw.wrapping_sub(niche.niche_start) & mask;
+                let idx = if relative <= (niche.last - niche.first) as u128 {
+                    niche.first + relative as usize
+                } else {
+                    niche.untagged_variant
+                };
+                (idx < variants.len()).then_some(idx)
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Size Helpers
+// =============================================================================
+
+/// Size in bytes of a primitive type (`None` for unsized `str`)
+pub fn primitive_size(rigid: &RigidTy, pointer_width: usize) -> Option<usize> {
+    use stable_mir::ty::{FloatTy, IntTy, UintTy};
+    match rigid {
+        RigidTy::Bool => Some(1),
+        RigidTy::Char => Some(4),
+        RigidTy::Int(IntTy::Isize) | RigidTy::Uint(UintTy::Usize) => Some(pointer_width),
+        RigidTy::Int(IntTy::I8) | RigidTy::Uint(UintTy::U8) => Some(1),
+        RigidTy::Int(IntTy::I16) | RigidTy::Uint(UintTy::U16) => Some(2),
+        RigidTy::Int(IntTy::I32) | RigidTy::Uint(UintTy::U32) => Some(4),
+        RigidTy::Int(IntTy::I64) | RigidTy::Uint(UintTy::U64) => Some(8),
+        RigidTy::Int(IntTy::I128) | RigidTy::Uint(UintTy::U128) => Some(16),
+        RigidTy::Float(FloatTy::F16) => Some(2),
+        RigidTy::Float(FloatTy::F32) => Some(4),
+        RigidTy::Float(FloatTy::F64) => Some(8),
+        RigidTy::Float(FloatTy::F128) => Some(16),
+        _ => None,
+    }
+}
+
+/// Size in bytes of an integer tag scalar
+fn scalar_int_size(scalar: &Scalar) -> Option<usize> {
+    let (Scalar::Initialized { value, .. } | Scalar::Union { value }) = scalar;
+    match value {
+        Primitive::Int { length, .. } => Some(match length {
+            IntegerLength::I8 => 1,
+            IntegerLength::I16 => 2,
+            IntegerLength::I32 => 4,
+            IntegerLength::I64 => 8,
+            IntegerLength::I128 => 16,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stable_mir::ty::UintTy;
+
+    fn entry(kind: TypeKind, size: Option<usize>, field_offsets: Vec<usize>) -> TypeEntry {
+        TypeEntry {
+            name: String::new(),
+            kind,
+            layout: size.map(|size| LayoutInfo {
+                size,
+                align: 4,
+                field_offsets,
+                variants: VariantLayout::Single(0),
+            }),
+        }
+    }
+
+    /// `[(u8, [u32; 2]); len]`
+    fn nested_arrays(len: Option<u64>) -> (TypeIndex, Ty) {
+        let [outer, pair, inner, word, byte] = [0, 1, 2, 3, 4].map(Ty::to_val);
+        let mut types = TypeIndex::new(8, false);
+        let array = |elem_ty, len| TypeKind::Array { elem_ty, len };
+        let outer_size = len.map(|len| 12 * len as usize);
+        types.insert(outer, entry(array(pair, len), outer_size, vec![]));
+        let pair_kind = TypeKind::Tuple {
+            fields: vec![byte, inner],
+        };
+        types.insert(pair, entry(pair_kind, Some(12), vec![0, 4]));
+        types.insert(inner, entry(array(word, Some(2)), Some(8), vec![0, 4]));
+        let uint = |ty| TypeKind::Primitive(RigidTy::Uint(ty));
+        types.insert(word, entry(uint(UintTy::U32), None, vec![]));
+        types.insert(byte, entry(uint(UintTy::U8), None, vec![]));
+        (types, outer)
+    }
+
+    #[test]
+    fn field_path_projects_into_nested_fields() {
+        let (types, ty) = nested_arrays(Some(3));
+        assert_eq!(types.field_path(ty, 0).as_deref(), Some(""));
+        assert_eq!(types.field_path(ty, 12).as_deref(), Some("[1]"));
+        assert_eq!(types.field_path(ty, 16).as_deref(), Some("[1].1"));
+        assert_eq!(types.field_path(ty, 32).as_deref(), Some("[2].1[1]"));
+        assert_eq!(types.field_path(ty, 34), None);
+    }
+
+    #[test]
+    fn field_path_stops_at_the_end_of_an_array() {
+        let (types, ty) = nested_arrays(Some(3));
+        assert_eq!(types.field_path(ty, 36), None);
+        assert_eq!(types.field_path(ty, 1200), None);
+        // slices have no length to check against
+        let (types, ty) = nested_arrays(None);
+        assert_eq!(types.field_path(ty, 36).as_deref(), Some("[3]"));
+    }
+
+    fn variants(discriminants: &[u128]) -> Vec<VariantInfo> {
+        discriminants
+            .iter()
+            .map(|&discriminant| VariantInfo {
+                discriminant,
+                fields: vec![],
+            })
+            .collect()
+    }
+
+    #[test]
+    fn direct_tag_matches_discriminants_in_tag_width() {
+        let tag = TagInfo {
+            offset: 0,
+            size: 1,
+            niche: None,
+        };
+        // `enum E { A = -1, B = 5 }` stores `A` as the byte 0xff
+        let variants = variants(&[u128::MAX, 5]);
+        assert_eq!(tag.variant(0xff, &variants), Some(0));
+        assert_eq!(tag.variant(5, &variants), Some(1));
+        assert_eq!(tag.variant(6, &variants), None);
+    }
+
+    #[test]
+    fn niche_tag_maps_values_outside_the_range_to_the_untagged_variant() {
+        // `Option<bool>`: `Some` holds the bool, `None` is the byte 2
+        let tag = TagInfo {
+            offset: 0,
+            size: 1,
+            niche: Some(NicheInfo {
+                untagged_variant: 1,
+                first: 0,
+                last: 0,
+                niche_start: 2,
+            }),
+        };
+        let variants = variants(&[0, 1]);
+        assert_eq!(tag.variant(2, &variants), Some(0));
+        assert_eq!(tag.variant(0, &variants), Some(1));
+        assert_eq!(tag.variant(1, &variants), Some(1));
+    }
+
+    #[test]
+    fn niche_tag_wraps_at_the_tag_width() {
+        // variants 1..=3 stored as 0xfe, 0xff, 0x00; variant 0 is untagged
+        let tag = TagInfo {
+            offset: 4,
+            size: 1,
+            niche: Some(NicheInfo {
+                untagged_variant: 0,
+                first: 1,
+                last: 3,
+                niche_start: 0xfe,
+            }),
+        };
+        let variants = variants(&[0, 1, 2, 3]);
+        assert_eq!(tag.variant(0xfe, &variants), Some(1));
+        assert_eq!(tag.variant(0xff, &variants), Some(2));
+        assert_eq!(tag.variant(0x00, &variants), Some(3));
+        assert_eq!(tag.variant(0x01, &variants), Some(0));
+        assert_eq!(tag.variant(0xfe, &variants[..1]), None);
+    }
+}
@@ -0,0 +1,112 @@
+//! How much of a value or label line the outputs show.
+//!
+//! Graph labels have to stay short, while Markdown reports can afford longer
+//! previews. [`LabelLimits::for_format`] starts from defaults for the output
+//! format and applies the overrides in `LABEL_LIMITS`, comma-separated
+//! `format.limit=N` entries such as `dot.string=40,md.elems=64`, where the
+//! format is named as in `OUTPUT_TO` and the limit is one of
+//! - `string`: characters of a string constant shown before `...`
+//! - `elems`: array and slice elements decoded before `..N more`
+//! - `depth`: nesting of aggregates decoded before `..`
+//! - `line`: characters of a block label line (`0` for no limit)
+
+use std::collections::HashMap;
+
+/// Truncation limits for the labels of one output format
+#[derive(Clone, Debug)]
+pub struct LabelLimits {
+    /// Maximum number of string characters shown in a preview
+    pub string_preview: usize,
+    /// Maximum number of array/slice elements shown before eliding the rest
+    pub decoded_elems: usize,
+    /// Nesting limit for decoded aggregates
+    pub decode_depth: usize,
+    /// Maximum characters of a label line, if lines are cut at all
+    pub line_chars: Option<usize>,
+}
+
+impl Default for LabelLimits {
+    fn default() -> Self {
+        Self {
+            string_preview: 20,
+            decoded_elems: 8,
+            decode_depth: 4,
+            line_chars: None,
+        }
+    }
+}
+
+impl LabelLimits {
+    /// Limits for output `format` (`dot`, `d2`, `svg`, `md`, ...), with the
+    /// `LABEL_LIMITS` overrides for it applied
+    pub fn for_format(format: &str) -> Self {
+        let mut limits = match format {
+            // no layout engine to wrap or shrink long lines
+            "svg" => Self {
+                line_chars: Some(60),
+                ..Self::default()
+            },
+            "md" => Self {
+                string_preview: 80,
+                decoded_elems: 32,
+                decode_depth: 6,
+                line_chars: None,
+            },
+            _ => Self::default(),
+        };
+        for ((fmt, limit), value) in overrides() {
+            if fmt != format {
+                continue;
+            }
+            match limit.as_str() {
+                "string" => limits.string_preview = *value,
+                "elems" => limits.decoded_elems = *value,
+                "depth" => limits.decode_depth = *value,
+                _ => limits.line_chars = (*value > 0).then_some(*value),
+            }
+        }
+        limits
+    }
+
+    /// `line` cut to the line limit, marked with `...` where it was cut
+    pub fn clip(&self, line: String) -> String {
+        match self.line_chars {
+            Some(max) if line.chars().count() > max => {
+                let kept: String = line.chars().take(max.saturating_sub(3)).collect();
+                format!("{}...", kept)
+            }
+            _ => line,
+        }
+    }
+}
+
+/// Limits set in `LABEL_LIMITS`, by format and limit name
+fn overrides() -> &'static HashMap<(String, String), usize> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<HashMap<(String, String), usize>> = OnceLock::new();
+    VAR.get_or_init(|| {
+        let Ok(spec) = std::env::var("LABEL_LIMITS") else {
+            return HashMap::new();
+        };
+        spec.split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let parsed = (|| {
+                    let (key, value) = entry.split_once('=')?;
+                    let (format, limit) = key.trim().split_once('.')?;
+                    let value = value.trim().parse().ok()?;
+                    matches!(limit, "string" | "elems" | "depth" | "line")
+                        .then(|| ((format.to_string(), limit.to_string()), value))
+                })();
+                parsed.unwrap_or_else(|| {
+                    panic!(
+                        "LABEL_LIMITS entry {:?} is not `format.limit=N` with limit one of \
+                         string, elems, depth, line",
+                        entry
+                    )
+                })
+            })
+            .collect()
+    })
+}
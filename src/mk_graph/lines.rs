@@ -0,0 +1,83 @@
+//! Grouping of blocks by the source line they come from.
+//!
+//! A single line such as `let x = v[i] + f(y)?;` can lower to a dozen blocks
+//! (bounds check, overflow check, call, `?` switch, drops). [`line_groups`]
+//! assigns every block to the line most of its statements (and its
+//! terminator) come from, so the DOT output can draw the blocks of one line in
+//! a cluster labeled with that line's source text. Enabled by setting
+//! `DOT_GROUP_LINES`.
+
+use std::collections::{BTreeMap, HashMap};
+
+use crate::compat::stable_mir;
+use stable_mir::mir::Body;
+
+use super::context::GraphContext;
+
+/// Whether `DOT_GROUP_LINES` is set
+pub fn group_lines_enabled() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("DOT_GROUP_LINES").is_ok())
+}
+
+/// Blocks of one body that come predominantly from the same source line
+pub struct LineGroup {
+    pub file: String,
+    pub line: usize,
+    /// Block indices, ascending
+    pub blocks: Vec<usize>,
+}
+
+impl LineGroup {
+    /// The trimmed source text of the line, or `file:line` if the file
+    /// cannot be read from the working directory (e.g. the standard library)
+    pub fn label(&self) -> String {
+        std::fs::read_to_string(&self.file)
+            .ok()
+            .and_then(|source| {
+                let text = source.lines().nth(self.line.checked_sub(1)?)?.trim();
+                (!text.is_empty()).then(|| format!("{}: {}", self.line, text))
+            })
+            .unwrap_or_else(|| format!("{}:{}", self.file, self.line))
+    }
+}
+
+/// Groups of at least two blocks sharing their predominant source line,
+/// ordered by their first block
+pub fn line_groups(body: &Body, ctx: &GraphContext) -> Vec<LineGroup> {
+    let mut by_line: BTreeMap<(String, usize), Vec<usize>> = BTreeMap::new();
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let spans = block
+            .statements
+            .iter()
+            .map(|s| &s.span)
+            .chain(std::iter::once(&block.terminator.span));
+        let mut counts: HashMap<(&str, usize), usize> = HashMap::new();
+        // first occurrence breaks ties, so the earliest statement wins
+        let mut order: Vec<(&str, usize)> = vec![];
+        for (file, line, ..) in spans.filter_map(|span| ctx.source_data(span)) {
+            let key = (file.as_str(), *line);
+            let count = counts.entry(key).or_default();
+            if *count == 0 {
+                order.push(key);
+            }
+            *count += 1;
+        }
+        let predominant = order.iter().copied().rev().max_by_key(|key| counts[key]);
+        if let Some((file, line)) = predominant {
+            by_line
+                .entry((file.to_string(), line))
+                .or_default()
+                .push(idx);
+        }
+    }
+
+    let mut groups: Vec<LineGroup> = by_line
+        .into_iter()
+        .filter(|(_, blocks)| blocks.len() > 1)
+        .map(|((file, line), blocks)| LineGroup { file, line, blocks })
+        .collect();
+    groups.sort_by_key(|g| g.blocks[0]);
+    groups
+}
@@ -0,0 +1,277 @@
+//! Liveness of locals and dead stores.
+//!
+//! A local is live at a point if its current value may still be read on some
+//! path from there. [`liveness`] computes the locals live on entry to and exit
+//! from every block by the usual backward dataflow over all control flow,
+//! cleanup edges included, treating a local as a whole: writing a field or
+//! through a projection reads the local and does not kill it. Locals whose
+//! address is taken are treated as live wherever they are defined, since
+//! reads through the reference are not tracked.
+//!
+//! [`dead_stores`] reports assignments whose value is overwritten or goes out
+//! of scope before it is read, which shows what the MIR optimizations left
+//! behind.
+
+use std::collections::HashSet;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    Body, Local, NonDivergingIntrinsic, Operand, Place, ProjectionElem, Rvalue, Statement,
+    StatementKind, Terminator, TerminatorKind, VarDebugInfoContents,
+};
+use stable_mir::ty::RigidTy;
+
+use super::messages::{fill, msg};
+use super::taint::Finding;
+use super::util::terminator_targets;
+
+/// Locals live at the boundaries of each block
+pub struct Liveness {
+    pub live_in: Vec<HashSet<Local>>,
+    pub live_out: Vec<HashSet<Local>>,
+}
+
+/// Locals read and the local (as a whole) written by one statement or
+/// terminator
+#[derive(Default)]
+pub struct Effects {
+    pub reads: Vec<Local>,
+    pub write: Option<Local>,
+}
+
+/// Live locals at the entry and exit of every block of `body`
+pub fn liveness(body: &Body) -> Liveness {
+    let n = body.blocks.len();
+    let successors: Vec<Vec<usize>> = body
+        .blocks
+        .iter()
+        .map(|b| {
+            terminator_targets(&b.terminator)
+                .into_iter()
+                .filter(|t| *t < n)
+                .collect()
+        })
+        .collect();
+    let mut live_in: Vec<HashSet<Local>> = vec![HashSet::new(); n];
+    let mut live_out: Vec<HashSet<Local>> = vec![HashSet::new(); n];
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for idx in (0..n).rev() {
+            let out: HashSet<Local> = successors[idx]
+                .iter()
+                .flat_map(|s| live_in[*s].iter().copied())
+                .collect();
+            let mut live = out.clone();
+            let block = &body.blocks[idx];
+            transfer(&mut live, &terminator_effects(&block.terminator));
+            for stmt in block.statements.iter().rev() {
+                transfer(&mut live, &statement_effects(stmt));
+            }
+            if live != live_in[idx] {
+                live_in[idx] = live;
+                changed = true;
+            }
+            live_out[idx] = out;
+        }
+    }
+    Liveness { live_in, live_out }
+}
+
+/// Step `live` backwards over one statement or terminator
+fn transfer(live: &mut HashSet<Local>, effects: &Effects) {
+    if let Some(local) = effects.write {
+        live.remove(&local);
+    }
+    live.extend(effects.reads.iter().copied());
+}
+
+/// Assignments in `body` whose value is never read
+pub fn dead_stores(body: &Body) -> Vec<Finding> {
+    let liveness = liveness(body);
+    let escaped = address_taken(body);
+    let mut findings = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let mut live = liveness.live_out[idx].clone();
+        transfer(&mut live, &terminator_effects(&block.terminator));
+        for stmt in block.statements.iter().rev() {
+            if let StatementKind::Assign(place, _) = &stmt.kind {
+                let local = place.local;
+                let reportable = place.projection.is_empty()
+                    && local != 0
+                    && !live.contains(&local)
+                    && !escaped.contains(&local)
+                    && !is_unit(body, local);
+                if reportable {
+                    let name = match local_name(body, local) {
+                        Some(name) => format!("{} (_{})", name, local),
+                        None => format!("_{}", local),
+                    };
+                    findings.push(Finding {
+                        rule: "dead-store",
+                        message: fill(msg("liveness.dead_store"), &[&name]),
+                        block: idx,
+                        span: stmt.span,
+                    });
+                }
+            }
+            transfer(&mut live, &statement_effects(stmt));
+        }
+    }
+    findings.reverse();
+    findings
+}
+
+// =============================================================================
+// Effects
+// =============================================================================
+
+/// What a statement reads and writes; `StorageLive` and `StorageDead` count as
+/// writes, ending the liveness of the previous value
+pub fn statement_effects(stmt: &Statement) -> Effects {
+    let mut effects = Effects::default();
+    match &stmt.kind {
+        StatementKind::Assign(place, rvalue) => {
+            rvalue_reads(rvalue, &mut effects.reads);
+            if place.projection.is_empty() {
+                effects.write = Some(place.local);
+            } else {
+                place_reads(place, &mut effects.reads);
+            }
+        }
+        StatementKind::StorageLive(local) | StatementKind::StorageDead(local) => {
+            effects.write = Some(*local)
+        }
+        StatementKind::FakeRead(_, place)
+        | StatementKind::SetDiscriminant { place, .. }
+        | StatementKind::Retag(_, place)
+        | StatementKind::PlaceMention(place)
+        | StatementKind::AscribeUserType { place, .. } => place_reads(place, &mut effects.reads),
+        StatementKind::Intrinsic(NonDivergingIntrinsic::Assume(op)) => {
+            operand_reads(op, &mut effects.reads)
+        }
+        StatementKind::Intrinsic(NonDivergingIntrinsic::CopyNonOverlapping(copy)) => {
+            operand_reads(&copy.src, &mut effects.reads);
+            operand_reads(&copy.dst, &mut effects.reads);
+            operand_reads(&copy.count, &mut effects.reads);
+        }
+        StatementKind::Deinit(_)
+        | StatementKind::Coverage(_)
+        | StatementKind::ConstEvalCounter {}
+        | StatementKind::Nop {} => {}
+    }
+    effects
+}
+
+/// What a terminator reads and writes
+pub fn terminator_effects(term: &Terminator) -> Effects {
+    let mut effects = Effects::default();
+    let reads = &mut effects.reads;
+    match &term.kind {
+        TerminatorKind::SwitchInt { discr, .. } => operand_reads(discr, reads),
+        TerminatorKind::Return {} => reads.push(0),
+        TerminatorKind::Drop { place, .. } => place_reads(place, reads),
+        TerminatorKind::Call {
+            func,
+            args,
+            destination,
+            ..
+        } => {
+            operand_reads(func, reads);
+            args.iter().for_each(|arg| operand_reads(arg, reads));
+            if destination.projection.is_empty() {
+                effects.write = Some(destination.local);
+            } else {
+                place_reads(destination, reads);
+            }
+        }
+        TerminatorKind::Assert { cond, .. } => operand_reads(cond, reads),
+        TerminatorKind::InlineAsm { operands, .. } => {
+            for op in operands {
+                if let Some(value) = &op.in_value {
+                    operand_reads(value, reads);
+                }
+                if let Some(place) = &op.out_place {
+                    place_reads(place, reads);
+                }
+            }
+        }
+        TerminatorKind::Goto { .. }
+        | TerminatorKind::Resume {}
+        | TerminatorKind::Abort {}
+        | TerminatorKind::Unreachable {} => {}
+    }
+    effects
+}
+
+fn place_reads(place: &Place, reads: &mut Vec<Local>) {
+    reads.push(place.local);
+    for elem in &place.projection {
+        if let ProjectionElem::Index(local) = elem {
+            reads.push(*local);
+        }
+    }
+}
+
+fn operand_reads(op: &Operand, reads: &mut Vec<Local>) {
+    if let Operand::Copy(place) | Operand::Move(place) = op {
+        place_reads(place, reads);
+    }
+}
+
+fn rvalue_reads(rvalue: &Rvalue, reads: &mut Vec<Local>) {
+    match rvalue {
+        Rvalue::AddressOf(_, place)
+        | Rvalue::Ref(_, _, place)
+        | Rvalue::CopyForDeref(place)
+        | Rvalue::Discriminant(place)
+        | Rvalue::Len(place) => place_reads(place, reads),
+        Rvalue::Aggregate(_, ops) => ops.iter().for_each(|op| operand_reads(op, reads)),
+        Rvalue::BinaryOp(_, a, b) | Rvalue::CheckedBinaryOp(_, a, b) => {
+            operand_reads(a, reads);
+            operand_reads(b, reads);
+        }
+        Rvalue::Cast(_, op, _)
+        | Rvalue::Repeat(op, _)
+        | Rvalue::ShallowInitBox(op, _)
+        | Rvalue::UnaryOp(_, op)
+        | Rvalue::Use(op) => operand_reads(op, reads),
+        Rvalue::ThreadLocalRef(_) | Rvalue::NullaryOp(..) => {}
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// Locals borrowed or turned into raw pointers somewhere in `body`
+fn address_taken(body: &Body) -> HashSet<Local> {
+    body.blocks
+        .iter()
+        .flat_map(|b| &b.statements)
+        .filter_map(|s| match &s.kind {
+            StatementKind::Assign(_, Rvalue::Ref(_, _, place) | Rvalue::AddressOf(_, place)) => {
+                Some(place.local)
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn is_unit(body: &Body, local: Local) -> bool {
+    matches!(
+        body.locals()[local].ty.kind().rigid(),
+        Some(RigidTy::Tuple(fields)) if fields.is_empty()
+    )
+}
+
+fn local_name(body: &Body, local: Local) -> Option<String> {
+    body.var_debug_info
+        .iter()
+        .find_map(|info| match &info.value {
+            VarDebugInfoContents::Place(p) if p.local == local && p.projection.is_empty() => {
+                Some(info.name.clone())
+            }
+            _ => None,
+        })
+}
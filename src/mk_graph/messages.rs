@@ -0,0 +1,586 @@
+//! Message catalog for the human-readable annotations in the outputs.
+//!
+//! Annotation and summary strings (`// MIR: 2 stmts, 1 call`, `for-loop
+//! header over xs`, ...) are looked up here by a stable message ID
+//! (`annotate.stmt`, `pattern.for_header`) in the language selected by
+//! `MIR_LANG` or `--lang=<code>`. Texts use `{}` placeholders, filled in order
+//! by [`fill`]. A message without a translation falls back to English, so
+//! adding a message never breaks other languages, and rewording the English
+//! text does not lose the translations.
+//!
+//! MIR syntax itself (statements, terminators, place names), rule IDs and
+//! the panic messages the compiled code carries are not translated.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Languages with a catalog
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    De,
+}
+
+impl Lang {
+    fn from_code(code: &str) -> Option<Self> {
+        match code.split(['_', '-', '.']).next()? {
+            "en" => Some(Lang::En),
+            "de" => Some(Lang::De),
+            _ => None,
+        }
+    }
+}
+
+/// Language selected by `MIR_LANG` (`de`, `de_DE`, ...; set by `--lang=<code>`);
+/// English by default
+pub fn lang() -> Lang {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Lang> = OnceLock::new();
+    *VAR.get_or_init(|| match std::env::var("MIR_LANG") {
+        Ok(code) => Lang::from_code(&code).unwrap_or_else(|| {
+            eprintln!("MIR_LANG={} has no message catalog, using English", code);
+            Lang::En
+        }),
+        Err(_) => Lang::En,
+    })
+}
+
+/// The text of message `id` in the selected language
+pub fn msg(id: &str) -> &'static str {
+    let en = || {
+        catalog(Lang::En)
+            .get(id)
+            .copied()
+            .unwrap_or_else(|| panic!("message {} is not in the English catalog", id))
+    };
+    match lang() {
+        Lang::En => en(),
+        lang => catalog(lang).get(id).copied().unwrap_or_else(en),
+    }
+}
+
+/// The text of a counted noun, choosing the singular or plural message
+pub fn msg_n(n: usize, singular: &str, plural: &str) -> &'static str {
+    if n == 1 {
+        msg(singular)
+    } else {
+        msg(plural)
+    }
+}
+
+/// The messages of `lang`, by ID
+fn catalog(lang: Lang) -> &'static HashMap<&'static str, &'static str> {
+    use std::sync::OnceLock;
+    static EN_CATALOG: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    static DE_CATALOG: OnceLock<HashMap<&str, &str>> = OnceLock::new();
+    match lang {
+        Lang::En => EN_CATALOG.get_or_init(|| EN.iter().copied().collect()),
+        Lang::De => DE_CATALOG.get_or_init(|| DE.iter().copied().collect()),
+    }
+}
+
+/// Replace the `{}` placeholders of `template` with `args`, in order
+pub fn fill(template: &str, args: &[&dyn Display]) -> String {
+    let mut out = String::new();
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    if let Some(first) = parts.next() {
+        out.push_str(first);
+    }
+    for part in parts {
+        if let Some(arg) = args.next() {
+            out.push_str(&arg.to_string());
+        }
+        out.push_str(part);
+    }
+    out
+}
+
+// =============================================================================
+// Catalogs
+// =============================================================================
+
+/// English texts; every message ID has one
+const EN: &[(&str, &str)] = &[
+    // per-line source annotations
+    ("annotate.stmt", "stmt"),
+    ("annotate.stmts", "stmts"),
+    ("annotate.call", "call"),
+    ("annotate.calls", "calls"),
+    ("annotate.assert", "assert"),
+    ("annotate.asserts", "asserts"),
+    ("annotate.drop", "drop"),
+    ("annotate.drops", "drops"),
+    ("annotate.branch", "branch"),
+    ("annotate.branches", "branches"),
+    ("annotate.return", "return"),
+    ("annotate.returns", "returns"),
+    ("annotate.cast", "cast"),
+    ("annotate.casts", "casts"),
+    ("annotate.borrow", "borrow"),
+    ("annotate.borrows", "borrows"),
+    ("annotate.checked", "checked {}"),
+    ("annotate.checked_plural", "checked {}s"),
+    ("annotate.from_expansion", "{} (from {} expansion)"),
+    (
+        "annotate.past_end",
+        "// line {} (past the end of the file): {}",
+    ),
+    // desugaring patterns
+    ("pattern.for_header", "for-loop header{}"),
+    ("pattern.for_body", "for-loop body{}"),
+    ("pattern.for_exit", "for-loop exit{}"),
+    ("pattern.for_over", " over {}"),
+    ("pattern.try_check", "? check"),
+    ("pattern.try_ok", "? ok, value unwrapped"),
+    ("pattern.try_return", "? early return with {} propagated"),
+    ("pattern.await", "await{} (yield point #{})"),
+    ("pattern.await_of", " of future {}"),
+    ("pattern.await_ready", "await #{} ready"),
+    ("pattern.await_pending", "await #{} pending, yields"),
+    // cast annotations
+    (
+        "cast.expose_address",
+        "exposes the provenance, address usable as integer",
+    ),
+    (
+        "cast.exposed_provenance",
+        "picks up a previously exposed provenance",
+    ),
+    ("cast.reify_fn_pointer", "fn item becomes a fn pointer"),
+    ("cast.unsafe_fn_pointer", "safe fn pointer becomes unsafe"),
+    (
+        "cast.closure_fn_pointer",
+        "non-capturing closure becomes a fn pointer",
+    ),
+    ("cast.mut_to_const", "drops mutability, same address"),
+    (
+        "cast.array_to_pointer",
+        "array pointer becomes element pointer",
+    ),
+    ("cast.unsize", "adds metadata (length or vtable)"),
+    ("cast.dyn_star", "boxes into a dyn* trait object"),
+    ("cast.int_to_int", "truncates, sign- or zero-extends"),
+    (
+        "cast.float_to_int",
+        "rounds toward zero, saturates, NaN to 0",
+    ),
+    ("cast.to_float", "rounds to nearest"),
+    (
+        "cast.ptr_to_ptr",
+        "changes the pointee type, keeps provenance",
+    ),
+    ("cast.fn_ptr_to_ptr", "fn pointer becomes a data pointer"),
+    ("cast.transmute", "reinterprets the bits, no validity check"),
+    // duplicated blocks and irreducible loops
+    ("duplicate.exact", "duplicate of {}"),
+    ("duplicate.similar", "same code as {}, other successors"),
+    ("cfg.irreducible", "irreducible loop over {}"),
+    // UbChecks branches
+    (
+        "ub_checks.enabled_branch",
+        "UB-check enabled branch (debug only)",
+    ),
+    (
+        "ub_checks.enabled",
+        "UB checks enabled, other branch left out",
+    ),
+    (
+        "ub_checks.disabled",
+        "UB checks disabled, other branch left out",
+    ),
+    // execution counts from BLOCK_COUNTS
+    ("counts.executed", "executed {} times"),
+    // storage liveness and dead stores
+    ("storage.dead_use", "_{} used where its storage may be dead"),
+    ("liveness.dead_store", "value assigned to {} is never read"),
+    // return value provenance
+    ("returns.comes_from", "return value comes from: {}"),
+    ("returns.or", "{} or {}"),
+    ("returns.call", "call to {}"),
+    ("returns.indirect_call", "indirect call"),
+    ("returns.parameter", "parameter {}"),
+    ("returns.aggregate", "aggregate {}"),
+    ("returns.arithmetic", "arithmetic"),
+    ("returns.reference", "reference to {}"),
+    ("returns.cast", "cast of {}"),
+    ("returns.computed", "computed value"),
+    ("returns.field_writes", "field writes"),
+    ("returns.variant", "variant {}"),
+    // block summaries
+    ("summary.computes", "computes {}"),
+    (
+        "summary.computes_checked",
+        "computes {} with overflow checks",
+    ),
+    ("summary.borrows", "borrows {}"),
+    ("summary.builds", "builds {}"),
+    ("summary.casts", "casts {}"),
+    ("summary.copies_one", "copies a value"),
+    ("summary.copies", "copies {} values"),
+    ("summary.discriminant", "reads the discriminant of {}"),
+    ("summary.calls", "calls {}"),
+    ("summary.branches", "branches on {}"),
+    ("summary.drops", "drops {}"),
+    ("summary.asserts", "asserts {}"),
+    ("summary.returns", "returns"),
+    ("summary.unreachable", "is unreachable"),
+    ("summary.then", "{}, then {}"),
+    // enum matches
+    ("match.other", "other: {}"),
+    ("match.other_unreachable", "other (unreachable)"),
+    // repeated copies of large values
+    ("copies.in_block", "{}-byte value copied {} times in bb{}"),
+    ("copies.of_local", "_{}: {}-byte value copied {} times"),
+    // transmute and pointer cast checks
+    ("casts.size", "transmute of {} ({} bytes) to {} ({} bytes)"),
+    (
+        "casts.fat_to_thin",
+        "{} cast to thin pointer {}, metadata dropped",
+    ),
+    (
+        "casts.align",
+        "pointer cast from {} (align {}) to {} (align {})",
+    ),
+    // constant-time and taint findings
+    (
+        "taint.ct_index",
+        "table lookup indexed by secret-dependent _{}",
+    ),
+    ("taint.ct_branch", "branch on secret-dependent _{}"),
+    ("taint.flow", "data from {} reaches {} (via {})"),
+    // unwind annotations
+    ("unwind.abort", "unwind: abort"),
+    ("unwind.impossible", "unwind: impossible"),
+    // graph placeholders and edge labels
+    ("graph.no_functions", "<no functions>"),
+    ("graph.empty_body", "<empty body>"),
+    ("graph.decided_at", "branch decided at {}"),
+    ("edge.other", "other"),
+    ("edge.cleanup", "Cleanup"),
+    // recursive calls
+    ("recursion.self", "recursive call"),
+    ("recursion.cycle", "part of recursion cycle with {}"),
+    // panic inventory
+    ("panics.title", "# Panic inventory of `{}`"),
+    (
+        "panics.header",
+        "| Function | Block | Kind | Message | Location |",
+    ),
+    ("panics.bounds_check", "index out of bounds"),
+    ("panics.misaligned", "misaligned pointer dereference"),
+    ("panics.assert", "assertion failed"),
+    // function properties in summary cards
+    ("property.recursive", "recursive"),
+    ("property.loops", "loops"),
+    ("property.irreducible", "irreducible"),
+    ("property.indirect_calls", "indirect_calls"),
+    ("property.may_panic", "may_panic"),
+    // SARIF rules and results
+    (
+        "sarif.ct_branch",
+        "Branch on a value derived from a secret parameter",
+    ),
+    (
+        "sarif.ct_index",
+        "Table lookup indexed by a value derived from a secret parameter",
+    ),
+    (
+        "sarif.taint_flow",
+        "Data from a taint source reaches a sink call",
+    ),
+    (
+        "sarif.cast_size",
+        "Transmute between types of different sizes",
+    ),
+    (
+        "sarif.cast_align",
+        "Pointer cast to a pointee with a stricter alignment",
+    ),
+    (
+        "sarif.cast_fat_to_thin",
+        "Cast of a fat pointer to a thin pointer, dropping its metadata",
+    ),
+    (
+        "sarif.dead_store",
+        "Assigned value is overwritten or goes out of scope before it is read",
+    ),
+    ("sarif.result", "{} (in {}, bb{})"),
+];
+
+/// German texts
+const DE: &[(&str, &str)] = &[
+    // per-line source annotations
+    ("annotate.stmt", "Anweisung"),
+    ("annotate.stmts", "Anweisungen"),
+    ("annotate.call", "Aufruf"),
+    ("annotate.calls", "Aufrufe"),
+    ("annotate.assert", "Prüfung"),
+    ("annotate.asserts", "Prüfungen"),
+    ("annotate.drop", "Drop"),
+    ("annotate.drops", "Drops"),
+    ("annotate.branch", "Verzweigung"),
+    ("annotate.branches", "Verzweigungen"),
+    ("annotate.return", "Rückkehr"),
+    ("annotate.returns", "Rückkehren"),
+    ("annotate.cast", "Umwandlung"),
+    ("annotate.casts", "Umwandlungen"),
+    ("annotate.borrow", "Ausleihe"),
+    ("annotate.borrows", "Ausleihen"),
+    ("annotate.checked", "{} (geprüft)"),
+    ("annotate.checked_plural", "{} (geprüft)"),
+    ("annotate.from_expansion", "{} (aus {}-Expansion)"),
+    (
+        "annotate.past_end",
+        "// Zeile {} (hinter dem Dateiende): {}",
+    ),
+    // desugaring patterns
+    ("pattern.for_header", "for-Schleife: Kopf{}"),
+    ("pattern.for_body", "for-Schleife: Rumpf{}"),
+    ("pattern.for_exit", "for-Schleife: Ausgang{}"),
+    ("pattern.for_over", " über {}"),
+    ("pattern.try_check", "?-Prüfung"),
+    ("pattern.try_ok", "? ok, Wert entpackt"),
+    (
+        "pattern.try_return",
+        "? vorzeitige Rückkehr, {} weitergereicht",
+    ),
+    ("pattern.await", "await{} (Yield-Punkt #{})"),
+    ("pattern.await_of", " auf Future {}"),
+    ("pattern.await_ready", "await #{} bereit"),
+    ("pattern.await_pending", "await #{} ausstehend, gibt ab"),
+    // cast annotations
+    (
+        "cast.expose_address",
+        "legt die Provenienz offen, Adresse als Ganzzahl nutzbar",
+    ),
+    (
+        "cast.exposed_provenance",
+        "übernimmt eine offengelegte Provenienz",
+    ),
+    ("cast.reify_fn_pointer", "Funktion wird zum Funktionszeiger"),
+    (
+        "cast.unsafe_fn_pointer",
+        "sicherer Funktionszeiger wird unsafe",
+    ),
+    (
+        "cast.closure_fn_pointer",
+        "Closure ohne Captures wird zum Funktionszeiger",
+    ),
+    ("cast.mut_to_const", "ohne Veränderbarkeit, gleiche Adresse"),
+    ("cast.array_to_pointer", "Array-Zeiger wird Elementzeiger"),
+    ("cast.unsize", "ergänzt Metadaten (Länge oder VTable)"),
+    ("cast.dyn_star", "verpackt in ein dyn*-Trait-Objekt"),
+    (
+        "cast.int_to_int",
+        "kürzt oder erweitert mit Vorzeichen oder Nullen",
+    ),
+    ("cast.float_to_int", "rundet gegen null, sättigt, NaN zu 0"),
+    ("cast.to_float", "rundet zum nächsten Wert"),
+    (
+        "cast.ptr_to_ptr",
+        "ändert den Zieltyp, behält die Provenienz",
+    ),
+    ("cast.fn_ptr_to_ptr", "Funktionszeiger wird Datenzeiger"),
+    (
+        "cast.transmute",
+        "deutet die Bits um, ohne Gültigkeitsprüfung",
+    ),
+    // duplicated blocks and irreducible loops
+    ("duplicate.exact", "Duplikat von {}"),
+    (
+        "duplicate.similar",
+        "gleicher Code wie {}, andere Nachfolger",
+    ),
+    ("cfg.irreducible", "irreduzible Schleife über {}"),
+    // UbChecks branches
+    (
+        "ub_checks.enabled_branch",
+        "Zweig mit UB-Prüfungen (nur Debug)",
+    ),
+    (
+        "ub_checks.enabled",
+        "UB-Prüfungen aktiv, anderer Zweig ausgelassen",
+    ),
+    (
+        "ub_checks.disabled",
+        "UB-Prüfungen inaktiv, anderer Zweig ausgelassen",
+    ),
+    // execution counts from BLOCK_COUNTS
+    ("counts.executed", "{}-mal ausgeführt"),
+    // storage liveness and dead stores
+    (
+        "storage.dead_use",
+        "_{} verwendet, wo sein Speicher tot sein kann",
+    ),
+    (
+        "liveness.dead_store",
+        "der {} zugewiesene Wert wird nie gelesen",
+    ),
+    // return value provenance
+    ("returns.comes_from", "Rückgabewert stammt aus: {}"),
+    ("returns.or", "{} oder {}"),
+    ("returns.call", "Aufruf von {}"),
+    ("returns.indirect_call", "indirektem Aufruf"),
+    ("returns.parameter", "Parameter {}"),
+    ("returns.aggregate", "Aggregat {}"),
+    ("returns.arithmetic", "Arithmetik"),
+    ("returns.reference", "Referenz auf {}"),
+    ("returns.cast", "Umwandlung von {}"),
+    ("returns.computed", "berechnetem Wert"),
+    ("returns.field_writes", "Feldzuweisungen"),
+    ("returns.variant", "Variante {}"),
+    // block summaries
+    ("summary.computes", "berechnet {}"),
+    (
+        "summary.computes_checked",
+        "berechnet {} mit Überlaufprüfung",
+    ),
+    ("summary.borrows", "leiht {}"),
+    ("summary.builds", "baut {}"),
+    ("summary.casts", "wandelt {} um"),
+    ("summary.copies_one", "kopiert einen Wert"),
+    ("summary.copies", "kopiert {} Werte"),
+    ("summary.discriminant", "liest die Diskriminante von {}"),
+    ("summary.calls", "ruft {} auf"),
+    ("summary.branches", "verzweigt nach {}"),
+    ("summary.drops", "gibt {} frei"),
+    ("summary.asserts", "prüft {}"),
+    ("summary.returns", "kehrt zurück"),
+    ("summary.unreachable", "ist unerreichbar"),
+    ("summary.then", "{}, dann {}"),
+    // enum matches
+    ("match.other", "sonst: {}"),
+    ("match.other_unreachable", "sonst (unerreichbar)"),
+    // repeated copies of large values
+    ("copies.in_block", "{}-Byte-Wert {}-mal kopiert in bb{}"),
+    ("copies.of_local", "_{}: {}-Byte-Wert {}-mal kopiert"),
+    // transmute and pointer cast checks
+    (
+        "casts.size",
+        "transmute von {} ({} Bytes) nach {} ({} Bytes)",
+    ),
+    (
+        "casts.fat_to_thin",
+        "{} in dünnen Zeiger {} umgewandelt, Metadaten verworfen",
+    ),
+    (
+        "casts.align",
+        "Zeigerumwandlung von {} (Ausrichtung {}) nach {} (Ausrichtung {})",
+    ),
+    // constant-time and taint findings
+    (
+        "taint.ct_index",
+        "Tabellenzugriff mit geheimnisabhängigem Index _{}",
+    ),
+    (
+        "taint.ct_branch",
+        "Verzweigung nach geheimnisabhängigem _{}",
+    ),
+    ("taint.flow", "Daten aus {} erreichen {} (über {})"),
+    // unwind annotations
+    ("unwind.abort", "Abwicklung: Abbruch"),
+    ("unwind.impossible", "Abwicklung: unmöglich"),
+    // graph placeholders and edge labels
+    ("graph.no_functions", "<keine Funktionen>"),
+    ("graph.empty_body", "<leerer Rumpf>"),
+    ("graph.decided_at", "Verzweigung entschieden in {}"),
+    ("edge.other", "sonst"),
+    ("edge.cleanup", "Aufräumen"),
+    // recursive calls
+    ("recursion.self", "rekursiver Aufruf"),
+    ("recursion.cycle", "Teil eines Rekursionszyklus mit {}"),
+    // panic inventory
+    ("panics.title", "# Panikstellen in `{}`"),
+    (
+        "panics.header",
+        "| Funktion | Block | Art | Meldung | Ort |",
+    ),
+    ("panics.bounds_check", "Index außerhalb der Grenzen"),
+    (
+        "panics.misaligned",
+        "Dereferenzierung eines falsch ausgerichteten Zeigers",
+    ),
+    ("panics.assert", "Prüfung fehlgeschlagen"),
+    // function properties in summary cards
+    ("property.recursive", "rekursiv"),
+    ("property.loops", "Schleifen"),
+    ("property.irreducible", "irreduzibel"),
+    ("property.indirect_calls", "indirekte Aufrufe"),
+    ("property.may_panic", "kann paniken"),
+    // SARIF rules and results
+    (
+        "sarif.ct_branch",
+        "Verzweigung nach einem Wert, der von einem geheimen Parameter abhängt",
+    ),
+    (
+        "sarif.ct_index",
+        "Tabellenzugriff mit einem Index, der von einem geheimen Parameter abhängt",
+    ),
+    (
+        "sarif.taint_flow",
+        "Daten aus einer Taint-Quelle erreichen einen Senken-Aufruf",
+    ),
+    (
+        "sarif.cast_size",
+        "Transmute zwischen Typen verschiedener Größe",
+    ),
+    (
+        "sarif.cast_align",
+        "Zeigerumwandlung auf einen Zieltyp mit strengerer Ausrichtung",
+    ),
+    (
+        "sarif.cast_fat_to_thin",
+        "Umwandlung eines breiten in einen dünnen Zeiger, die Metadaten gehen verloren",
+    ),
+    (
+        "sarif.dead_store",
+        "Zugewiesener Wert wird überschrieben oder ungültig, bevor er gelesen wird",
+    ),
+    ("sarif.result", "{} (in {}, bb{})"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn message_ids_are_unique() {
+        for table in [EN, DE] {
+            let ids: std::collections::HashSet<&str> = table.iter().map(|(id, _)| *id).collect();
+            assert_eq!(ids.len(), table.len());
+        }
+    }
+
+    #[test]
+    fn translations_match_english_messages() {
+        for (id, text) in DE {
+            let english = catalog(Lang::En)
+                .get(id)
+                .unwrap_or_else(|| panic!("{} is not an English message", id));
+            assert_eq!(
+                text.matches("{}").count(),
+                english.matches("{}").count(),
+                "placeholders of {}",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn every_message_is_translated() {
+        for (id, _) in EN {
+            assert!(
+                catalog(Lang::De).contains_key(id),
+                "{} has no German text",
+                id
+            );
+        }
+    }
+
+    #[test]
+    fn fill_replaces_placeholders_in_order() {
+        assert_eq!(fill("{} or {}", &[&"a", &1]), "a or 1");
+        assert_eq!(fill("{} or {}", &[&"a"]), "a or ");
+    }
+}
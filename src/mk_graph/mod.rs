@@ -1,20 +1,55 @@
 //! MIR graph generation module.
 //!
 //! This module provides functionality to generate graph visualizations
-//! of Rust's MIR in various formats (DOT, D2).
+//! of Rust's MIR in various formats (DOT, D2, SVG), source copies annotated
+//! with per-line MIR summaries, compact per-function summary cards, SARIF
+//! logs of analysis findings, item dependency graphs, arithmetic overflow
+//! and repeated-copy reports, panic inventories, reports of spans whose
+//! source cannot be shown, CFG skeletons, CFG diffs of a function between two
+//! `*.smir.json` files, and block counter modules for instrumented builds.
 
-use std::fs::File;
+use std::collections::HashMap;
 use std::io::{self, Write};
+use std::path::Path;
 
 use crate::compat::middle::ty::TyCtxt;
-use crate::compat::output::{mir_output_path, OutputDest};
-use crate::printer::collect_smir;
+use crate::compat::spans::{crate_root_file, macro_origin};
+use crate::compat::stable_mir::ty::IndexedVal;
+use crate::pipeline::{is_shared_destination, Document, Pipeline};
+use crate::printer::{collect_smir, load_smir_json, SmirJson};
+use crate::timings::time;
+use crate::MonoItemKind;
+use budgets::Budgets;
+use output::annotate::MacroOrigins;
 
 // Sub-modules
+pub mod analysis;
+pub mod budgets;
+pub mod casts;
+pub mod cfg;
+pub mod clusters;
 pub mod context;
+pub mod copies;
+pub mod counts;
+pub mod decode;
+pub mod duplicates;
 pub mod index;
+pub mod limits;
+pub mod lines;
+pub mod liveness;
+pub mod messages;
 pub mod output;
+pub mod patterns;
+pub mod returns;
+pub mod simplify;
+pub mod skeleton;
+pub mod spancheck;
+pub mod storage;
+pub mod taint;
+pub mod ubchecks;
 pub mod util;
+pub mod variants;
+pub mod visit;
 
 // Re-exports for convenience
 pub use context::GraphContext;
@@ -25,38 +60,226 @@ pub use util::GraphLabelString;
 // Entry Points
 // =============================================================================
 
+/// Collect the crate, render it with `render` and write the result as
+/// `extension`, timing each step
+fn emit(tcx: TyCtxt<'_>, extension: &str, render: fn(SmirJson) -> String) {
+    let smir = collect_smir(tcx);
+    let text = time(&format!("render {}", extension), || render(smir));
+    time("write", || {
+        Pipeline::from_env().write(tcx, Document::new(extension, text))
+    })
+    .unwrap_or_else(|e| panic!("Failed to write {}: {}", extension, e));
+}
+
+/// Collect the crate and stream it through `render` into the output
+/// `extension` (see [`Pipeline::stream`])
+fn emit_streamed(
+    tcx: TyCtxt<'_>,
+    extension: &str,
+    render: fn(SmirJson, &mut dyn Write) -> io::Result<()>,
+) {
+    let smir = collect_smir(tcx);
+    time(&format!("render {}", extension), || {
+        Pipeline::from_env().stream(tcx, extension, |out| render(smir, out))
+    })
+    .unwrap_or_else(|e| panic!("Failed to write {}: {}", extension, e));
+}
+
 /// Entry point to write the DOT file
 pub fn emit_dotfile(tcx: TyCtxt<'_>) {
-    let smir_dot = collect_smir(tcx).to_dot_file();
+    emit_streamed(tcx, "smir.dot", SmirJson::write_dot_file);
+}
 
-    match mir_output_path(tcx, "smir.dot") {
-        OutputDest::Stdout => {
-            write!(io::stdout(), "{}", smir_dot).expect("Failed to write smir.dot");
-        }
-        OutputDest::File(path) => {
-            let mut b = io::BufWriter::new(
-                File::create(&path)
-                    .unwrap_or_else(|e| panic!("Failed to create {}: {}", path.display(), e)),
+/// Entry point to write the D2 file
+pub fn emit_d2file(tcx: TyCtxt<'_>) {
+    emit_streamed(tcx, "smir.d2", SmirJson::write_d2_file);
+}
+
+/// Entry point to write the SVG file
+pub fn emit_svgfile(tcx: TyCtxt<'_>) {
+    emit(tcx, "smir.svg", SmirJson::to_svg_file);
+}
+
+/// Entry point to write the block structure of every function
+pub fn emit_skeleton(tcx: TyCtxt<'_>) {
+    emit(tcx, "smir.skeleton.json", SmirJson::to_skeleton_json);
+}
+
+/// Entry point to write the per-function summary cards
+pub fn emit_summary_cards(tcx: TyCtxt<'_>) {
+    emit(tcx, "smir.summary.json", SmirJson::to_summary_cards);
+}
+
+/// Entry point to write the item dependency graph
+pub fn emit_dependency_graph(tcx: TyCtxt<'_>) {
+    emit(tcx, "smir.deps.json", SmirJson::to_dependency_graph);
+}
+
+/// Entry point to write analysis findings as a SARIF log
+pub fn emit_sarif(tcx: TyCtxt<'_>) {
+    emit(tcx, "smir.sarif", SmirJson::to_sarif);
+}
+
+/// Entry point to write the arithmetic overflow report as CSV
+pub fn emit_arith_csv(tcx: TyCtxt<'_>) {
+    emit(tcx, "arith.csv", SmirJson::to_arith_csv);
+}
+
+/// Entry point to write the arithmetic overflow report as Markdown
+pub fn emit_arith_markdown(tcx: TyCtxt<'_>) {
+    emit(tcx, "arith.md", SmirJson::to_arith_markdown);
+}
+
+/// Entry point to write the block counter module for an instrumented build
+pub fn emit_counter_harness(tcx: TyCtxt<'_>) {
+    emit(tcx, "counters.rs", SmirJson::to_counter_harness);
+}
+
+/// Entry point to write the repeated-copy hints as Markdown
+pub fn emit_copies_markdown(tcx: TyCtxt<'_>) {
+    emit(tcx, "copies.md", SmirJson::to_copies_markdown);
+}
+
+/// Entry point to write the panic inventory as Markdown
+pub fn emit_panics_markdown(tcx: TyCtxt<'_>) {
+    emit(tcx, "panics.md", SmirJson::to_panics_markdown);
+}
+
+/// Entry point to write the panic inventory as JSON
+pub fn emit_panics_json(tcx: TyCtxt<'_>) {
+    emit(tcx, "panics.json", SmirJson::to_panics_json);
+}
+
+/// Entry point to write annotated copies of the crate's source files.
+///
+/// Each source file becomes its own output file, named by its path relative
+/// to the directory of the crate root (`src/parser/mod.rs` becomes
+/// `parser.mod.annotated.rs` next to `main.annotated.rs`). Standard output can
+/// only carry one of them unambiguously, so writing several there is an
+/// error rather than an interleaved stream.
+pub fn emit_annotated_sources(tcx: TyCtxt<'_>) {
+    let smir = collect_smir(tcx);
+    let macros = macro_origins(tcx, &smir);
+    let annotated = time("render annotated sources", || {
+        smir.to_annotated_sources(&macros)
+    });
+
+    if annotated.len() > 1 && is_shared_destination(tcx, &Document::new("annotated.rs", "")) {
+        let sources: Vec<&str> = annotated.iter().map(|(s, _)| s.as_str()).collect();
+        panic!(
+            "--annotate produces one file per source file ({}) and cannot multiplex them \
+             onto one output; use --out-dir instead of -o - or OUTPUT_TO",
+            sources.join(", ")
+        );
+    }
+
+    let root_file = crate_root_file(tcx);
+    let root = Path::new(&root_file).parent().unwrap_or(Path::new(""));
+    let mut written: HashMap<String, String> = HashMap::new();
+    let pipeline = Pipeline::from_env();
+    for (source, text) in annotated {
+        let name = annotated_name(Path::new(&source), root);
+        if let Some(other) = written.insert(name.clone(), source.clone()) {
+            panic!(
+                "--annotate would write both {} and {} to {}.annotated.rs",
+                other, source, name
             );
-            write!(b, "{}", smir_dot).expect("Failed to write smir.dot");
         }
+        pipeline
+            .write(tcx, Document::new(format!("{}.annotated.rs", name), text))
+            .unwrap_or_else(|e| panic!("Failed to write {}.annotated.rs: {}", name, e));
     }
 }
 
-/// Entry point to write the D2 file
-pub fn emit_d2file(tcx: TyCtxt<'_>) {
-    let smir_d2 = collect_smir(tcx).to_d2_file();
+/// Output name of the annotated copy of `source`: its path relative to
+/// `root` (or its whole path, for files outside it) without the extension,
+/// with `.` between the directories
+fn annotated_name(source: &Path, root: &Path) -> String {
+    let relative = source.strip_prefix(root).unwrap_or(source);
+    let parts: Vec<String> = relative
+        .with_extension("")
+        .components()
+        .filter_map(|c| match c {
+            std::path::Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            _ => None,
+        })
+        .collect();
+    if parts.is_empty() {
+        "source".to_string()
+    } else {
+        parts.join(".")
+    }
+}
+
+/// Entry point to write the report of spans whose source cannot be shown
+pub fn emit_span_report(tcx: TyCtxt<'_>) {
+    let smir = collect_smir(tcx);
+    let macros = macro_origins(tcx, &smir);
+    let text = time("render spans.md", || smir.to_span_report(&macros));
+    time("write", || {
+        Pipeline::from_env().write(tcx, Document::new("spans.md", text))
+    })
+    .unwrap_or_else(|e| panic!("Failed to write spans.md: {}", e));
+}
+
+/// Entry point to check the crate against the budgets in the file named in
+/// `BUDGETS`; prints the violations to standard error and returns whether
+/// there were none
+pub fn emit_budget_check(tcx: TyCtxt<'_>) -> bool {
+    let path = std::env::var("BUDGETS").expect("BUDGETS is set by --check-budgets");
+    let budgets = Budgets::from_file(&path);
+    let smir = collect_smir(tcx);
+    let violations = time("check budgets", || budgets.check(&smir));
+    if violations.is_empty() {
+        eprintln!("budgets of `{}` in {} are met", smir.name, path);
+        return true;
+    }
+    eprintln!(
+        "budgets of `{}` in {}: {} violations",
+        smir.name,
+        path,
+        violations.len()
+    );
+    for v in &violations {
+        eprintln!("  {}: {}", v.scope, v.message);
+    }
+    false
+}
 
-    match mir_output_path(tcx, "smir.d2") {
-        OutputDest::Stdout => {
-            write!(io::stdout(), "{}", smir_d2).expect("Failed to write smir.d2");
+/// Entry point to write the CFG diff of `function` between two `*.smir.json`
+/// files as DOT to standard output; runs without a compiler session
+pub fn emit_function_diff(old_path: &str, new_path: &str, function: &str) -> Result<(), String> {
+    let read = |path: &str| load_smir_json(path).map_err(|e| e.to_string());
+    let dot = output::diff::diff_dot(&read(old_path)?, &read(new_path)?, function)?;
+    print!("{}", dot);
+    Ok(())
+}
+
+/// Find the statements and terminators that come from macro expansions
+fn macro_origins(tcx: TyCtxt<'_>, smir: &SmirJson) -> MacroOrigins {
+    let mut origins = MacroOrigins::new();
+    for item in &smir.items {
+        let (MonoItemKind::MonoItemFn {
+            body: Some(body), ..
         }
-        OutputDest::File(path) => {
-            let mut b = io::BufWriter::new(
-                File::create(&path)
-                    .unwrap_or_else(|e| panic!("Failed to create {}: {}", path.display(), e)),
-            );
-            write!(b, "{}", smir_d2).expect("Failed to write smir.d2");
+        | MonoItemKind::MonoItemStatic {
+            body: Some(body), ..
+        }) = &item.mono_item_kind
+        else {
+            continue;
+        };
+        for block in &body.blocks {
+            let spans = block
+                .statements
+                .iter()
+                .map(|s| &s.span)
+                .chain(std::iter::once(&block.terminator.span));
+            for span in spans {
+                if let Some(origin) = macro_origin(tcx, span) {
+                    origins.insert(span.to_index(), origin);
+                }
+            }
         }
     }
+    origins
 }
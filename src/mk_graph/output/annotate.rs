@@ -0,0 +1,221 @@
+//! Source copies annotated with per-line MIR summaries.
+//!
+//! Every source line that MIR statements or terminators were lowered from
+//! gets a trailing comment such as `// MIR: 2 stmts, 1 checked add, 1 call`,
+//! which is handy for code listings where a full graph is too much.
+//! Statements lowered from a macro expansion are counted at the macro's call
+//! site and tagged with the macro name (`(from vec! expansion)`).
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Rvalue, StatementKind, TerminatorKind};
+use stable_mir::ty::{IndexedVal, Span};
+
+use crate::compat::spans::SourceData;
+use crate::mk_graph::messages::{fill, msg, msg_n};
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+/// What was lowered from one source line
+#[derive(Default)]
+struct LineSummary {
+    stmts: usize,
+    /// Notable operations in order of first appearance, with counts
+    ops: Vec<(String, usize)>,
+    /// Macros whose expansions contributed to this line
+    macros: Vec<String>,
+}
+
+impl LineSummary {
+    fn bump(&mut self, op: String) {
+        match self.ops.iter_mut().find(|(o, _)| *o == op) {
+            Some((_, n)) => *n += 1,
+            None => self.ops.push((op, 1)),
+        }
+    }
+
+    fn comment(&self) -> String {
+        let mut parts = vec![];
+        if self.stmts > 0 {
+            parts.push(plural(self.stmts, "stmt"));
+        }
+        parts.extend(self.ops.iter().map(|(op, n)| plural(*n, op)));
+        if self.macros.is_empty() {
+            format!("// MIR: {}", parts.join(", "))
+        } else {
+            format!(
+                "// MIR: {}",
+                fill(
+                    msg("annotate.from_expansion"),
+                    &[&parts.join(", "), &self.macros.join(", ")]
+                )
+            )
+        }
+    }
+}
+
+/// `n` with the (translated) singular or plural of an operation name
+fn plural(n: usize, what: &str) -> String {
+    let noun = match what {
+        "stmt" => msg_n(n, "annotate.stmt", "annotate.stmts"),
+        "call" => msg_n(n, "annotate.call", "annotate.calls"),
+        "assert" => msg_n(n, "annotate.assert", "annotate.asserts"),
+        "drop" => msg_n(n, "annotate.drop", "annotate.drops"),
+        "branch" => msg_n(n, "annotate.branch", "annotate.branches"),
+        "return" => msg_n(n, "annotate.return", "annotate.returns"),
+        "cast" => msg_n(n, "annotate.cast", "annotate.casts"),
+        "borrow" => msg_n(n, "annotate.borrow", "annotate.borrows"),
+        _ => {
+            return match what.strip_prefix("checked ") {
+                Some(op) => format!(
+                    "{} {}",
+                    n,
+                    fill(
+                        msg_n(n, "annotate.checked", "annotate.checked_plural"),
+                        &[&op]
+                    )
+                ),
+                None if n == 1 => format!("{} {}", n, what),
+                None => format!("{} {}s", n, what),
+            }
+        }
+    };
+    format!("{} {}", n, noun)
+}
+
+/// Per-file, per-line summaries keyed by span file name and 1-based line
+type LineSummaries = BTreeMap<String, BTreeMap<usize, LineSummary>>;
+
+/// Span index to (macro name, call-site location) for spans inside macro
+/// expansions; see [`crate::compat::spans::macro_origin`]
+pub type MacroOrigins = HashMap<usize, (String, SourceData)>;
+
+impl SmirJson {
+    /// Annotate the source files that bodies were lowered from.
+    ///
+    /// Returns `(source file, annotated text)` pairs. Files that cannot be
+    /// read from the working directory (e.g. the standard library) are skipped
+    /// and listed on standard error; summaries of lines past the end of a
+    /// file are appended to it.
+    pub fn to_annotated_sources(self, macros: &MacroOrigins) -> Vec<(String, String)> {
+        let spans: HashMap<usize, &SourceData> = self.spans.iter().map(|(i, d)| (*i, d)).collect();
+        let mut summaries = LineSummaries::new();
+
+        for item in &self.items {
+            match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    body: Some(body), ..
+                }
+                | MonoItemKind::MonoItemStatic {
+                    body: Some(body), ..
+                } => summarize_body(body, &spans, macros, &mut summaries),
+                _ => {}
+            }
+        }
+
+        let mut unreadable = vec![];
+        let annotated = summaries
+            .into_iter()
+            .filter_map(|(file, lines)| {
+                let Ok(source) = std::fs::read_to_string(Path::new(&file)) else {
+                    unreadable.push(file);
+                    return None;
+                };
+                let mut annotated = source
+                    .lines()
+                    .enumerate()
+                    .map(|(i, line)| match lines.get(&(i + 1)) {
+                        Some(summary) => format!("{}  {}", line, summary.comment()),
+                        None => line.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                // lines the file no longer has, e.g. because it changed since
+                // compilation, are listed at the end rather than dropped
+                let len = annotated.len();
+                for (line, summary) in lines.range(len + 1..) {
+                    annotated.push(fill(msg("annotate.past_end"), &[&line, &summary.comment()]));
+                }
+                Some((file, annotated.join("\n") + "\n"))
+            })
+            .collect();
+        if !unreadable.is_empty() {
+            eprintln!(
+                "--annotate: {} source files cannot be read from the working directory and \
+                 are not annotated (see --span-report): {}",
+                unreadable.len(),
+                unreadable.join(", ")
+            );
+        }
+        annotated
+    }
+}
+
+fn summarize_body(
+    body: &Body,
+    spans: &HashMap<usize, &SourceData>,
+    macros: &MacroOrigins,
+    summaries: &mut LineSummaries,
+) {
+    for block in &body.blocks {
+        for stmt in &block.statements {
+            use StatementKind::*;
+            if matches!(stmt.kind, StorageLive(_) | StorageDead(_) | Nop {}) {
+                continue;
+            }
+            let Some(summary) = line_summary(spans, macros, summaries, &stmt.span) else {
+                continue;
+            };
+            summary.stmts += 1;
+            if let Assign(_, rvalue) = &stmt.kind {
+                match rvalue {
+                    Rvalue::CheckedBinaryOp(op, _, _) => {
+                        summary.bump(format!("checked {:?}", op).to_lowercase())
+                    }
+                    Rvalue::Cast(..) => summary.bump("cast".to_string()),
+                    Rvalue::Ref(..) => summary.bump("borrow".to_string()),
+                    _ => {}
+                }
+            }
+        }
+
+        let op = match &block.terminator.kind {
+            TerminatorKind::Call { .. } => "call",
+            TerminatorKind::Assert { .. } => "assert",
+            TerminatorKind::Drop { .. } => "drop",
+            TerminatorKind::SwitchInt { .. } => "branch",
+            TerminatorKind::Return {} => "return",
+            _ => continue,
+        };
+        if let Some(summary) = line_summary(spans, macros, summaries, &block.terminator.span) {
+            summary.bump(op.to_string());
+        }
+    }
+}
+
+/// The summary for the line a span belongs to; spans inside macro expansions
+/// go to the macro's call site, which records the macro name
+fn line_summary<'s>(
+    spans: &HashMap<usize, &SourceData>,
+    macros: &MacroOrigins,
+    summaries: &'s mut LineSummaries,
+    span: &Span,
+) -> Option<&'s mut LineSummary> {
+    let (location, macro_name) = match macros.get(&span.to_index()) {
+        Some((name, site)) => (site, Some(name)),
+        None => (*spans.get(&span.to_index())?, None),
+    };
+    let (file, lo_line, ..) = location;
+    let summary = summaries
+        .entry(file.clone())
+        .or_default()
+        .entry(*lo_line)
+        .or_default();
+    if let Some(name) = macro_name {
+        if !summary.macros.contains(name) {
+            summary.macros.push(name.clone());
+        }
+    }
+    Some(summary)
+}
@@ -0,0 +1,246 @@
+//! Overflow-behavior report of the arithmetic in a crate.
+//!
+//! Every integer arithmetic operation (binary operators, negation, and the
+//! arithmetic intrinsics) becomes one row: the operator, its operand types,
+//! how overflow is handled, and whether the operation has a panic path. The
+//! report is written as CSV for spreadsheets or as a Markdown table for audit
+//! documents.
+//!
+//! Overflow handling is read off the MIR shape:
+//! - `checked`: a `CheckedBinaryOp` or an operation guarded by an overflow or
+//!   division assert (panics), or a `*_with_overflow` intrinsic (returns a flag)
+//! - `wrapping`: a plain operator without a guard, or a `wrapping_*` intrinsic
+//! - `unchecked`: an `*Unchecked` operator or `unchecked_*` intrinsic, where
+//!   overflow is undefined behavior
+//! - `saturating`: a `saturating_*` intrinsic
+
+use std::collections::HashSet;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    AssertMessage, BinOp, Body, Operand, Rvalue, StatementKind, TerminatorKind, UnOp,
+};
+use stable_mir::ty::RigidTy;
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::util::{callee_path, CallCategory};
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+/// One arithmetic operation
+pub struct ArithRow {
+    pub function: String,
+    pub block: usize,
+    /// `Add`, `Shl`, `Neg`, ...
+    pub operator: String,
+    pub operand_types: Vec<String>,
+    /// `checked`, `wrapping`, `unchecked` or `saturating`
+    pub behavior: &'static str,
+    /// Whether overflow (or division by zero) panics
+    pub panics: bool,
+}
+
+impl SmirJson {
+    /// The arithmetic report as CSV, one row per operation
+    pub fn to_arith_csv(self) -> String {
+        let mut out = String::from("function,block,operator,operand_types,behavior,panics\n");
+        for row in arith_rows(&self, "csv") {
+            let fields = [
+                row.function,
+                format!("bb{}", row.block),
+                row.operator,
+                row.operand_types.join(", "),
+                row.behavior.to_string(),
+                row.panics.to_string(),
+            ];
+            let fields: Vec<String> = fields.iter().map(|f| csv_field(f)).collect();
+            out.push_str(&fields.join(","));
+            out.push('\n');
+        }
+        out
+    }
+
+    /// The arithmetic report as a Markdown document with one table
+    pub fn to_arith_markdown(self) -> String {
+        let rows = arith_rows(&self, "md");
+        let mut out = format!("# Arithmetic in `{}`\n\n", self.name);
+        for behavior in ["checked", "wrapping", "unchecked", "saturating"] {
+            let count = rows.iter().filter(|r| r.behavior == behavior).count();
+            out.push_str(&format!("- {}: {}\n", behavior, count));
+        }
+        out.push_str("\n| Function | Block | Operator | Operand types | Behavior | Panics |\n");
+        out.push_str("|---|---|---|---|---|---|\n");
+        for row in rows {
+            out.push_str(&format!(
+                "| `{}` | bb{} | {} | {} | {} | {} |\n",
+                row.function.replace('|', "\\|"),
+                row.block,
+                row.operator,
+                row.operand_types.join(", ").replace('|', "\\|"),
+                row.behavior,
+                if row.panics { "yes" } else { "no" }
+            ));
+        }
+        out
+    }
+}
+
+/// Rows for all function bodies of the crate, by function name and block,
+/// with the label limits of output `format`
+fn arith_rows(smir: &SmirJson, format: &str) -> Vec<ArithRow> {
+    let ctx = GraphContext::with_limits(smir, LabelLimits::for_format(format));
+    let mut rows: Vec<ArithRow> = smir
+        .items
+        .iter()
+        .filter_map(|item| match &item.mono_item_kind {
+            MonoItemKind::MonoItemFn {
+                name,
+                body: Some(body),
+                ..
+            } => Some(body_rows(&ctx, name, body)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    rows.sort_by(|a, b| a.function.cmp(&b.function).then(a.block.cmp(&b.block)));
+    rows
+}
+
+fn body_rows(ctx: &GraphContext, function: &str, body: &Body) -> Vec<ArithRow> {
+    // blocks entered only after an overflow or division check passed
+    let guarded: HashSet<usize> = body
+        .blocks
+        .iter()
+        .filter_map(|b| match &b.terminator.kind {
+            TerminatorKind::Assert {
+                msg:
+                    AssertMessage::Overflow(..)
+                    | AssertMessage::OverflowNeg(_)
+                    | AssertMessage::DivisionByZero(_)
+                    | AssertMessage::RemainderByZero(_),
+                target,
+                ..
+            } => Some(*target),
+            _ => None,
+        })
+        .collect();
+    let type_name = |op: &Operand| match op.ty(body.locals()) {
+        Ok(ty) => ctx.types.get_name(ty),
+        Err(_) => "?".to_string(),
+    };
+    let is_int = |op: &Operand| {
+        op.ty(body.locals()).is_ok_and(|ty| {
+            matches!(
+                ty.kind().rigid(),
+                Some(RigidTy::Int(_)) | Some(RigidTy::Uint(_))
+            )
+        })
+    };
+
+    let mut rows = vec![];
+    let mut row = |block: usize, operator: String, ops: &[&Operand], behavior, panics| {
+        rows.push(ArithRow {
+            function: function.to_string(),
+            block,
+            operator,
+            operand_types: ops.iter().map(|op| type_name(op)).collect(),
+            behavior,
+            panics,
+        })
+    };
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let guard = guarded.contains(&idx);
+        for stmt in &block.statements {
+            let StatementKind::Assign(_, rvalue) = &stmt.kind else {
+                continue;
+            };
+            match rvalue {
+                Rvalue::CheckedBinaryOp(op, a, b) => {
+                    // the overflow assert ends the same block
+                    let panics = matches!(block.terminator.kind, TerminatorKind::Assert { .. });
+                    row(idx, format!("{:?}", op), &[a, b], "checked", panics)
+                }
+                Rvalue::BinaryOp(op, a, b) if is_int(a) => {
+                    let Some((operator, behavior)) = binop_behavior(*op, guard) else {
+                        continue;
+                    };
+                    row(idx, operator.to_string(), &[a, b], behavior, guard)
+                }
+                Rvalue::UnaryOp(UnOp::Neg, a) if is_int(a) => {
+                    let behavior = if guard { "checked" } else { "wrapping" };
+                    row(idx, "Neg".to_string(), &[a], behavior, guard)
+                }
+                _ => {}
+            }
+        }
+        if let TerminatorKind::Call { func, args, .. } = &block.terminator.kind {
+            let Some(path) = callee_path(func) else {
+                continue;
+            };
+            if CallCategory::from_path(&path) != CallCategory::Intrinsic {
+                continue;
+            }
+            let name = path.rsplit("::").next().unwrap_or_default();
+            if let Some((operator, behavior)) = intrinsic_behavior(name) {
+                let ops: Vec<&Operand> = args.iter().collect();
+                row(idx, operator, &ops, behavior, false)
+            }
+        }
+    }
+    rows
+}
+
+/// Operator name and overflow behavior of an arithmetic binary operator;
+/// `None` for comparisons, bit operations and pointer offsets
+fn binop_behavior(op: BinOp, guarded: bool) -> Option<(&'static str, &'static str)> {
+    use BinOp::*;
+    let checked_or = |otherwise| if guarded { "checked" } else { otherwise };
+    Some(match op {
+        Add => ("Add", checked_or("wrapping")),
+        Sub => ("Sub", checked_or("wrapping")),
+        Mul => ("Mul", checked_or("wrapping")),
+        // without a guard, shift amounts are masked to the bit width
+        Shl => ("Shl", checked_or("wrapping")),
+        Shr => ("Shr", checked_or("wrapping")),
+        Div => ("Div", checked_or("unchecked")),
+        Rem => ("Rem", checked_or("unchecked")),
+        AddUnchecked => ("Add", "unchecked"),
+        SubUnchecked => ("Sub", "unchecked"),
+        MulUnchecked => ("Mul", "unchecked"),
+        ShlUnchecked => ("Shl", "unchecked"),
+        ShrUnchecked => ("Shr", "unchecked"),
+        BitXor | BitAnd | BitOr | Eq | Lt | Le | Ne | Ge | Gt | Cmp | Offset => return None,
+    })
+}
+
+/// Operator name and overflow behavior of an arithmetic intrinsic
+/// (`wrapping_add`, `unchecked_shl`, `mul_with_overflow`, ...)
+fn intrinsic_behavior(name: &str) -> Option<(String, &'static str)> {
+    let (op, behavior) = if let Some(op) = name.strip_prefix("wrapping_") {
+        (op, "wrapping")
+    } else if let Some(op) = name.strip_prefix("unchecked_") {
+        (op, "unchecked")
+    } else if let Some(op) = name.strip_prefix("saturating_") {
+        (op, "saturating")
+    } else if let Some(op) = name.strip_suffix("_with_overflow") {
+        (op, "checked")
+    } else {
+        return None;
+    };
+    let mut chars = op.chars();
+    let first = chars.next()?;
+    Some((
+        format!("{}{}", first.to_ascii_uppercase(), chars.as_str()),
+        behavior,
+    ))
+}
+
+/// Quote a CSV field if it contains a separator, quote, or line break
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
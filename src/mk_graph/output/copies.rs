@@ -0,0 +1,58 @@
+//! Markdown report of large values copied repeatedly within a block.
+
+use crate::mk_graph::clusters::block_summary;
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::copies::{copy_chains, LARGE_VALUE_BYTES};
+use crate::mk_graph::limits::LabelLimits;
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+impl SmirJson {
+    /// Optimization hints for repeated copies of values of at least
+    /// [`LARGE_VALUE_BYTES`], as a Markdown document listed by function
+    pub fn to_copies_markdown(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("md"));
+        let mut out = format!(
+            "# Repeated copies in `{}`\n\nValues of {} bytes or more copied more than once within a block.\n",
+            self.name, LARGE_VALUE_BYTES
+        );
+        let mut functions: Vec<(&str, &str, _)> = self
+            .items
+            .iter()
+            .filter_map(|item| match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    name,
+                    body: Some(body),
+                    ..
+                } => Some((name.as_str(), item.symbol_name.as_str(), body)),
+                _ => None,
+            })
+            .collect();
+        functions.sort_by_key(|(name, symbol, _)| (*name, *symbol));
+
+        let mut total = 0;
+        for (name, _, body) in functions {
+            let chains = copy_chains(body, &ctx);
+            if chains.is_empty() {
+                continue;
+            }
+            total += chains.len();
+            out.push_str(&format!("\n## `{}`\n\n", name));
+            for chain in chains {
+                out.push_str(&format!(
+                    "- {} (`_{}: {}`)\n",
+                    chain.message(),
+                    chain.root,
+                    chain.ty
+                ));
+                if let Some(summary) = block_summary(&ctx, body, chain.block) {
+                    out.push_str(&format!("  - bb{} {}\n", chain.block, summary));
+                }
+            }
+        }
+        if total == 0 {
+            out.push_str("\nNo repeated copies found.\n");
+        }
+        out
+    }
+}
@@ -1,40 +1,250 @@
 //! D2 diagram format output for MIR graphs.
 
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
 use crate::compat::stable_mir;
 use stable_mir::mir::TerminatorKind;
 
-use crate::printer::SmirJson;
+use crate::printer::{BlockRole, SmirJson};
+use crate::timings::time_fn;
 use crate::MonoItemKind;
 
 use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::duplicates::duplicate_color;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::messages::msg;
+use crate::mk_graph::skeleton::{cleanup_blocks, skeleton_enabled};
+use crate::mk_graph::ubchecks::shown_blocks;
 use crate::mk_graph::util::{
-    escape_d2, is_unqualified, name_lines, short_name, terminator_targets,
+    d2_lines, escape_d2, has_functions, is_unqualified, name_lines, short_name, strip_comment,
+    CallCategory, EdgeKind, TerminatorEdge,
 };
+use crate::mk_graph::visit::{walk_body, BlockMarks, BodyRenderer};
+
+// =============================================================================
+// Layout and Style Options
+// =============================================================================
+
+/// User-supplied D2 layout options and class styles, read from the file
+/// named by the `D2_ATTRS` environment variable.
+///
+/// The file uses the syntax of `DOT_ATTRS`, except that `#` only starts a
+/// comment at the start of a line or after whitespace, so `"#e0e0ff"` is a
+/// color. Section `graph` sets `direction` (`down`, `right`, `up`, `left`;
+/// `right` by default), `theme` (a D2 theme ID) and `sketch` (`true` for the
+/// hand-drawn look); sections named `class.<class>` set the style of one kind
+/// of element (see [`CLASSES`]), emitted as D2 `classes`.
+///
+/// ```toml
+/// [graph]
+/// direction = "down"
+/// theme = 200
+/// sketch = true
+///
+/// [class.call]
+/// style.fill = "#e8f0ff"
+/// ```
+#[derive(Default)]
+pub struct D2Attributes {
+    direction: Option<String>,
+    theme: Option<u32>,
+    sketch: bool,
+    classes: BTreeMap<String, Vec<(String, String)>>,
+}
+
+/// Element kinds that can be styled via `[class.<name>]` sections: blocks by
+/// role (terminator kind, as in `--skeleton`), then other elements
+pub const CLASSES: &[&str] = &[
+    "goto",
+    "switch_int",
+    "resume",
+    "abort",
+    "return",
+    "unreachable",
+    "drop",
+    "call",
+    "assert",
+    "inline_asm",
+    "cleanup",  // block that only runs while unwinding (applied after the role)
+    "function", // container of a function
+    "static",   // static item node
+    "asm",      // global asm node
+];
+
+impl D2Attributes {
+    /// Read options from the file named in `D2_ATTRS`, or return the
+    /// defaults if the variable is not set.
+    pub fn from_env() -> Self {
+        match std::env::var("D2_ATTRS") {
+            Ok(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|e| panic!("Failed to read D2_ATTRS file {}: {}", path, e));
+                Self::parse(&text)
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parse the options file format. Malformed lines, unknown sections and
+    /// invalid values are reported on stderr and skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut attrs = Self::default();
+        let mut section = String::from("graph");
+        for (lineno, raw) in text.lines().enumerate() {
+            let warn = |what: &str| eprintln!("D2_ATTRS line {}: {}: {}", lineno + 1, what, raw);
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                warn("expected `key = value`");
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim().trim_matches('"'));
+            match section.as_str() {
+                "graph" => match key {
+                    "direction" if matches!(value, "down" | "right" | "up" | "left") => {
+                        attrs.direction = Some(value.to_string())
+                    }
+                    "theme" if value.parse::<u32>().is_ok() => attrs.theme = value.parse().ok(),
+                    "sketch" if value.parse::<bool>().is_ok() => attrs.sketch = value == "true",
+                    _ => warn(
+                        "expected direction (down, right, up, left), theme (a theme ID) \
+                         or sketch (true, false)",
+                    ),
+                },
+                other => match other.strip_prefix("class.") {
+                    Some(class) if CLASSES.contains(&class) => attrs
+                        .classes
+                        .entry(class.to_string())
+                        .or_default()
+                        .push((key.to_string(), value.to_string())),
+                    _ => warn(&format!(
+                        "unknown section [{}] (expected graph or class.<{}>)",
+                        other,
+                        CLASSES.join("|")
+                    )),
+                },
+            }
+        }
+        attrs
+    }
+
+    /// The theme and sketch configuration, direction, and class definitions
+    /// that open the diagram
+    fn header(&self) -> String {
+        let mut out = String::new();
+        if self.theme.is_some() || self.sketch {
+            out.push_str("vars: {\n  d2-config: {\n");
+            if let Some(theme) = self.theme {
+                out.push_str(&format!("    theme-id: {}\n", theme));
+            }
+            if self.sketch {
+                out.push_str("    sketch: true\n");
+            }
+            out.push_str("  }\n}\n\n");
+        }
+        out.push_str(&format!(
+            "direction: {}\n\n",
+            self.direction.as_deref().unwrap_or("right")
+        ));
+        if !self.classes.is_empty() {
+            out.push_str("classes: {\n");
+            for (class, entries) in &self.classes {
+                out.push_str(&format!("  {}: {{\n", class));
+                for (key, value) in entries {
+                    out.push_str(&format!("    {}: \"{}\"\n", key, escape_d2(value)));
+                }
+                out.push_str("  }\n");
+            }
+            out.push_str("}\n\n");
+        }
+        out
+    }
+
+    /// The `class` field for an element of the given kinds, listing those
+    /// that have a `[class.<name>]` section; empty if none has
+    fn class_of(&self, kinds: &[&str]) -> Option<String> {
+        let defined: Vec<&str> = kinds
+            .iter()
+            .copied()
+            .filter(|k| self.classes.contains_key(*k))
+            .collect();
+        match defined.as_slice() {
+            [] => None,
+            [one] => Some(one.to_string()),
+            many => Some(format!("[{}]", many.join("; "))),
+        }
+    }
+}
 
 impl SmirJson {
     /// Convert the MIR to D2 diagram format
     pub fn to_d2_file(self) -> String {
-        let ctx = GraphContext::from_smir(&self);
-        let mut output = String::new();
+        let mut bytes = Vec::new();
+        self.write_d2_file(&mut bytes)
+            .expect("writing to memory cannot fail");
+        String::from_utf8(bytes).expect("Error converting d2 file")
+    }
 
-        output.push_str("direction: right\n\n");
-        render_d2_allocs_legend(&ctx, &mut output);
+    /// Write the MIR in D2 format to `out` one item at a time, so that only
+    /// the section of the current item is held in memory
+    pub fn write_d2_file(self, out: &mut dyn Write) -> io::Result<()> {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("d2"));
+        let attrs = D2Attributes::from_env();
+        let mut output = attrs.header();
+
+        if !skeleton_enabled() {
+            render_d2_allocs_legend(&ctx, &mut output);
+        }
+
+        // A crate with only statics/asm (or nothing at all) should still say so
+        if !has_functions(&self.items) {
+            output.push_str(&format!(
+                "no_functions: \"{}\" {{\n",
+                escape_d2(msg("graph.no_functions"))
+            ));
+            output.push_str("  style.stroke-dash: 3\n");
+            output.push_str("}\n\n");
+        }
+        out.write_all(output.as_bytes())?;
 
         for item in self.items {
+            output.clear();
             match item.mono_item_kind {
                 MonoItemKind::MonoItemFn { name, body, .. } => {
-                    render_d2_function(&name, body.as_ref(), &ctx, &mut output);
+                    time_fn(&name, || {
+                        render_d2_function(
+                            &name,
+                            &item.symbol_name,
+                            body.as_ref(),
+                            &ctx,
+                            &attrs,
+                            &mut output,
+                        )
+                    });
                 }
                 MonoItemKind::MonoItemGlobalAsm { asm } => {
-                    render_d2_asm(&asm, &mut output);
+                    render_d2_asm(&asm, &attrs, &mut output);
                 }
-                MonoItemKind::MonoItemStatic { name, .. } => {
-                    render_d2_static(&name, &mut output);
+                MonoItemKind::MonoItemStatic {
+                    name,
+                    allocation,
+                    body,
+                    ..
+                } => {
+                    let lines = ctx.static_lines(&name, allocation.as_ref(), body.as_ref());
+                    render_d2_static(&name, &lines, &attrs, &mut output);
                 }
             }
+            out.write_all(output.as_bytes())?;
         }
-
-        output
+        Ok(())
     }
 }
 
@@ -48,77 +258,75 @@ fn render_d2_allocs_legend(ctx: &GraphContext, out: &mut String) {
     out.push_str("ALLOCS: {\n");
     out.push_str("  style.fill: \"#ffffcc\"\n");
     out.push_str("  style.stroke: \"#999999\"\n");
-    let legend_text = legend_lines
-        .iter()
-        .map(|s| escape_d2(s))
-        .collect::<Vec<_>>()
-        .join("\\n");
-    out.push_str(&format!("  label: \"{}\"\n", legend_text));
+    out.push_str(&format!("  label: \"{}\"\n", d2_lines(&legend_lines)));
     out.push_str("}\n\n");
 }
 
 fn render_d2_function(
     name: &str,
+    symbol: &str,
     body: Option<&stable_mir::mir::Body>,
     ctx: &GraphContext,
+    attrs: &D2Attributes,
     out: &mut String,
 ) {
     let fn_id = short_name(name);
-    let display_name = escape_d2(&name_lines(name));
+    let display_name = d2_lines(&name_lines(name));
 
     // Function container
     out.push_str(&format!("{}: {{\n", fn_id));
     out.push_str(&format!("  label: \"{}\"\n", display_name));
-    out.push_str("  style.fill: \"#e0e0ff\"\n");
+    // a class replaces the default fill, which would take precedence over it
+    match attrs.class_of(&["function"]) {
+        Some(class) => out.push_str(&format!("  class: {}\n", class)),
+        None => out.push_str("  style.fill: \"#e0e0ff\"\n"),
+    }
 
     if let Some(body) = body {
-        render_d2_blocks(body, ctx, out);
-        render_d2_block_edges(body, out);
+        let classes = if attrs.classes.is_empty() {
+            vec![]
+        } else {
+            let cleanup = cleanup_blocks(body);
+            body.blocks
+                .iter()
+                .enumerate()
+                .map(|(idx, block)| {
+                    let mut kinds = vec![BlockRole::of(&block.terminator).name()];
+                    if cleanup[idx] {
+                        kinds.push("cleanup");
+                    }
+                    attrs.class_of(&kinds)
+                })
+                .collect()
+        };
+        walk_body(ctx, symbol, body, &mut D2Blocks { out, classes });
     }
 
     out.push_str("}\n\n");
 
-    // Call edges (must be outside the container)
+    // Call and static-use edges (must be outside the container)
     if let Some(body) = body {
-        render_d2_call_edges(&fn_id, body, ctx, out);
-    }
-}
-
-fn render_d2_blocks(body: &stable_mir::mir::Body, ctx: &GraphContext, out: &mut String) {
-    for (idx, block) in body.blocks.iter().enumerate() {
-        let stmts: Vec<String> = block
-            .statements
-            .iter()
-            .map(|s| escape_d2(&ctx.render_stmt(s)))
-            .collect();
-        let term_str = escape_d2(&ctx.render_terminator(&block.terminator));
-
-        let mut label = format!("bb{}:", idx);
-        for stmt in &stmts {
-            label.push_str(&format!("\\n{}", stmt));
-        }
-        label.push_str(&format!("\\n---\\n{}", term_str));
-
-        out.push_str(&format!("  bb{}: \"{}\"\n", idx, label));
-    }
-}
-
-fn render_d2_block_edges(body: &stable_mir::mir::Body, out: &mut String) {
-    for (idx, block) in body.blocks.iter().enumerate() {
-        for target in terminator_targets(&block.terminator) {
-            out.push_str(&format!("  bb{} -> bb{}\n", idx, target));
-        }
+        let shown = shown_blocks(body, ctx.ub_checks);
+        render_d2_call_edges(&fn_id, symbol, body, &shown, ctx, out);
+        render_d2_static_edges(&fn_id, body, &shown, ctx, out);
     }
 }
 
 fn render_d2_call_edges(
     fn_id: &str,
+    symbol: &str,
     body: &stable_mir::mir::Body,
+    shown: &[bool],
     ctx: &GraphContext,
     out: &mut String,
 ) {
-    for (idx, block) in body.blocks.iter().enumerate() {
-        let TerminatorKind::Call { func, .. } = &block.terminator.kind else {
+    for (idx, block) in body
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| shown[*idx])
+    {
+        let TerminatorKind::Call { func, args, .. } = &block.terminator.kind else {
             continue;
         };
         let Some(callee_name) = ctx.resolve_call_target(func) else {
@@ -131,21 +339,239 @@ fn render_d2_call_edges(
         let target_id = short_name(&callee_name);
         out.push_str(&format!("{}: \"{}\"\n", target_id, escape_d2(&callee_name)));
         out.push_str(&format!("{}.style.fill: \"#ffe0e0\"\n", target_id));
-        out.push_str(&format!("{}.bb{} -> {}: call\n", fn_id, idx, target_id));
+        let args = ctx.call_arg_mapping(func, args);
+        let mut label = if args.is_empty() {
+            "call".to_string()
+        } else {
+            format!("call: {}", args)
+        };
+        if let Some(note) = ctx.recursion_note(symbol, &callee_name) {
+            label = format!("{} ({})", label, note);
+        }
+        let label = escape_d2(&label);
+        let style = match ctx.call_category(func).and_then(CallCategory::color) {
+            Some(color) => format!(" {{ style.stroke: {} }}", color),
+            None => String::new(),
+        };
+        out.push_str(&format!(
+            "{}.bb{} -> {}: \"{}\"{}\n",
+            fn_id, idx, target_id, label, style
+        ));
     }
 }
 
-fn render_d2_asm(asm: &str, out: &mut String) {
+fn render_d2_static_edges(
+    fn_id: &str,
+    body: &stable_mir::mir::Body,
+    shown: &[bool],
+    ctx: &GraphContext,
+    out: &mut String,
+) {
+    for (idx, block) in body
+        .blocks
+        .iter()
+        .enumerate()
+        .filter(|(idx, _)| shown[*idx])
+    {
+        for static_name in ctx.static_refs(block) {
+            out.push_str(&format!(
+                "{}.bb{} -> {}: use {{ style.stroke-dash: 3 }}\n",
+                fn_id,
+                idx,
+                short_name(&static_name)
+            ));
+        }
+    }
+}
+
+fn render_d2_asm(asm: &str, attrs: &D2Attributes, out: &mut String) {
     let asm_id = short_name(asm);
     let asm_text = escape_d2(&asm.lines().collect::<String>());
     out.push_str(&format!("{}: \"{}\" {{\n", asm_id, asm_text));
-    out.push_str("  style.fill: \"#ffe0ff\"\n");
+    match attrs.class_of(&["asm"]) {
+        Some(class) => out.push_str(&format!("  class: {}\n", class)),
+        None => out.push_str("  style.fill: \"#ffe0ff\"\n"),
+    }
     out.push_str("}\n\n");
 }
 
-fn render_d2_static(name: &str, out: &mut String) {
+fn render_d2_static(name: &str, lines: &[String], attrs: &D2Attributes, out: &mut String) {
     let static_id = short_name(name);
-    out.push_str(&format!("{}: \"{}\" {{\n", static_id, escape_d2(name)));
-    out.push_str("  style.fill: \"#e0ffe0\"\n");
+    out.push_str(&format!("{}: \"{}\" {{\n", static_id, d2_lines(lines)));
+    match attrs.class_of(&["static"]) {
+        Some(class) => out.push_str(&format!("  class: {}\n", class)),
+        None => out.push_str("  style.fill: \"#e0ffe0\"\n"),
+    }
     out.push_str("}\n\n");
 }
+
+// =============================================================================
+// Block Rendering
+// =============================================================================
+
+/// Writes the blocks and intra-function edges of one body into its container
+struct D2Blocks<'a> {
+    out: &'a mut String,
+    /// `class` of each block, by block index; empty without class styles
+    classes: Vec<Option<String>>,
+}
+
+impl BodyRenderer for D2Blocks<'_> {
+    fn block(
+        &mut self,
+        idx: usize,
+        statements: Vec<String>,
+        terminator: String,
+        marks: BlockMarks,
+    ) {
+        let mut label = format!("bb{}:", idx);
+        for stmt in &statements {
+            label.push_str(&format!("\\n{}", escape_d2(stmt)));
+        }
+        label.push_str(&format!("\\n---\\n{}", escape_d2(&terminator)));
+
+        self.out.push_str(&format!("  bb{}: \"{}\"\n", idx, label));
+        if let Some(Some(class)) = self.classes.get(idx) {
+            self.out
+                .push_str(&format!("  bb{}.class: {}\n", idx, class));
+        }
+        if marks.nounwind {
+            self.out
+                .push_str(&format!("  bb{}.style.double-border: true\n", idx));
+        }
+        // taint takes precedence over the duplicate coloring
+        if marks.tainted {
+            self.out
+                .push_str(&format!("  bb{}.style.fill: \"#ffe4e1\"\n", idx));
+        } else if let Some(group) = marks.duplicate {
+            self.out.push_str(&format!(
+                "  bb{}.style.fill: {}\n",
+                idx,
+                duplicate_color(group)
+            ));
+        }
+        if marks.bad_cast {
+            self.out
+                .push_str(&format!("  bb{}.style.stroke: red\n", idx));
+        }
+    }
+
+    fn edge(&mut self, from: usize, edge: &TerminatorEdge, label: String, _: Option<&str>) {
+        if label.is_empty() {
+            self.out
+                .push_str(&format!("  bb{} -> bb{}\n", from, edge.target));
+        } else if edge.kind == EdgeKind::UnreachableOtherwise {
+            self.out.push_str(&format!(
+                "  bb{} -> bb{}: \"{}\" {{ style.stroke-dash: 3; style.stroke: grey }}\n",
+                from,
+                edge.target,
+                escape_d2(&label)
+            ));
+        } else {
+            self.out.push_str(&format!(
+                "  bb{} -> bb{}: \"{}\"\n",
+                from,
+                edge.target,
+                escape_d2(&label)
+            ));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_graph_options() {
+        let attrs = D2Attributes::parse(
+            "[graph]\n\
+             direction = \"down\"\n\
+             theme = 200   # dark mauve\n\
+             sketch = true\n",
+        );
+        assert_eq!(attrs.direction.as_deref(), Some("down"));
+        assert_eq!(attrs.theme, Some(200));
+        assert!(attrs.sketch);
+        assert!(attrs.classes.is_empty());
+    }
+
+    #[test]
+    fn parse_skips_invalid_graph_options() {
+        let attrs = D2Attributes::parse(
+            "direction = sideways\n\
+             theme = dark\n\
+             sketch = yes\n\
+             layout = elk\n\
+             direction\n",
+        );
+        assert_eq!(attrs.direction, None);
+        assert_eq!(attrs.theme, None);
+        assert!(!attrs.sketch);
+        let attrs = D2Attributes::parse("sketch = true\nsketch = false\n");
+        assert!(!attrs.sketch);
+    }
+
+    #[test]
+    fn parse_collects_known_classes() {
+        let attrs = D2Attributes::parse(
+            "[class.call]\n\
+             style.fill = \"#e8f0ff\"\n\
+             [class.no_such_class]\n\
+             style.fill = red\n\
+             [class.cleanup]\n\
+             style.stroke-dash = 3\n\
+             [class.call]\n\
+             style.bold = true\n",
+        );
+        let classes: Vec<(&str, Vec<(&str, &str)>)> = attrs
+            .classes
+            .iter()
+            .map(|(class, entries)| {
+                let entries = entries
+                    .iter()
+                    .map(|(k, v)| (k.as_str(), v.as_str()))
+                    .collect();
+                (class.as_str(), entries)
+            })
+            .collect();
+        assert_eq!(
+            classes,
+            [
+                (
+                    "call",
+                    vec![("style.fill", "#e8f0ff"), ("style.bold", "true")]
+                ),
+                ("cleanup", vec![("style.stroke-dash", "3")]),
+            ]
+        );
+    }
+
+    #[test]
+    fn header_writes_config_direction_and_classes() {
+        assert_eq!(D2Attributes::default().header(), "direction: right\n\n");
+        let attrs = D2Attributes::parse(
+            "theme = 200\n\
+             sketch = true\n\
+             [class.call]\n\
+             style.fill = \"#e8f0ff\"\n",
+        );
+        assert_eq!(
+            attrs.header(),
+            "vars: {\n  d2-config: {\n    theme-id: 200\n    sketch: true\n  }\n}\n\n\
+             direction: right\n\n\
+             classes: {\n  call: {\n    style.fill: \"#e8f0ff\"\n  }\n}\n\n"
+        );
+    }
+
+    #[test]
+    fn class_of_lists_defined_classes() {
+        let attrs = D2Attributes::parse("[class.call]\nx = 1\n[class.cleanup]\nx = 2\n");
+        assert_eq!(attrs.class_of(&["goto"]), None);
+        assert_eq!(attrs.class_of(&["call"]).as_deref(), Some("call"));
+        assert_eq!(
+            attrs.class_of(&["call", "cleanup"]).as_deref(),
+            Some("[call; cleanup]")
+        );
+    }
+}
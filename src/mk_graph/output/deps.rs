@@ -0,0 +1,228 @@
+//! Item dependency graph in JSON.
+//!
+//! Nodes are the crate's functions (by symbol name, as in the `functions`
+//! map), statics, and named types (structs, enums, unions, trait objects).
+//! Edges say how one item depends on another:
+//! - `calls`: a function calls another function
+//! - `uses_type`: a function has a local of the type (looking through
+//!   references, pointers, arrays and tuples), or a type has a field of it
+//! - `reads_static`: a function refers to a static's allocation
+//! - `references_vtable`: a function unsizes a pointer to the type into a
+//!   trait object, so it needs the type's vtable for that trait
+//! - `takes_fn_address`: a function turns a function into a function pointer
+//!
+//! Refactoring tools can compute the blast radius of a change from these
+//! edges without a compiler session.
+
+use std::collections::{BTreeSet, HashSet};
+
+use crate::compat::serde;
+use crate::compat::serde_json;
+use crate::compat::stable_mir;
+use serde::Serialize;
+use stable_mir::mir::{
+    Body, CastKind, ConstOperand, Operand, PointerCoercion, Rvalue, StatementKind, TerminatorKind,
+};
+use stable_mir::ty::{RigidTy, Ty};
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::index::TypeKind;
+use crate::mk_graph::limits::LabelLimits;
+use crate::printer::{SmirJson, TypeMetadata};
+use crate::MonoItemKind;
+
+#[derive(Serialize)]
+pub struct DependencyGraph {
+    pub nodes: Vec<DependencyNode>,
+    pub edges: Vec<DependencyEdge>,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DependencyNode {
+    /// Symbol name for functions, name for statics and types
+    pub id: String,
+    /// `function`, `static` or `type`
+    pub kind: &'static str,
+    /// Readable name (the path of a function)
+    pub name: String,
+}
+
+#[derive(Serialize, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DependencyEdge {
+    pub from: String,
+    pub to: String,
+    /// `calls`, `uses_type`, `reads_static`, `references_vtable` or
+    /// `takes_fn_address`
+    pub kind: &'static str,
+}
+
+impl SmirJson {
+    /// The dependency graph of the crate's items, as JSON
+    pub fn to_dependency_graph(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("json"));
+        let mut nodes: BTreeSet<DependencyNode> = BTreeSet::new();
+        let mut edges: BTreeSet<DependencyEdge> = BTreeSet::new();
+
+        for (ty, metadata) in &self.types {
+            let name = match metadata {
+                TypeMetadata::EnumType { name, .. }
+                | TypeMetadata::StructType { name, .. }
+                | TypeMetadata::UnionType { name, .. }
+                | TypeMetadata::DynType { name, .. } => name,
+                _ => continue,
+            };
+            nodes.insert(DependencyNode {
+                id: name.clone(),
+                kind: "type",
+                name: name.clone(),
+            });
+            for field in field_types(&ctx, *ty) {
+                for used in named_types(&ctx, field) {
+                    edges.insert(edge(name, &used, "uses_type"));
+                }
+            }
+        }
+
+        for item in &self.items {
+            match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn { name, body, .. } => {
+                    nodes.insert(DependencyNode {
+                        id: item.symbol_name.clone(),
+                        kind: "function",
+                        name: name.clone(),
+                    });
+                    if let Some(body) = body {
+                        body_edges(&ctx, &item.symbol_name, body, &mut edges);
+                    }
+                }
+                MonoItemKind::MonoItemStatic { name, .. } => {
+                    nodes.insert(DependencyNode {
+                        id: name.clone(),
+                        kind: "static",
+                        name: name.clone(),
+                    });
+                }
+                MonoItemKind::MonoItemGlobalAsm { .. } => {}
+            }
+        }
+        // callees without a body in the crate are nodes too
+        let ids: HashSet<String> = nodes.iter().map(|n| n.id.clone()).collect();
+        for edge in &edges {
+            if matches!(edge.kind, "calls" | "takes_fn_address") && !ids.contains(&edge.to) {
+                nodes.insert(DependencyNode {
+                    id: edge.to.clone(),
+                    kind: "function",
+                    name: ctx.short_fn_name(&edge.to),
+                });
+            }
+        }
+
+        let graph = DependencyGraph {
+            nodes: nodes.into_iter().collect(),
+            edges: edges.into_iter().collect(),
+        };
+        serde_json::to_string_pretty(&graph).expect("serde_json failed to write dependency graph")
+    }
+}
+
+fn edge(from: &str, to: &str, kind: &'static str) -> DependencyEdge {
+    DependencyEdge {
+        from: from.to_string(),
+        to: to.to_string(),
+        kind,
+    }
+}
+
+fn body_edges(ctx: &GraphContext, symbol: &str, body: &Body, edges: &mut BTreeSet<DependencyEdge>) {
+    for decl in body.locals() {
+        for used in named_types(ctx, decl.ty) {
+            edges.insert(edge(symbol, &used, "uses_type"));
+        }
+    }
+    for block in &body.blocks {
+        for name in ctx.static_refs(block) {
+            edges.insert(edge(symbol, &name, "reads_static"));
+        }
+        if let TerminatorKind::Call { func, .. } = &block.terminator.kind {
+            if let Some(callee) = ctx.resolve_call_target(func) {
+                edges.insert(edge(symbol, &callee, "calls"));
+            }
+        }
+        for stmt in &block.statements {
+            let StatementKind::Assign(
+                _,
+                Rvalue::Cast(CastKind::PointerCoercion(coercion), op, target),
+            ) = &stmt.kind
+            else {
+                continue;
+            };
+            match coercion {
+                PointerCoercion::ReifyFnPointer | PointerCoercion::ClosureFnPointer(_) => {
+                    if let Operand::Constant(ConstOperand { const_, .. }) = op {
+                        if let Some(f) = ctx.functions.get(&const_.ty()) {
+                            edges.insert(edge(symbol, f, "takes_fn_address"));
+                        }
+                    }
+                }
+                PointerCoercion::Unsize if points_to_dyn(*target) => {
+                    let source = op.ty(body.locals()).ok().and_then(pointee);
+                    for used in source.into_iter().flat_map(|ty| named_types(ctx, ty)) {
+                        edges.insert(edge(symbol, &used, "references_vtable"));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Names of the structs, enums, unions and trait objects that `ty` is or
+/// contains directly (through references, pointers, arrays and tuples)
+fn named_types(ctx: &GraphContext, ty: Ty) -> Vec<String> {
+    let mut names = vec![];
+    let mut pending = vec![ty];
+    // recursive types only recurse through ADTs, where the walk stops
+    while let Some(ty) = pending.pop() {
+        let Some(entry) = ctx.types.get(ty) else {
+            continue;
+        };
+        match &entry.kind {
+            TypeKind::Struct { .. }
+            | TypeKind::Enum { .. }
+            | TypeKind::Union { .. }
+            | TypeKind::Dyn => names.push(entry.name.clone()),
+            TypeKind::Array { elem_ty, .. } => pending.push(*elem_ty),
+            TypeKind::Tuple { fields } => pending.extend(fields),
+            TypeKind::Ptr { pointee, .. } | TypeKind::Ref { pointee, .. } => pending.push(*pointee),
+            TypeKind::Primitive(_) | TypeKind::Function | TypeKind::Void => {}
+        }
+    }
+    names
+}
+
+/// Types of the fields of a struct, union, or any enum variant
+fn field_types(ctx: &GraphContext, ty: Ty) -> Vec<Ty> {
+    match ctx.types.get(ty).map(|entry| &entry.kind) {
+        Some(TypeKind::Struct { fields } | TypeKind::Union { fields }) => {
+            fields.iter().map(|f| f.ty).collect()
+        }
+        Some(TypeKind::Enum { variants }) => variants
+            .iter()
+            .flat_map(|v| v.fields.iter().map(|f| f.ty))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// Pointee of a raw pointer or reference type
+fn pointee(ty: Ty) -> Option<Ty> {
+    match ty.kind().rigid()? {
+        RigidTy::RawPtr(pointee, _) | RigidTy::Ref(_, pointee, _) => Some(*pointee),
+        _ => None,
+    }
+}
+
+/// Whether `ty` is a pointer or reference to a trait object
+fn points_to_dyn(ty: Ty) -> bool {
+    pointee(ty).is_some_and(|p| matches!(p.kind().rigid(), Some(RigidTy::Dynamic(..))))
+}
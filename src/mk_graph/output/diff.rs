@@ -0,0 +1,274 @@
+//! CFG diff of one function between two `*.smir.json` files.
+//!
+//! The blocks of the old and new body are aligned by a longest common
+//! subsequence over their shapes: the statement and terminator kinds with all
+//! numbers (locals, block targets, type and constant ids, spans) left out, so
+//! that renumbering does not count as a change. The merged graph shows
+//! matched blocks with both numbers (`bb3 -> bb4`), removed blocks in red and
+//! inserted blocks in green; edges only in one version are drawn in that
+//! version's color. A function missing from the new file under its old
+//! name is looked up by its `fingerprint`, so renames are followed as long
+//! as the body is unchanged.
+//!
+//! Works on the JSON documents directly, without a compiler session.
+
+use std::collections::BTreeSet;
+
+use crate::compat::serde_json;
+use serde_json::Value;
+
+use crate::mk_graph::util::{dot_lines, escape_dot};
+use crate::printer::check_format_version;
+
+/// A node of the merged graph
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum DiffBlock {
+    Both(usize, usize),
+    Removed(usize),
+    Inserted(usize),
+}
+
+impl DiffBlock {
+    fn id(self) -> String {
+        match self {
+            DiffBlock::Both(old, new) => format!("m{}_{}", old, new),
+            DiffBlock::Removed(old) => format!("old{}", old),
+            DiffBlock::Inserted(new) => format!("new{}", new),
+        }
+    }
+}
+
+/// Merged DOT graph of `function` in `old` and `new`
+pub fn diff_dot(old: &Value, new: &Value, function: &str) -> Result<String, String> {
+    check_format_version(old)?;
+    check_format_version(new)?;
+    let (name, old_blocks) = function_blocks(old, |item| selects(item, function))
+        .ok_or_else(|| format!("old file has no function {}", function))?;
+    // a renamed function is found by its unchanged body
+    let (_, new_blocks) = function_blocks(new, |item| selects(item, function))
+        .or_else(|| {
+            let fingerprint = function_item(old, function)?["fingerprint"].as_str()?;
+            function_blocks(new, |item| {
+                item["fingerprint"].as_str() == Some(fingerprint)
+            })
+        })
+        .ok_or_else(|| format!("new file has no function {}", function))?;
+
+    let old_shapes: Vec<String> = old_blocks.iter().map(shape).collect();
+    let new_shapes: Vec<String> = new_blocks.iter().map(shape).collect();
+    let nodes = align(&old_shapes, &new_shapes);
+
+    let old_node = |b: usize| {
+        nodes.iter().copied().find(|n| match n {
+            DiffBlock::Both(o, _) | DiffBlock::Removed(o) => *o == b,
+            DiffBlock::Inserted(_) => false,
+        })
+    };
+    let new_node = |b: usize| {
+        nodes.iter().copied().find(|n| match n {
+            DiffBlock::Both(_, m) | DiffBlock::Inserted(m) => *m == b,
+            DiffBlock::Removed(_) => false,
+        })
+    };
+    let edges = |blocks: &[Value], node: &dyn Fn(usize) -> Option<DiffBlock>| {
+        let mut edges = BTreeSet::new();
+        for (idx, block) in blocks.iter().enumerate() {
+            let mut targets = vec![];
+            block_targets(&block["terminator"]["kind"], &mut targets);
+            for target in targets {
+                if let (Some(from), Some(to)) = (node(idx), node(target)) {
+                    edges.insert((from, to));
+                }
+            }
+        }
+        edges
+    };
+    let old_edges = edges(&old_blocks, &old_node);
+    let new_edges = edges(&new_blocks, &new_node);
+
+    let mut out = format!(
+        "digraph \"diff {}\" {{\n  label = \"{}\";\n  node [shape=box, fontname=monospace];\n",
+        escape_dot(&name),
+        escape_dot(&name)
+    );
+    for node in &nodes {
+        let (title, block, color) = match *node {
+            DiffBlock::Both(o, n) => (format!("bb{} -> bb{}", o, n), &new_blocks[n], "white"),
+            DiffBlock::Removed(o) => (format!("bb{} (removed)", o), &old_blocks[o], "mistyrose"),
+            DiffBlock::Inserted(n) => (format!("bb{} (inserted)", n), &new_blocks[n], "palegreen"),
+        };
+        let mut label = vec![title];
+        label.extend(block_summary(block));
+        label.push(String::new());
+        out.push_str(&format!(
+            "  {} [label=\"{}\", style=filled, fillcolor={}];\n",
+            node.id(),
+            dot_lines(&label, "\\l"),
+            color
+        ));
+    }
+    for edge in old_edges.union(&new_edges) {
+        let style = match (old_edges.contains(edge), new_edges.contains(edge)) {
+            (true, true) => "",
+            (true, false) => " [color=red, style=dashed]",
+            _ => " [color=darkgreen]",
+        };
+        out.push_str(&format!("  {} -> {}{};\n", edge.0.id(), edge.1.id(), style));
+    }
+    out.push_str("}\n");
+    Ok(out)
+}
+
+/// Name and blocks of the first function whose item satisfies `select`
+fn function_blocks(smir: &Value, select: impl Fn(&Value) -> bool) -> Option<(String, Vec<Value>)> {
+    smir["items"].as_array()?.iter().find_map(|item| {
+        let f = &item["mono_item_kind"]["MonoItemFn"];
+        let name = f["name"].as_str()?;
+        if !select(item) {
+            return None;
+        }
+        Some((name.to_string(), f["body"]["blocks"].as_array()?.clone()))
+    })
+}
+
+/// The item of the function matching `query`
+fn function_item<'a>(smir: &'a Value, query: &str) -> Option<&'a Value> {
+    smir["items"]
+        .as_array()?
+        .iter()
+        .find(|item| selects(item, query))
+}
+
+/// Whether `item` is a function matching `query` like `QUERY_FN` does
+fn selects(item: &Value, query: &str) -> bool {
+    let Some(name) = item["mono_item_kind"]["MonoItemFn"]["name"].as_str() else {
+        return false;
+    };
+    name == query
+        || name.ends_with(&format!("::{}", query))
+        || item["symbol_name"].as_str() == Some(query)
+}
+
+/// The strings (variant and field names, operator names) of a block, without
+/// any numbers
+fn shape(block: &Value) -> String {
+    fn walk(v: &Value, out: &mut String) {
+        match v {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    if k == "span" {
+                        continue;
+                    }
+                    out.push_str(k);
+                    out.push('{');
+                    walk(v, out);
+                    out.push('}');
+                }
+            }
+            Value::Array(items) => items.iter().for_each(|v| walk(v, out)),
+            Value::String(s) => {
+                out.push_str(s);
+                out.push(' ');
+            }
+            Value::Number(_) | Value::Bool(_) | Value::Null => {}
+        }
+    }
+    let mut out = String::new();
+    walk(block, &mut out);
+    out
+}
+
+/// Longest common subsequence alignment of two shape sequences
+fn align(old: &[String], new: &[String]) -> Vec<DiffBlock> {
+    let (n, m) = (old.len(), new.len());
+    // lcs[i][j]: length of the LCS of old[i..] and new[j..]
+    let mut lcs = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    let mut nodes = vec![];
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            nodes.push(DiffBlock::Both(i, j));
+            i += 1;
+            j += 1;
+        } else if j < m && (i == n || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            nodes.push(DiffBlock::Inserted(j));
+            j += 1;
+        } else {
+            nodes.push(DiffBlock::Removed(i));
+            i += 1;
+        }
+    }
+    nodes
+}
+
+/// Successor blocks named in a serialized terminator kind
+fn block_targets(kind: &Value, targets: &mut Vec<usize>) {
+    match kind {
+        Value::Object(map) => {
+            for (k, v) in map {
+                match (k.as_str(), v) {
+                    ("target" | "otherwise" | "Cleanup", Value::Number(n)) => {
+                        targets.extend(n.as_u64().map(|n| n as usize))
+                    }
+                    ("branches", Value::Array(branches)) => targets.extend(
+                        branches
+                            .iter()
+                            .filter_map(|b| b.get(1)?.as_u64().map(|n| n as usize)),
+                    ),
+                    // `InlineAsm` names its return block `destination`
+                    ("destination", Value::Number(n)) => {
+                        targets.extend(n.as_u64().map(|n| n as usize))
+                    }
+                    (_, v) => block_targets(v, targets),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| block_targets(v, targets)),
+        _ => {}
+    }
+}
+
+/// One line per statement and the terminator: the kind, and for assignments
+/// the rvalue kind (`Assign(CheckedBinaryOp)`)
+fn block_summary(block: &Value) -> Vec<String> {
+    let variant = |v: &Value| -> Option<String> {
+        let map = v.as_object()?;
+        if map.len() == 1 {
+            map.keys().next().cloned()
+        } else {
+            None
+        }
+    };
+    let describe = |kind: &Value| -> String {
+        match kind {
+            Value::String(s) => s.clone(),
+            Value::Object(map) if map.len() == 1 => {
+                let (name, inner) = map.iter().next().unwrap();
+                let detail = inner
+                    .as_array()
+                    .and_then(|parts| parts.last())
+                    .and_then(variant);
+                match detail {
+                    Some(detail) => format!("{}({})", name, detail),
+                    None => name.clone(),
+                }
+            }
+            _ => "?".to_string(),
+        }
+    };
+    let mut lines: Vec<String> = block["statements"]
+        .as_array()
+        .map(|stmts| stmts.iter().map(|s| describe(&s["kind"])).collect())
+        .unwrap_or_default();
+    lines.push(describe(&block["terminator"]["kind"]));
+    lines
+}
@@ -1,67 +1,450 @@
 //! DOT (Graphviz) format output for MIR graphs.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
 
 use dot_writer::{Attributes, Color, DotWriter, Scope, Shape, Style};
 
 use crate::compat::stable_mir;
-use stable_mir::mir::{BasicBlock, ConstOperand, Operand, TerminatorKind, UnwindAction};
+use stable_mir::mir::{BasicBlock, ConstOperand, Operand, TerminatorKind};
 
 use crate::printer::SmirJson;
+use crate::timings::time_fn;
 use crate::MonoItemKind;
 
+use crate::mk_graph::clusters::block_summary;
 use crate::mk_graph::context::GraphContext;
-use crate::mk_graph::util::{block_name, is_unqualified, name_lines, short_name, GraphLabelString};
+use crate::mk_graph::duplicates::duplicate_color;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::lines::{group_lines_enabled, line_groups};
+use crate::mk_graph::messages::{fill, msg};
+use crate::mk_graph::returns::{return_sources, return_summary};
+use crate::mk_graph::skeleton::skeleton_enabled;
+use crate::mk_graph::ubchecks::shown_blocks;
+use crate::mk_graph::util::{
+    block_name, dot_lines, escape_dot, has_functions, is_unqualified, name_lines, short_name,
+    strip_comment, CallCategory, EdgeKind, GraphLabelString, TerminatorEdge,
+};
+use crate::mk_graph::visit::{walk_body, BlockMarks, BodyRenderer};
+
+// =============================================================================
+// Attribute Overrides
+// =============================================================================
+
+/// User-supplied Graphviz attribute overrides, read from the file named by
+/// the `DOT_ATTRS` environment variable.
+///
+/// The file uses a small TOML-like syntax: `[section]` headers followed by
+/// `key = value` lines (values may be quoted; `#` at the start of a line or
+/// after whitespace starts a comment, so `"#336699"` is a value).
+/// Sections `graph`, `node` and `edge` set the global defaults; sections
+/// named `role.<role>` apply to one kind of element only (see [`ROLES`]).
+///
+/// ```toml
+/// [graph]
+/// rankdir = "LR"
+/// dpi = 150
+///
+/// [node]
+/// fontname = "Helvetica"
+///
+/// [role.fn_local]
+/// color = "lightblue"
+/// ```
+#[derive(Default)]
+pub struct DotAttributes {
+    graph: Vec<(String, String)>,
+    node: Vec<(String, String)>,
+    edge: Vec<(String, String)>,
+    roles: HashMap<String, Vec<(String, String)>>,
+}
+
+/// Element roles that can be styled via `[role.<name>]` sections.
+pub const ROLES: &[&str] = &[
+    "fn_local",       // cluster of a function defined in the crate (unqualified name)
+    "fn_external",    // cluster of any other function
+    "fn_missing",     // node for a called function without a body
+    "locals",         // LOCALS node inside a function cluster
+    "block",          // basic block node
+    "nounwind_block", // basic block whose terminator cannot unwind (applied after `block`)
+    "tainted_block",  // basic block on a TAINT_SOURCES-to-TAINT_SINKS flow (applied after `block`)
+    "dup_block",      // basic block with the same code as other blocks (applied after `block`)
+    "cast_block",     // basic block with a mismatched transmute or pointer cast (after `block`)
+    "line_group",     // cluster of the blocks of one source line (with DOT_GROUP_LINES)
+    "allocs",         // ALLOCS legend node
+    "types",          // TYPES legend node
+    "static",         // static item node
+    "asm",            // global asm node
+    "call_edge",      // edge from a call site to the callee
+    "call_user",      // call edge to user code (applied after `call_edge`, likewise below)
+    "call_std",       // call edge into core/alloc/std
+    "call_intrinsic", // call edge to a compiler intrinsic
+    "call_panic",     // call edge into the panic machinery
+    "call_alloc",     // call edge to the allocator
+    "static_edge",    // edge from a block using a static to the static's node
+    "cleanup_edge",   // unwind edge to a cleanup block
+    "dead_edge",      // `otherwise` edge of an enum match that covers every variant
+];
+
+/// Graphviz attribute names; other keys are passed on with a warning, since
+/// Graphviz ignores them
+const GRAPHVIZ_ATTRIBUTES: &[&str] = &[
+    "_background",
+    "area",
+    "arrowhead",
+    "arrowsize",
+    "arrowtail",
+    "bb",
+    "beautify",
+    "bgcolor",
+    "center",
+    "charset",
+    "class",
+    "cluster",
+    "clusterrank",
+    "color",
+    "colorscheme",
+    "comment",
+    "compound",
+    "concentrate",
+    "constraint",
+    "Damping",
+    "decorate",
+    "defaultdist",
+    "dim",
+    "dimen",
+    "dir",
+    "diredgeconstraints",
+    "distortion",
+    "dpi",
+    "edgehref",
+    "edgetarget",
+    "edgetooltip",
+    "edgeURL",
+    "epsilon",
+    "esep",
+    "fillcolor",
+    "fixedsize",
+    "fontcolor",
+    "fontname",
+    "fontnames",
+    "fontpath",
+    "fontsize",
+    "forcelabels",
+    "gradientangle",
+    "group",
+    "head_lp",
+    "headclip",
+    "headhref",
+    "headlabel",
+    "headport",
+    "headtarget",
+    "headtooltip",
+    "headURL",
+    "height",
+    "href",
+    "id",
+    "image",
+    "imagepath",
+    "imagepos",
+    "imagescale",
+    "inputscale",
+    "K",
+    "label",
+    "label_scheme",
+    "labelangle",
+    "labeldistance",
+    "labelfloat",
+    "labelfontcolor",
+    "labelfontname",
+    "labelfontsize",
+    "labelhref",
+    "labeljust",
+    "labelloc",
+    "labeltarget",
+    "labeltooltip",
+    "labelURL",
+    "landscape",
+    "layer",
+    "layerlistsep",
+    "layers",
+    "layerselect",
+    "layersep",
+    "layout",
+    "len",
+    "levels",
+    "levelsgap",
+    "lhead",
+    "lheight",
+    "linelength",
+    "lp",
+    "ltail",
+    "lwidth",
+    "margin",
+    "maxiter",
+    "mclimit",
+    "mindist",
+    "minlen",
+    "mode",
+    "model",
+    "newrank",
+    "nodesep",
+    "nojustify",
+    "normalize",
+    "notranslate",
+    "nslimit",
+    "nslimit1",
+    "oneblock",
+    "ordering",
+    "orientation",
+    "outputorder",
+    "overlap",
+    "overlap_scaling",
+    "overlap_shrink",
+    "pack",
+    "packmode",
+    "pad",
+    "page",
+    "pagedir",
+    "pencolor",
+    "penwidth",
+    "peripheries",
+    "pin",
+    "pos",
+    "quadtree",
+    "quantum",
+    "radius",
+    "rank",
+    "rankdir",
+    "ranksep",
+    "ratio",
+    "rects",
+    "regular",
+    "remincross",
+    "repulsiveforce",
+    "resolution",
+    "root",
+    "rotate",
+    "rotation",
+    "samehead",
+    "sametail",
+    "samplepoints",
+    "scale",
+    "searchsize",
+    "sep",
+    "shape",
+    "shapefile",
+    "showboxes",
+    "sides",
+    "size",
+    "skew",
+    "smoothing",
+    "sortv",
+    "splines",
+    "start",
+    "style",
+    "stylesheet",
+    "tail_lp",
+    "tailclip",
+    "tailhref",
+    "taillabel",
+    "tailport",
+    "tailtarget",
+    "tailtooltip",
+    "tailURL",
+    "target",
+    "TBbalance",
+    "tooltip",
+    "truecolor",
+    "URL",
+    "vertices",
+    "viewport",
+    "voro_margin",
+    "weight",
+    "width",
+    "xdotversion",
+    "xlabel",
+    "xlp",
+    "z",
+];
+
+impl DotAttributes {
+    /// Read overrides from the file named in `DOT_ATTRS`, or return an empty
+    /// set of overrides if the variable is not set.
+    pub fn from_env() -> Self {
+        match std::env::var("DOT_ATTRS") {
+            Ok(path) => {
+                let text = std::fs::read_to_string(&path)
+                    .unwrap_or_else(|e| panic!("Failed to read DOT_ATTRS file {}: {}", path, e));
+                Self::parse(&text)
+            }
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Parse the attribute file format. Malformed lines and unknown sections
+    /// are reported on stderr and skipped; keys that are not Graphviz
+    /// attributes are reported and kept.
+    pub fn parse(text: &str) -> Self {
+        let mut attrs = Self::default();
+        let mut section = String::from("graph");
+        for (lineno, raw) in text.lines().enumerate() {
+            let line = strip_comment(raw).trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                section = name.trim().to_string();
+                continue;
+            }
+            let Some((key, value)) = line.split_once('=') else {
+                eprintln!(
+                    "DOT_ATTRS line {}: expected `key = value`: {}",
+                    lineno + 1,
+                    raw
+                );
+                continue;
+            };
+            let entry = (
+                key.trim().to_string(),
+                value.trim().trim_matches('"').to_string(),
+            );
+            if !GRAPHVIZ_ATTRIBUTES.contains(&entry.0.as_str()) {
+                eprintln!(
+                    "DOT_ATTRS line {}: `{}` is not a Graphviz attribute",
+                    lineno + 1,
+                    entry.0
+                );
+            }
+            match section.as_str() {
+                "graph" => attrs.graph.push(entry),
+                "node" => attrs.node.push(entry),
+                "edge" => attrs.edge.push(entry),
+                other => match other.strip_prefix("role.") {
+                    Some(role) if ROLES.contains(&role) => {
+                        attrs.roles.entry(role.to_string()).or_default().push(entry)
+                    }
+                    _ => eprintln!(
+                        "DOT_ATTRS line {}: unknown section [{}] (expected graph, node, edge, or role.<{}>)",
+                        lineno + 1,
+                        other,
+                        ROLES.join("|")
+                    ),
+                },
+            }
+        }
+        attrs
+    }
+
+    /// Apply the `[graph]`, `[node]` and `[edge]` defaults to the top-level graph.
+    fn apply_globals(&self, graph: &mut Scope<'_, '_>) {
+        set_all(graph, &self.graph);
+        if !self.node.is_empty() {
+            set_all(&mut graph.node_attributes(), &self.node);
+        }
+        if !self.edge.is_empty() {
+            set_all(&mut graph.edge_attributes(), &self.edge);
+        }
+    }
+
+    /// Apply the overrides for `role` to an element (node, cluster, or edge attributes).
+    fn apply_role<A: Attributes>(&self, role: &str, target: &mut A) {
+        if let Some(entries) = self.roles.get(role) {
+            set_all(target, entries);
+        }
+    }
+
+    /// Style a call edge: the default color of the callee's category, then
+    /// the `call_edge` role and the category's `call_<category>` role
+    fn apply_call_roles<A: Attributes>(&self, category: Option<CallCategory>, target: &mut A) {
+        if let Some(color) = category.and_then(CallCategory::color) {
+            target.set("color", color, false);
+            target.set("fontcolor", color, false);
+        }
+        self.apply_role("call_edge", target);
+        if let Some(category) = category {
+            self.apply_role(&format!("call_{}", category.name()), target);
+        }
+    }
+}
+
+fn set_all<A: Attributes>(target: &mut A, entries: &[(String, String)]) {
+    for (key, value) in entries {
+        target.set(key, value, true);
+    }
+}
 
 impl SmirJson {
     /// Convert the MIR to DOT (Graphviz) format
+    ///
+    /// Attribute overrides are read from the file named by `DOT_ATTRS`
+    /// (see [`DotAttributes`]).
     pub fn to_dot_file(self) -> String {
         let mut bytes = Vec::new();
+        self.write_dot_file(&mut bytes)
+            .expect("writing to memory cannot fail");
+        String::from_utf8(bytes).expect("Error converting dot file")
+    }
 
+    /// Write the MIR in DOT format to `out` as it is rendered, one function
+    /// cluster after the other, dropping each item once it is written
+    pub fn write_dot_file(self, out: &mut dyn Write) -> io::Result<()> {
         // Build context BEFORE consuming self
-        let ctx = GraphContext::from_smir(&self);
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("dot"));
+        let attrs = DotAttributes::from_env();
+        let mut out = FirstError::new(out);
 
         {
-            let mut writer = DotWriter::from(&mut bytes);
+            let mut writer = DotWriter::from(&mut out);
 
             writer.set_pretty_print(true);
 
             let mut graph = writer.digraph();
-            graph.set_label(&self.name[..]);
+            graph.set_label(&escape_dot(&self.name));
             graph.node_attributes().set_shape(Shape::Rectangle);
+            attrs.apply_globals(&mut graph);
 
             let item_names: HashSet<String> =
                 self.items.iter().map(|i| i.symbol_name.clone()).collect();
 
+            // A crate with only statics/asm (or nothing at all) should still say so
+            if !has_functions(&self.items) {
+                let mut n = graph.node_auto();
+                n.set_label(msg("graph.no_functions"))
+                    .set_style(Style::Dashed);
+            }
+
             // Add allocs legend node if there are any allocs
-            if !ctx.allocs.by_id.is_empty() {
+            if !ctx.allocs.by_id.is_empty() && !skeleton_enabled() {
                 let mut alloc_node = graph.node_auto();
                 let mut lines = ctx.allocs_legend_lines();
                 lines.push("".to_string());
-                alloc_node.set_label(&lines.join("\\l"));
+                alloc_node.set_label(&dot_lines(&lines, "\\l"));
                 alloc_node.set_style(Style::Filled);
                 alloc_node.set("color", "lightyellow", false);
+                attrs.apply_role("allocs", &mut *alloc_node);
             }
 
             // Add types legend node showing composite types with layouts
             let type_lines = ctx.types_legend_lines();
-            if type_lines.len() > 1 {
+            if type_lines.len() > 1 && !skeleton_enabled() {
                 // Only show if there are actual types (more than just "TYPES" header)
                 let mut type_node = graph.node_auto();
                 let mut lines = type_lines;
                 lines.push("".to_string());
-                type_node.set_label(&lines.join("\\l"));
+                type_node.set_label(&dot_lines(&lines, "\\l"));
                 type_node.set_style(Style::Filled);
                 type_node.set("color", "lavender", false);
+                attrs.apply_role("types", &mut *type_node);
             }
 
             // first create all nodes for functions not in the items list
             for f in ctx.functions.values() {
                 if !item_names.contains(f) {
-                    graph
-                        .node_named(block_name(f, 0))
-                        .set_label(&name_lines(f))
+                    let mut n = graph.node_named(block_name(f, 0));
+                    n.set_label(&dot_lines(&name_lines(f), "\\n"))
                         .set_color(Color::Red);
+                    attrs.apply_role("fn_missing", &mut *n);
                 }
             }
 
@@ -69,168 +452,72 @@ impl SmirJson {
                 match item.mono_item_kind {
                     MonoItemKind::MonoItemFn { name, body, id: _ } => {
                         let mut c = graph.cluster();
-                        c.set_label(&name_lines(&name));
+                        c.set_label(&dot_lines(&name_lines(&name), "\\n"));
                         c.set_style(Style::Filled);
                         if is_unqualified(&name) {
                             c.set_color(Color::PaleGreen);
+                            attrs.apply_role("fn_local", &mut c);
                         } else {
                             c.set_color(Color::LightGrey);
+                            attrs.apply_role("fn_external", &mut c);
                         }
 
                         // Set out the type information of the locals with layout info
-                        let mut local_node = c.node_auto();
-                        let mut vector: Vec<String> = vec![];
-                        vector.push(String::from("LOCALS"));
-                        for (index, decl) in body.clone().unwrap().local_decls() {
-                            let ty_with_layout = ctx.render_type_with_layout(decl.ty);
-                            vector.push(format!("{index} = {}", ty_with_layout));
+                        if !skeleton_enabled() {
+                            let mut local_node = c.node_auto();
+                            let mut vector: Vec<String> = vec![];
+                            vector.push(String::from("LOCALS"));
+                            for (index, decl) in body.clone().unwrap().local_decls() {
+                                let ty_with_layout = ctx.render_type_with_layout(decl.ty);
+                                vector.push(format!("{index} = {}", ty_with_layout));
+                            }
+                            if let Some(body) = &body {
+                                vector.extend(return_summary(&return_sources(&ctx, body)));
+                            }
+                            vector.push("".to_string());
+                            local_node.set_label(&dot_lines(&vector, "\\l"));
+                            local_node.set_style(Style::Filled);
+                            local_node.set("color", "palegreen3", false);
+                            attrs.apply_role("locals", &mut *local_node);
                         }
-                        vector.push("".to_string());
-                        local_node.set_label(vector.join("\\l").to_string().as_str());
-                        local_node.set_style(Style::Filled);
-                        local_node.set("color", "palegreen3", false);
-                        drop(local_node);
-
-                        // Cannot define local functions that capture env. variables. Instead we define _closures_.
-                        let process_block =
-                            |cluster: &mut Scope<'_, '_>, node_id: usize, b: &BasicBlock| {
-                                let name = &item.symbol_name;
-                                let this_block = block_name(name, node_id);
-
-                                let mut label_strs: Vec<String> =
-                                    b.statements.iter().map(|s| ctx.render_stmt(s)).collect();
-
-                                use TerminatorKind::*;
-                                match &b.terminator.kind {
-                                    Goto { target } => {
-                                        label_strs.push("Goto".to_string());
-                                        cluster.edge(&this_block, block_name(name, *target));
-                                    }
-                                    SwitchInt { discr, targets } => {
-                                        label_strs.push(format!(
-                                            "SwitchInt {}",
-                                            ctx.render_operand(discr)
-                                        ));
-                                        for (d, t) in targets.clone().branches() {
-                                            cluster
-                                                .edge(&this_block, block_name(name, t))
-                                                .attributes()
-                                                .set_label(&format!("{d}"));
-                                        }
-                                        cluster
-                                            .edge(
-                                                &this_block,
-                                                block_name(name, targets.otherwise()),
-                                            )
-                                            .attributes()
-                                            .set_label("other");
-                                    }
-                                    Resume {} => {
-                                        label_strs.push("Resume".to_string());
-                                    }
-                                    Abort {} => {
-                                        label_strs.push("Abort".to_string());
-                                    }
-                                    Return {} => {
-                                        label_strs.push("Return".to_string());
-                                    }
-                                    Unreachable {} => {
-                                        label_strs.push("Unreachable".to_string());
-                                    }
-                                    TerminatorKind::Drop {
-                                        place,
-                                        target,
-                                        unwind,
-                                    } => {
-                                        label_strs.push(format!("Drop {}", place.label()));
-                                        if let UnwindAction::Cleanup(t) = unwind {
-                                            cluster
-                                                .edge(&this_block, block_name(name, *t))
-                                                .attributes()
-                                                .set_label("Cleanup");
-                                        }
-                                        cluster.edge(&this_block, block_name(name, *target));
-                                    }
-                                    Call {
-                                        func: _,
-                                        args: _,
-                                        destination,
-                                        target,
-                                        unwind,
-                                    } => {
-                                        label_strs.push("Call".to_string());
-                                        if let UnwindAction::Cleanup(t) = unwind {
-                                            cluster
-                                                .edge(&this_block, block_name(name, *t))
-                                                .attributes()
-                                                .set_label("Cleanup");
-                                        }
-                                        if let Some(t) = target {
-                                            let dest = destination.label();
-                                            cluster
-                                                .edge(&this_block, block_name(name, *t))
-                                                .attributes()
-                                                .set_label(&dest);
-                                        }
 
-                                        // The call edge has to be drawn outside the cluster, outside this function (cluster borrows &mut graph)!
-                                        // Code for that is therefore separated into its own second function below.
-                                    }
-                                    Assert {
-                                        cond,
-                                        expected,
-                                        msg: _,
-                                        target,
-                                        unwind,
-                                    } => {
-                                        label_strs.push(format!(
-                                            "Assert {} == {}",
-                                            ctx.render_operand(cond),
-                                            expected
-                                        ));
-                                        if let UnwindAction::Cleanup(t) = unwind {
-                                            cluster
-                                                .edge(&this_block, block_name(name, *t))
-                                                .attributes()
-                                                .set_label("Cleanup");
-                                        }
-                                        cluster.edge(&this_block, block_name(name, *target));
-                                    }
-                                    InlineAsm {
-                                        destination,
-                                        unwind,
-                                        ..
-                                    } => {
-                                        label_strs.push("Inline ASM".to_string());
-                                        if let Some(t) = destination {
-                                            cluster.edge(&this_block, block_name(name, *t));
-                                        }
-                                        if let UnwindAction::Cleanup(t) = unwind {
-                                            cluster
-                                                .edge(&this_block, block_name(name, *t))
-                                                .attributes()
-                                                .set_label("Cleanup");
+                        let shown = body
+                            .as_ref()
+                            .map(|body| shown_blocks(body, ctx.ub_checks))
+                            .unwrap_or_default();
+                        if let Some(body) = &body {
+                            let mut blocks = DotBlocks {
+                                cluster: &mut c,
+                                name: &item.symbol_name,
+                                attrs: &attrs,
+                                summaries: (0..body.blocks.len())
+                                    .map(|idx| {
+                                        if skeleton_enabled() {
+                                            None
+                                        } else {
+                                            block_summary(&ctx, body, idx)
                                         }
-                                    }
-                                }
-                                let mut n = cluster.node_named(&this_block);
-                                label_strs.push("".to_string());
-                                n.set_label(&label_strs.join("\\l"));
+                                    })
+                                    .collect(),
                             };
+                            time_fn(&name, || {
+                                walk_body(&ctx, &item.symbol_name, body, &mut blocks)
+                            });
 
-                        let process_blocks =
-                            |cluster: &mut Scope<'_, '_>, offset, blocks: &Vec<BasicBlock>| {
-                                let mut n: usize = offset;
-                                for b in blocks {
-                                    process_block(cluster, n, b);
-                                    n += 1;
+                            // nodes named again in a nested cluster move into it
+                            if group_lines_enabled() && !skeleton_enabled() {
+                                for group in line_groups(body, &ctx) {
+                                    let mut g = c.cluster();
+                                    g.set_label(&escape_dot(&group.label()));
+                                    g.set_style(Style::Dashed);
+                                    attrs.apply_role("line_group", &mut g);
+                                    for idx in group.blocks.into_iter().filter(|b| shown[*b]) {
+                                        g.node_named(block_name(&item.symbol_name, idx));
+                                    }
                                 }
-                            };
-
-                        if let Some(body) = &body {
-                            process_blocks(&mut c, 0, &body.blocks);
+                            }
                         } else {
-                            c.node_auto().set_label("<empty body>");
+                            c.node_auto().set_label(msg("graph.empty_body"));
                         }
 
                         drop(c); // so we can borrow graph again
@@ -241,6 +528,9 @@ impl SmirJson {
                         let add_call_edges =
                             |graph: &mut Scope<'_, '_>, offset: usize, bs: &Vec<BasicBlock>| {
                                 for (i, b) in bs.iter().enumerate() {
+                                    if !shown[offset + i] {
+                                        continue;
+                                    }
                                     let this_block = block_name(&item.symbol_name, offset + i);
 
                                     match &b.terminator.kind {
@@ -260,30 +550,58 @@ impl SmirJson {
                                                     } else {
                                                         let unknown = format!("{}", const_.ty());
                                                         // pathological case, could panic! instead.
-                                                        // all unknown callees will be collapsed into one `unknown` node
-                                                        graph.edge(&this_block, unknown)
+                                                        // calls to the same unknown callee share a node
+                                                        let id =
+                                                            callee_node(graph, &unknown, &unknown);
+                                                        graph.edge(&this_block, id)
                                                     }
                                                 }
-                                                Operand::Copy(place) => graph.edge(
-                                                    &this_block,
-                                                    format!("{}: {}", &this_block, place.label()),
-                                                ),
-                                                Operand::Move(place) => graph.edge(
-                                                    &this_block,
-                                                    format!("{}: {}", &this_block, place.label()),
-                                                ),
+                                                Operand::Copy(place) | Operand::Move(place) => {
+                                                    // one node per indirect call site
+                                                    let id = callee_node(
+                                                        graph,
+                                                        &format!(
+                                                            "{}: {}",
+                                                            this_block,
+                                                            place.label()
+                                                        ),
+                                                        &place.label(),
+                                                    );
+                                                    graph.edge(&this_block, id)
+                                                }
                                             };
-                                            let arg_str = args
-                                                .iter()
-                                                .map(|op| ctx.render_operand(op))
-                                                .collect::<Vec<String>>()
-                                                .join(",");
-                                            e.attributes().set_label(&arg_str);
+                                            let mut arg_str = ctx.call_arg_mapping(func, args);
+                                            if let Some(note) =
+                                                ctx.resolve_call_target(func).and_then(|callee| {
+                                                    ctx.recursion_note(&item.symbol_name, &callee)
+                                                })
+                                            {
+                                                arg_str = if arg_str.is_empty() {
+                                                    note
+                                                } else {
+                                                    format!("{} ({})", arg_str, note)
+                                                };
+                                            }
+                                            attrs.apply_call_roles(
+                                                ctx.call_category(func),
+                                                e.attributes().set_label(&escape_dot(&arg_str)),
+                                            );
                                         }
                                         _other => {
                                             // nothing to do
                                         }
                                     }
+
+                                    // uses of statics point back to their definition
+                                    for static_name in ctx.static_refs(b) {
+                                        attrs.apply_role(
+                                            "static_edge",
+                                            graph
+                                                .edge(&this_block, short_name(&static_name))
+                                                .attributes()
+                                                .set_style(Style::Dashed),
+                                        );
+                                    }
                                 }
                             };
 
@@ -293,16 +611,233 @@ impl SmirJson {
                     }
                     MonoItemKind::MonoItemGlobalAsm { asm } => {
                         let mut n = graph.node_named(short_name(&asm));
-                        n.set_label(&asm.lines().collect::<String>()[..]);
+                        n.set_label(&escape_dot(&asm.lines().collect::<String>()));
+                        attrs.apply_role("asm", &mut *n);
                     }
-                    MonoItemKind::MonoItemStatic { name, .. } => {
+                    MonoItemKind::MonoItemStatic {
+                        name,
+                        allocation,
+                        body,
+                        ..
+                    } => {
+                        let mut lines = ctx.static_lines(&name, allocation.as_ref(), body.as_ref());
+                        lines.push("".to_string());
                         let mut n = graph.node_named(short_name(&name));
-                        n.set_label(&name[..]);
+                        n.set_label(&dot_lines(&lines, "\\l"));
+                        attrs.apply_role("static", &mut *n);
                     }
                 }
             }
         }
+        out.finish()
+    }
+}
 
-        String::from_utf8(bytes).expect("Error converting dot file")
+/// Writer passing everything on to `inner` until a write fails, then
+/// discarding the rest. `DotWriter` panics on write errors, so it writes
+/// through this and the first error is returned by [`FirstError::finish`].
+struct FirstError<'a> {
+    inner: &'a mut dyn Write,
+    error: Option<io::Error>,
+}
+
+impl<'a> FirstError<'a> {
+    fn new(inner: &'a mut dyn Write) -> Self {
+        Self { inner, error: None }
+    }
+
+    fn finish(mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => self.inner.flush(),
+        }
+    }
+}
+
+impl Write for FirstError<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.error.is_none() {
+            if let Err(e) = self.inner.write_all(buf) {
+                self.error = Some(e);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// =============================================================================
+// Block Rendering
+// =============================================================================
+
+/// Writes the blocks and intra-function edges of one body into its cluster
+struct DotBlocks<'c, 'd, 'w> {
+    cluster: &'c mut Scope<'d, 'w>,
+    name: &'c str,
+    attrs: &'c DotAttributes,
+    /// [`block_summary`] of each block, shown as its tooltip
+    summaries: Vec<Option<String>>,
+}
+
+impl BodyRenderer for DotBlocks<'_, '_, '_> {
+    fn block(
+        &mut self,
+        idx: usize,
+        statements: Vec<String>,
+        terminator: String,
+        marks: BlockMarks,
+    ) {
+        let mut label_strs = statements;
+        label_strs.push(terminator);
+        label_strs.push("".to_string());
+        let mut n = self.cluster.node_named(block_name(self.name, idx));
+        n.set_label(&dot_lines(&label_strs, "\\l"));
+        if let Some(Some(summary)) = self.summaries.get(idx) {
+            n.set("tooltip", &escape_dot(summary), true);
+        }
+        self.attrs.apply_role("block", &mut *n);
+        if marks.nounwind {
+            n.set("peripheries", "2", false);
+            self.attrs.apply_role("nounwind_block", &mut *n);
+        }
+        if let Some(group) = marks.duplicate {
+            n.set_style(Style::Filled);
+            n.set("fillcolor", duplicate_color(group), false);
+            self.attrs.apply_role("dup_block", &mut *n);
+        }
+        // taint takes precedence over the duplicate coloring
+        if marks.tainted {
+            n.set_style(Style::Filled);
+            n.set("fillcolor", "mistyrose", false);
+            self.attrs.apply_role("tainted_block", &mut *n);
+        }
+        // the border stays free for layout mismatches, whatever the fill
+        if marks.bad_cast {
+            n.set_color(Color::Red);
+            n.set("penwidth", "2", false);
+            self.attrs.apply_role("cast_block", &mut *n);
+        }
+    }
+
+    fn edge(&mut self, from: usize, edge: &TerminatorEdge, label: String, location: Option<&str>) {
+        let this_block = block_name(self.name, from);
+        let target = block_name(self.name, edge.target);
+        let kind = &edge.kind;
+        let tooltip = location.map(|l| escape_dot(&fill(msg("graph.decided_at"), &[&l])));
+        let label = escape_dot(&label);
+        let e = self.cluster.edge(this_block, target);
+        match (tooltip, label.is_empty()) {
+            (Some(tooltip), false) => apply_cfg_edge_role(
+                self.attrs,
+                kind,
+                e.attributes()
+                    .set("tooltip", &tooltip, true)
+                    .set_label(&label),
+            ),
+            (Some(tooltip), true) => apply_cfg_edge_role(
+                self.attrs,
+                kind,
+                e.attributes().set("tooltip", &tooltip, true),
+            ),
+            (None, false) => {
+                apply_cfg_edge_role(self.attrs, kind, e.attributes().set_label(&label))
+            }
+            // plain successors stay unlabelled
+            (None, true) => {}
+        }
+    }
+}
+
+/// Node for a call target that is not a known function: `key` names the node,
+/// `label` is shown. Returns the node's ID.
+fn callee_node(graph: &mut Scope<'_, '_>, key: &str, label: &str) -> String {
+    let id = short_name(key);
+    graph.node_named(id.clone()).set_label(&escape_dot(label));
+    id
+}
+
+/// Apply the cleanup role to unwind edges, and draw unreachable `otherwise`
+/// edges dashed and grey
+fn apply_cfg_edge_role<A: Attributes>(attrs: &DotAttributes, kind: &EdgeKind, target: &mut A) {
+    match kind {
+        EdgeKind::Cleanup => attrs.apply_role("cleanup_edge", target),
+        EdgeKind::UnreachableOtherwise => {
+            target
+                .set_style(Style::Dashed)
+                .set("color", "grey", false)
+                .set("fontcolor", "grey", false);
+            attrs.apply_role("dead_edge", target);
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pairs(entries: &[(String, String)]) -> Vec<(&str, &str)> {
+        entries
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn parse_sorts_entries_into_sections() {
+        let attrs = DotAttributes::parse(
+            "rankdir = LR   # before any header: [graph]\n\
+             [node]\n\
+             fontname = \"Helvetica\"\n\
+             \n\
+             [ edge ]\n\
+             color = \"#336699\"\n\
+             [graph]\n\
+             dpi=150\n\
+             [role.fn_local]\n\
+             color = lightblue\n\
+             [role.block]\n\
+             shape = box\n\
+             [role.fn_local]\n\
+             style = filled\n",
+        );
+        assert_eq!(pairs(&attrs.graph), [("rankdir", "LR"), ("dpi", "150")]);
+        assert_eq!(pairs(&attrs.node), [("fontname", "Helvetica")]);
+        assert_eq!(pairs(&attrs.edge), [("color", "#336699")]);
+        assert_eq!(
+            pairs(&attrs.roles["fn_local"]),
+            [("color", "lightblue"), ("style", "filled")]
+        );
+        assert_eq!(pairs(&attrs.roles["block"]), [("shape", "box")]);
+    }
+
+    #[test]
+    fn parse_skips_malformed_lines_and_unknown_sections() {
+        let attrs = DotAttributes::parse(
+            "[graph]\n\
+             rankdir\n\
+             [role.no_such_role]\n\
+             color = red\n\
+             [nodes]\n\
+             color = blue\n\
+             [node]\n\
+             colour = green\n",
+        );
+        assert!(attrs.graph.is_empty());
+        assert!(attrs.edge.is_empty());
+        assert!(attrs.roles.is_empty());
+        // unknown attribute names are only reported
+        assert_eq!(pairs(&attrs.node), [("colour", "green")]);
+    }
+
+    #[test]
+    fn role_and_attribute_names_are_unique() {
+        let roles: HashSet<&str> = ROLES.iter().copied().collect();
+        assert_eq!(roles.len(), ROLES.len());
+        let attributes: HashSet<&str> = GRAPHVIZ_ATTRIBUTES.iter().copied().collect();
+        assert_eq!(attributes.len(), GRAPHVIZ_ATTRIBUTES.len());
     }
 }
@@ -0,0 +1,104 @@
+//! Block execution-count harness for an instrumented build.
+//!
+//! `--instrument` writes a Rust module (`*.counters.rs`) with one counter per
+//! basic block of the crate's own functions and a table mapping each counter
+//! to its function symbol, block number and source location. The user adds
+//! the module to the crate, calls `hit(index)` where the source of each
+//! interesting block starts, and calls `dump()` before exiting. The resulting
+//! counts file, passed back in `BLOCK_COUNTS`, annotates the graphs with how
+//! often every counted block ran (see [`crate::mk_graph::counts`]).
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::limits::LabelLimits;
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+impl SmirJson {
+    /// The counter module for all functions with a body in the crate's own
+    /// source files
+    pub fn to_counter_harness(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("rs"));
+        let local_files: Vec<&str> = self
+            .source_files
+            .iter()
+            .filter(|f| f.krate == self.name)
+            .map(|f| f.file.as_str())
+            .collect();
+
+        let mut entries = vec![];
+        for item in &self.items {
+            let MonoItemKind::MonoItemFn {
+                body: Some(body), ..
+            } = &item.mono_item_kind
+            else {
+                continue;
+            };
+            let is_local = ctx
+                .source_data(&body.span)
+                .is_some_and(|(file, ..)| local_files.contains(&file.as_str()));
+            if !is_local {
+                continue;
+            }
+            for (idx, block) in body.blocks.iter().enumerate() {
+                let span = block
+                    .statements
+                    .first()
+                    .map_or(&block.terminator.span, |s| &s.span);
+                let location = ctx.source_location(span).unwrap_or_default();
+                entries.push(format!(
+                    "    ({:?}, {}, {:?}),\n",
+                    item.symbol_name, idx, location
+                ));
+            }
+        }
+
+        let mut out = format!(
+            "// Block counters for crate `{}`, generated by stable_mir_json --instrument.\n\
+             //\n\
+             // Call `hit(i)` where the code of block `BLOCKS[i]` starts (its source\n\
+             // location is the third field) and `dump()` before the program exits.\n\
+             // Pass the written file back in BLOCK_COUNTS to annotate the graphs.\n\
+             \n\
+             use std::sync::atomic::{{AtomicU64, Ordering}};\n\
+             \n\
+             /// (function symbol, block, source location) of each counter\n\
+             pub const BLOCKS: [(&str, usize, &str); {}] = [\n",
+            self.name,
+            entries.len()
+        );
+        for entry in &entries {
+            out.push_str(entry);
+        }
+        out.push_str(&format!(
+            "];\n\
+             \n\
+             #[allow(clippy::declare_interior_mutable_const)]\n\
+             const ZERO: AtomicU64 = AtomicU64::new(0);\n\
+             pub static COUNTS: [AtomicU64; {}] = [ZERO; {}];\n\
+             \n\
+             /// Count one execution of block `BLOCKS[index]`\n\
+             #[inline(always)]\n\
+             pub fn hit(index: usize) {{\n\
+             \x20   COUNTS[index].fetch_add(1, Ordering::Relaxed);\n\
+             }}\n\
+             \n\
+             /// Write `<symbol> <block> <count>` lines for the executed blocks to the\n\
+             /// file named in BLOCK_COUNTS_OUT, or `block_counts.txt`\n\
+             pub fn dump() -> std::io::Result<()> {{\n\
+             \x20   let path = std::env::var(\"BLOCK_COUNTS_OUT\")\n\
+             \x20       .unwrap_or_else(|_| \"block_counts.txt\".to_string());\n\
+             \x20   let mut out = String::new();\n\
+             \x20   for (counter, (symbol, block, _)) in COUNTS.iter().zip(BLOCKS) {{\n\
+             \x20       let count = counter.load(Ordering::Relaxed);\n\
+             \x20       if count > 0 {{\n\
+             \x20           out.push_str(&format!(\"{{}} {{}} {{}}\\n\", symbol, block, count));\n\
+             \x20       }}\n\
+             \x20   }}\n\
+             \x20   std::fs::write(path, out)\n\
+             }}\n",
+            entries.len(),
+            entries.len()
+        ));
+        out
+    }
+}
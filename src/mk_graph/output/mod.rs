@@ -1,4 +1,16 @@
 //! Output format modules for MIR graph generation.
 
+pub mod annotate;
+pub mod arith;
+pub mod copies;
 pub mod d2;
+pub mod deps;
+pub mod diff;
 pub mod dot;
+pub mod instrument;
+pub mod panics;
+pub mod sarif;
+pub mod skeleton;
+pub mod spans;
+pub mod summary;
+pub mod svg;
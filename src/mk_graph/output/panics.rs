@@ -0,0 +1,262 @@
+//! Inventory of the places where a crate can panic.
+//!
+//! Every function body is searched for
+//! - `assert` terminators (overflow, bounds, division and alignment checks),
+//!   with the message the check panics with,
+//! - calls into the panic machinery (`panic!`, `unreachable!`, failed
+//!   `assert!`s, ...), with the string constants of the call and of the
+//!   blocks leading into it, which hold the panic message or its format
+//!   string pieces,
+//! - calls to `unwrap` and `expect` (and their `_err` forms), with the
+//!   message passed to `expect`.
+//!
+//! The inventory is written as a Markdown section for audit documents or as
+//! a JSON list with source locations for tools, e.g. to check that code for
+//! an embedded target has no panic paths left.
+
+use std::collections::HashMap;
+
+use crate::compat::serde;
+use crate::compat::serde_json;
+use crate::compat::stable_mir;
+use serde::Serialize;
+use stable_mir::mir::alloc::GlobalAlloc;
+use stable_mir::mir::visit::{Location, MirVisitor};
+use stable_mir::mir::{AssertMessage, BasicBlock, Body, TerminatorKind};
+use stable_mir::ty::{ConstantKind, IndexedVal, MirConst};
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::index::AllocKind;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::messages::{fill, msg};
+use crate::mk_graph::util::{callee_path, terminator_targets, CallCategory};
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+/// One place where a function can panic
+#[derive(Serialize)]
+pub struct PanicSite {
+    pub function: String,
+    pub block: usize,
+    /// `assert`, `panic`, `unwrap` or `expect`
+    pub kind: &'static str,
+    /// The check's message, or the string constants passed to the panic
+    pub messages: Vec<String>,
+    /// `file:line` of the terminator, if its span was recorded
+    pub location: Option<String>,
+    /// Index of the terminator's span in the `spans` table
+    pub span: usize,
+}
+
+impl SmirJson {
+    /// The panic inventory as a Markdown section
+    pub fn to_panics_markdown(self) -> String {
+        let sites = panic_sites(&self, "md");
+        let mut out = fill(msg("panics.title"), &[&self.name]);
+        out.push_str("\n\n");
+        for kind in ["assert", "panic", "unwrap", "expect"] {
+            let count = sites.iter().filter(|s| s.kind == kind).count();
+            out.push_str(&format!("- {}: {}\n", kind, count));
+        }
+        out.push_str(&format!("\n{}\n", msg("panics.header")));
+        out.push_str("|---|---|---|---|---|\n");
+        for site in sites {
+            let messages: Vec<String> = site
+                .messages
+                .iter()
+                .map(|m| format!("`{}`", m.replace('`', "'")))
+                .collect();
+            out.push_str(&format!(
+                "| `{}` | bb{} | {} | {} | {} |\n",
+                site.function.replace('|', "\\|"),
+                site.block,
+                site.kind,
+                messages.join(" ").replace('|', "\\|"),
+                site.location.unwrap_or_default()
+            ));
+        }
+        out
+    }
+
+    /// The panic inventory as a JSON list
+    pub fn to_panics_json(self) -> String {
+        let sites = panic_sites(&self, "json");
+        serde_json::to_string_pretty(&sites).expect("serde_json failed to write panic inventory")
+    }
+}
+
+/// Panic sites of all function bodies of the crate, by function name and
+/// block, with the label limits of output `format`
+pub fn panic_sites(smir: &SmirJson, format: &str) -> Vec<PanicSite> {
+    let ctx = GraphContext::with_limits(smir, LabelLimits::for_format(format));
+    let strings = Strings::new(smir, &ctx);
+    let mut sites: Vec<PanicSite> = smir
+        .items
+        .iter()
+        .filter_map(|item| match &item.mono_item_kind {
+            MonoItemKind::MonoItemFn {
+                name,
+                body: Some(body),
+                ..
+            } => Some(body_sites(&ctx, &strings, name, body)),
+            _ => None,
+        })
+        .flatten()
+        .collect();
+    sites.sort_by(|a, b| a.function.cmp(&b.function).then(a.block.cmp(&b.block)));
+    sites
+}
+
+fn body_sites(
+    ctx: &GraphContext,
+    strings: &Strings,
+    function: &str,
+    body: &Body,
+) -> Vec<PanicSite> {
+    let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+    for (idx, block) in body.blocks.iter().enumerate() {
+        for succ in terminator_targets(&block.terminator) {
+            predecessors.entry(succ).or_default().push(idx);
+        }
+    }
+    // string constants of a block and of the blocks leading into it
+    let messages_near = |idx: usize| {
+        let mut found: Vec<String> = vec![];
+        let blocks =
+            std::iter::once(idx).chain(predecessors.get(&idx).into_iter().flatten().copied());
+        for s in blocks.flat_map(|b| strings.of_block(&body.blocks[b])) {
+            if !found.contains(&s) {
+                found.push(s);
+            }
+        }
+        found
+    };
+
+    let mut sites = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let site = match &block.terminator.kind {
+            TerminatorKind::Assert { msg, .. } => Some(("assert", vec![assert_message(msg)])),
+            TerminatorKind::Call { func, .. } => {
+                let path = callee_path(func).unwrap_or_default();
+                let method = path.rsplit("::").next().unwrap_or_default();
+                if CallCategory::from_path(&path) == CallCategory::Panic {
+                    Some(("panic", messages_near(idx)))
+                } else if matches!(method, "unwrap" | "unwrap_err") {
+                    Some(("unwrap", vec![]))
+                } else if matches!(method, "expect" | "expect_err") {
+                    Some(("expect", strings.of_block(block)))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        if let Some((kind, messages)) = site {
+            let span = &block.terminator.span;
+            sites.push(PanicSite {
+                function: function.to_string(),
+                block: idx,
+                kind,
+                messages: messages.into_iter().map(|m| ctx.limits.clip(m)).collect(),
+                location: ctx.source_location(span),
+                span: span.to_index(),
+            });
+        }
+    }
+    sites
+}
+
+/// The message a failed check panics with
+fn assert_message(message: &AssertMessage) -> String {
+    match message.description() {
+        Ok(text) => text.to_string(),
+        Err(_) => match message {
+            AssertMessage::BoundsCheck { .. } => msg("panics.bounds_check").to_string(),
+            AssertMessage::MisalignedPointerDereference { .. } => {
+                msg("panics.misaligned").to_string()
+            }
+            _ => msg("panics.assert").to_string(),
+        },
+    }
+}
+
+/// The string allocations of a crate, and the allocations pointing to
+/// them (such as the pieces of a format string)
+struct Strings {
+    /// Text of each string allocation
+    text: HashMap<u64, String>,
+    /// Allocations each allocation points to
+    targets: HashMap<u64, Vec<u64>>,
+}
+
+impl Strings {
+    fn new(smir: &SmirJson, ctx: &GraphContext) -> Self {
+        let mut text = HashMap::new();
+        let mut targets = HashMap::new();
+        for info in &smir.allocs {
+            let GlobalAlloc::Memory(alloc) = info.global_alloc() else {
+                continue;
+            };
+            let id = info.alloc_id().to_index() as u64;
+            let is_str = matches!(
+                ctx.allocs.get(id).map(|e| &e.kind),
+                Some(AllocKind::Memory { is_str: true, .. })
+            );
+            if is_str {
+                let bytes: Option<Vec<u8>> = alloc.bytes.iter().copied().collect();
+                if let Some(s) = bytes.and_then(|b| String::from_utf8(b).ok()) {
+                    text.insert(id, s);
+                }
+            }
+            let ptrs: Vec<u64> = alloc
+                .provenance
+                .ptrs
+                .iter()
+                .map(|(_, prov)| prov.0.to_index() as u64)
+                .collect();
+            if !ptrs.is_empty() {
+                targets.insert(id, ptrs);
+            }
+        }
+        Self { text, targets }
+    }
+
+    /// Strings that constants in `block` point to, directly or through one
+    /// allocation of pointers
+    fn of_block(&self, block: &BasicBlock) -> Vec<String> {
+        let mut collector = ProvenanceCollector { ids: vec![] };
+        collector.visit_basic_block(block);
+        let mut found = vec![];
+        for id in collector.ids {
+            let nested = self.targets.get(&id).into_iter().flatten().copied();
+            for id in std::iter::once(id).chain(nested) {
+                if let Some(s) = self.text.get(&id) {
+                    if !found.contains(s) {
+                        found.push(s.clone());
+                    }
+                }
+            }
+        }
+        found
+    }
+}
+
+/// Collects the allocations pointed to by constants
+struct ProvenanceCollector {
+    ids: Vec<u64>,
+}
+
+impl MirVisitor for ProvenanceCollector {
+    fn visit_mir_const(&mut self, constant: &MirConst, loc: Location) {
+        if let ConstantKind::Allocated(alloc) = constant.kind() {
+            self.ids.extend(
+                alloc
+                    .provenance
+                    .ptrs
+                    .iter()
+                    .map(|(_, prov)| prov.0.to_index() as u64),
+            );
+        }
+        self.super_mir_const(constant, loc);
+    }
+}
@@ -0,0 +1,98 @@
+//! SARIF 2.1.0 log of analysis findings.
+//!
+//! Code scanning tools (GitHub, IDE plugins) read SARIF, so the findings of
+//! the checks in [`crate::mk_graph::taint`] and [`crate::mk_graph::casts`] are written in that format rather
+//! than a bespoke one. Each result points at the source span of the flagged
+//! statement or terminator and names the function and block.
+
+use crate::compat::serde_json;
+use serde_json::{json, Value};
+
+use crate::mk_graph::casts::cast_findings;
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::liveness::dead_stores;
+use crate::mk_graph::messages::{fill, msg};
+use crate::mk_graph::taint::{constant_time_findings, flow_findings, taint_flows, Finding};
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+/// Rules that findings can refer to: (id, message ID of the short description)
+const RULES: &[(&str, &str)] = &[
+    ("ct-branch", "sarif.ct_branch"),
+    ("ct-index", "sarif.ct_index"),
+    ("taint-flow", "sarif.taint_flow"),
+    ("cast-size", "sarif.cast_size"),
+    ("cast-align", "sarif.cast_align"),
+    ("cast-fat-to-thin", "sarif.cast_fat_to_thin"),
+    ("dead-store", "sarif.dead_store"),
+];
+
+impl SmirJson {
+    /// Findings of all analyses over the crate's function bodies, as a SARIF log
+    pub fn to_sarif(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("sarif"));
+        let mut results: Vec<Value> = vec![];
+        for item in &self.items {
+            let MonoItemKind::MonoItemFn {
+                name,
+                body: Some(body),
+                ..
+            } = &item.mono_item_kind
+            else {
+                continue;
+            };
+            let mut findings = constant_time_findings(body);
+            findings.extend(flow_findings(&taint_flows(body, &ctx)));
+            findings.extend(cast_findings(body, &ctx));
+            findings.extend(dead_stores(body));
+            for finding in findings {
+                results.push(sarif_result(&ctx, name, &finding));
+            }
+        }
+
+        let rules: Vec<Value> = RULES
+            .iter()
+            .map(|(id, text)| json!({ "id": id, "shortDescription": { "text": msg(text) } }))
+            .collect();
+        let log = json!({
+            "version": "2.1.0",
+            "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+            "runs": [{
+                "tool": {
+                    "driver": {
+                        "name": "stable-mir-json",
+                        "version": env!("CARGO_PKG_VERSION"),
+                        "rules": rules,
+                    }
+                },
+                "results": results,
+            }]
+        });
+        serde_json::to_string_pretty(&log).expect("serde_json failed to write SARIF log")
+    }
+}
+
+fn sarif_result(ctx: &GraphContext, function: &str, finding: &Finding) -> Value {
+    let mut result = json!({
+        "ruleId": finding.rule,
+        "level": "warning",
+        "message": {
+            "text": fill(msg("sarif.result"), &[&finding.message, &function, &finding.block])
+        },
+    });
+    if let Some((file, lo_line, lo_col, hi_line, hi_col)) = ctx.source_data(&finding.span) {
+        result["locations"] = json!([{
+            "physicalLocation": {
+                "artifactLocation": { "uri": file },
+                "region": {
+                    "startLine": lo_line,
+                    "startColumn": lo_col,
+                    "endLine": hi_line,
+                    "endColumn": hi_col,
+                }
+            }
+        }]);
+    }
+    result
+}
@@ -0,0 +1,92 @@
+//! Control-flow skeletons in JSON.
+//!
+//! Written instead of `*.smir.json` under `--skeleton`: for every function
+//! with a body, its blocks with their roles and outgoing edges, and nothing
+//! about statements, operands, types or allocations (see
+//! [`crate::mk_graph::skeleton`]).
+
+use crate::compat::serde;
+use crate::compat::serde_json;
+use serde::Serialize;
+
+use crate::mk_graph::skeleton::{block_role, cleanup_blocks, edge_role};
+use crate::mk_graph::util::terminator_edges;
+use crate::printer::SmirJson;
+use crate::MonoItemKind;
+
+#[derive(Serialize)]
+pub struct Skeleton {
+    pub name: String,
+    pub functions: Vec<SkeletonFunction>,
+}
+
+#[derive(Serialize)]
+pub struct SkeletonFunction {
+    pub name: String,
+    pub symbol: String,
+    pub blocks: Vec<SkeletonBlock>,
+}
+
+#[derive(Serialize)]
+pub struct SkeletonBlock {
+    pub id: usize,
+    /// Terminator kind, `goto`, `switch_int`, `call`, `return`, ...
+    pub role: &'static str,
+    /// Reachable only through a cleanup edge
+    pub cleanup: bool,
+    pub edges: Vec<SkeletonEdge>,
+}
+
+#[derive(Serialize)]
+pub struct SkeletonEdge {
+    pub target: usize,
+    /// `normal`, `branch`, `otherwise`, `return` or `cleanup`
+    pub kind: &'static str,
+}
+
+impl SmirJson {
+    /// The block structure of every function, as JSON
+    pub fn to_skeleton_json(self) -> String {
+        let functions = self
+            .items
+            .into_iter()
+            .filter_map(|item| match item.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    name,
+                    body: Some(body),
+                    ..
+                } => {
+                    let cleanup = cleanup_blocks(&body);
+                    let blocks = body
+                        .blocks
+                        .iter()
+                        .enumerate()
+                        .map(|(idx, block)| SkeletonBlock {
+                            id: idx,
+                            role: block_role(&block.terminator),
+                            cleanup: cleanup[idx],
+                            edges: terminator_edges(&block.terminator)
+                                .iter()
+                                .map(|e| SkeletonEdge {
+                                    target: e.target,
+                                    kind: edge_role(&e.kind),
+                                })
+                                .collect(),
+                        })
+                        .collect();
+                    Some(SkeletonFunction {
+                        name,
+                        symbol: item.symbol_name,
+                        blocks,
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        let skeleton = Skeleton {
+            name: self.name,
+            functions,
+        };
+        serde_json::to_string_pretty(&skeleton).expect("serde_json failed to write skeleton")
+    }
+}
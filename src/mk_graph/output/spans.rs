@@ -0,0 +1,73 @@
+//! Report of the recorded spans whose source text cannot be shown.
+//!
+//! Counts the spans of each [`SpanCategory`], then lists the files that are
+//! missing or too short for their spans and the macros whose call sites
+//! could not be found, with the number of spans affected, so that blank
+//! source in the other outputs can be traced back to its cause.
+
+use std::collections::BTreeMap;
+
+use crate::mk_graph::output::annotate::MacroOrigins;
+use crate::mk_graph::spancheck::{check_spans, SpanCategory};
+use crate::printer::SmirJson;
+
+impl SmirJson {
+    /// The span report as a Markdown section
+    pub fn to_span_report(self, macros: &MacroOrigins) -> String {
+        let checks = check_spans(&self, macros);
+        let mut out = format!("# Span report of `{}`\n\n", self.name);
+        for category in SpanCategory::ALL {
+            let count = checks.iter().filter(|c| c.category == category).count();
+            out.push_str(&format!("- {}: {}\n", category.name(), count));
+        }
+
+        // spans per (file, problem), keeping the first span as an example
+        let mut missing: BTreeMap<(&str, &str), (usize, usize)> = BTreeMap::new();
+        // spans per macro and call-site problem
+        let mut expansions: BTreeMap<(&str, Option<&str>), usize> = BTreeMap::new();
+        for check in &checks {
+            match (check.category, &check.problem) {
+                (SpanCategory::Missing, Some(problem)) => {
+                    missing
+                        .entry((&check.file, problem))
+                        .or_insert((check.span, 0))
+                        .1 += 1
+                }
+                (SpanCategory::MacroExpansion, problem) => {
+                    let name = check.macro_name.as_deref().unwrap_or_default();
+                    *expansions.entry((name, problem.as_deref())).or_default() += 1
+                }
+                _ => {}
+            }
+        }
+
+        if !missing.is_empty() {
+            out.push_str("\n## Missing source\n\n");
+            out.push_str("| File | Spans | Example span | Problem |\n");
+            out.push_str("|---|---|---|---|\n");
+            for ((file, problem), (example, count)) in missing {
+                out.push_str(&format!(
+                    "| `{}` | {} | {} | {} |\n",
+                    file.replace('|', "\\|"),
+                    count,
+                    example,
+                    problem.replace('|', "\\|")
+                ));
+            }
+        }
+        if !expansions.is_empty() {
+            out.push_str("\n## Macro expansions\n\n");
+            out.push_str("| Macro | Spans | Call site |\n");
+            out.push_str("|---|---|---|\n");
+            for ((name, problem), count) in expansions {
+                out.push_str(&format!(
+                    "| `{}` | {} | {} |\n",
+                    name,
+                    count,
+                    problem.unwrap_or("found").replace('|', "\\|")
+                ));
+            }
+        }
+        out
+    }
+}
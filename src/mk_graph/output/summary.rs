@@ -0,0 +1,185 @@
+//! Compact per-function summary cards in JSON.
+//!
+//! A card condenses one function into its signature, a few properties and
+//! complexity metrics, the functions it calls, whether it can panic, a
+//! "hot path" through its blocks, and where its return value comes from. Cards are meant for dashboards and review
+//! bots that do not want to load the full `*.smir.json`.
+
+use std::collections::VecDeque;
+
+use crate::compat::serde;
+use crate::compat::serde_json;
+use crate::compat::stable_mir;
+use serde::Serialize;
+use stable_mir::mir::{Body, TerminatorKind};
+
+use crate::mk_graph::analysis::function_properties;
+use crate::mk_graph::casts::cast_findings;
+use crate::mk_graph::cfg::{irreducible_regions, successors};
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::returns::return_sources;
+use crate::mk_graph::util::CallCategory;
+use crate::printer::{FunctionProperty, Item, SmirJson};
+use crate::MonoItemKind;
+
+/// Summary of one function with a body
+#[derive(Serialize)]
+pub struct SummaryCard {
+    pub name: String,
+    pub symbol: String,
+    /// Content hash of the body, as in the item's `fingerprint` in
+    /// `*.smir.json`
+    pub fingerprint: Option<String>,
+    /// `fn(arg types) -> return type`
+    pub signature: String,
+    /// Codegen attributes such as `inline(always)` or `cold`
+    pub attributes: Vec<&'static str>,
+    /// Flags such as `recursive`, `loops`, `irreducible`, `indirect_calls`,
+    /// in the language of `MIR_LANG`
+    pub properties: Vec<&'static str>,
+    pub metrics: Metrics,
+    /// Distinct directly called functions, in order of first call
+    pub callees: Vec<String>,
+    /// Whether some block calls into the panic machinery or asserts
+    pub may_panic: bool,
+    /// Shortest block sequence from `bb0` to a `return` that avoids
+    /// cleanup edges and panicking blocks
+    pub hot_path: Vec<usize>,
+    /// Loops that can be entered at more than one block (empty when the
+    /// control-flow graph is reducible)
+    pub irreducible_regions: Vec<Vec<usize>>,
+    /// Transmutes and pointer casts with mismatched layouts
+    /// (`bb3: cast-size: transmute of u32 (4 bytes) to u64 (8 bytes)`)
+    pub cast_warnings: Vec<String>,
+    /// What defines the return value, by block (`call to parse (bb3)`,
+    /// `const 0_i32 (bb5)`); empty for functions returning `()`
+    pub return_sources: Vec<String>,
+}
+
+/// Size and complexity of a body
+#[derive(Serialize)]
+pub struct Metrics {
+    pub blocks: usize,
+    pub statements: usize,
+    pub locals: usize,
+    /// Edges minus blocks plus two, over the non-cleanup control flow
+    pub cyclomatic: usize,
+}
+
+impl SmirJson {
+    /// Summary cards for all functions with a body, as a JSON array
+    pub fn to_summary_cards(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("json"));
+        let cards: Vec<SummaryCard> = self
+            .items
+            .iter()
+            .filter_map(|item| match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn {
+                    name,
+                    body: Some(body),
+                    ..
+                } => Some(summary_card(&ctx, name, item, body)),
+                _ => None,
+            })
+            .collect();
+        serde_json::to_string_pretty(&cards).expect("serde_json failed to write summary cards")
+    }
+}
+
+fn summary_card(ctx: &GraphContext, name: &str, item: &Item, body: &Body) -> SummaryCard {
+    let symbol = &item.symbol_name;
+    let args: Vec<String> = body
+        .arg_locals()
+        .iter()
+        .map(|decl| ctx.types.get_name(decl.ty))
+        .collect();
+    let signature = format!(
+        "fn({}) -> {}",
+        args.join(", "),
+        ctx.types.get_name(body.ret_local().ty)
+    );
+
+    let mut callees: Vec<String> = vec![];
+    // blocks that call into the panic machinery
+    let mut panicking = vec![false; body.blocks.len()];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        if let TerminatorKind::Call { func, .. } = &block.terminator.kind {
+            panicking[idx] = ctx.call_category(func) == Some(CallCategory::Panic);
+            if let Some(callee) = ctx.resolve_call_target(func) {
+                let short = ctx.short_fn_name(&callee);
+                if !callees.contains(&short) {
+                    callees.push(short);
+                }
+            }
+        }
+    }
+    let properties = function_properties(ctx, symbol, body);
+
+    let successors = successors(body);
+    let irreducible_regions = irreducible_regions(&successors);
+    let edges: usize = successors.iter().map(Vec::len).sum();
+
+    SummaryCard {
+        name: name.to_string(),
+        symbol: symbol.to_string(),
+        fingerprint: item.fingerprint.clone(),
+        signature,
+        attributes: item.attributes.clone(),
+        properties: properties
+            .iter()
+            .filter(|p| **p != FunctionProperty::MayPanic)
+            .map(|p| p.label())
+            .collect(),
+        metrics: Metrics {
+            blocks: body.blocks.len(),
+            statements: body.blocks.iter().map(|b| b.statements.len()).sum(),
+            locals: body.locals().len(),
+            cyclomatic: (edges + 2).saturating_sub(body.blocks.len()),
+        },
+        callees,
+        may_panic: properties.contains(&FunctionProperty::MayPanic),
+        hot_path: hot_path(body, &successors, &panicking),
+        irreducible_regions,
+        cast_warnings: cast_findings(body, ctx)
+            .into_iter()
+            .map(|f| format!("bb{}: {}: {}", f.block, f.rule, f.message))
+            .collect(),
+        return_sources: return_sources(ctx, body),
+    }
+}
+
+/// Breadth-first shortest path from `bb0` to a returning block, not passing
+/// through blocks that panic
+fn hot_path(body: &Body, successors: &[Vec<usize>], panicking: &[bool]) -> Vec<usize> {
+    let n = body.blocks.len();
+    if n == 0 || panicking[0] {
+        return vec![];
+    }
+    let mut parent: Vec<Option<usize>> = vec![None; n];
+    let mut seen = vec![false; n];
+    let mut queue = VecDeque::new();
+    seen[0] = true;
+    queue.push_back(0);
+    while let Some(block) = queue.pop_front() {
+        if matches!(
+            body.blocks[block].terminator.kind,
+            TerminatorKind::Return {}
+        ) {
+            let mut path = vec![block];
+            while let Some(p) = parent[*path.last().unwrap()] {
+                path.push(p);
+            }
+            path.reverse();
+            return path;
+        }
+        for &succ in &successors[block] {
+            if succ < n && !seen[succ] && !panicking[succ] {
+                seen[succ] = true;
+                parent[succ] = Some(block);
+                queue.push_back(succ);
+            }
+        }
+    }
+    vec![]
+}
@@ -0,0 +1,450 @@
+//! Self-contained SVG output for MIR graphs (no Graphviz or D2 needed).
+//!
+//! Each function body is laid out as a layered graph: blocks are ranked by
+//! breadth-first distance from `bb0`, ordered within a rank by the average
+//! position of their predecessors (one barycenter sweep), and stacked
+//! function by function. Edges pointing to the same or an earlier rank are
+//! drawn as arcs on the right so loops stay readable.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::printer::SmirJson;
+use crate::timings::time_fn;
+use crate::MonoItemKind;
+
+use crate::mk_graph::context::GraphContext;
+use crate::mk_graph::duplicates::duplicate_color;
+use crate::mk_graph::limits::LabelLimits;
+use crate::mk_graph::messages::msg;
+use crate::mk_graph::skeleton::skeleton_enabled;
+use crate::mk_graph::util::{escape_xml, is_unqualified, EdgeKind, TerminatorEdge};
+use crate::mk_graph::visit::{walk_body, BlockMarks, BodyRenderer};
+
+// =============================================================================
+// Geometry
+// =============================================================================
+
+/// Approximate glyph width of the monospace font, in pixels
+const CHAR_WIDTH: usize = 7;
+const LINE_HEIGHT: usize = 14;
+const NODE_PADDING: usize = 8;
+const RANK_GAP: usize = 40;
+const NODE_GAP: usize = 24;
+const CLUSTER_PADDING: usize = 16;
+const CLUSTER_GAP: usize = 32;
+
+// Same colors as the DOT output's roles
+const COLOR_FN_LOCAL: &str = "palegreen";
+const COLOR_FN_EXTERNAL: &str = "lightgrey";
+const COLOR_ALLOCS: &str = "lightyellow";
+const COLOR_STATIC: &str = "#e0ffe0";
+const COLOR_TAINTED: &str = "mistyrose";
+
+impl SmirJson {
+    /// Convert the MIR to a standalone SVG image
+    pub fn to_svg_file(self) -> String {
+        let ctx = GraphContext::with_limits(&self, LabelLimits::for_format("svg"));
+        let mut body_svg = String::new();
+        let mut y = CLUSTER_GAP;
+        let mut width = 0;
+
+        if !ctx.allocs.by_id.is_empty() && !skeleton_enabled() {
+            let lines = clip_lines(&ctx, ctx.allocs_legend_lines());
+            let (w, h) = text_box(&lines);
+            draw_box(
+                &mut body_svg,
+                (CLUSTER_GAP, y),
+                (w, h),
+                COLOR_ALLOCS,
+                ("black", 1),
+                &lines,
+            );
+            width = width.max(w);
+            y += h + CLUSTER_GAP;
+        }
+
+        for item in self.items {
+            match item.mono_item_kind {
+                MonoItemKind::MonoItemFn { name, body, .. } => {
+                    let mut blocks = SvgBlocks::default();
+                    if let Some(body) = &body {
+                        time_fn(&name, || {
+                            walk_body(&ctx, &item.symbol_name, body, &mut blocks)
+                        });
+                        blocks.renumber();
+                    } else {
+                        blocks.nodes.push(SvgNode {
+                            lines: vec![msg("graph.empty_body").to_string()],
+                            marks: BlockMarks::default(),
+                        });
+                    }
+                    let color = if is_unqualified(&name) {
+                        COLOR_FN_LOCAL
+                    } else {
+                        COLOR_FN_EXTERNAL
+                    };
+                    let title = ctx.limits.clip(name);
+                    let (w, h) = render_function(&mut body_svg, &title, color, &blocks, y);
+                    width = width.max(w);
+                    y += h + CLUSTER_GAP;
+                }
+                MonoItemKind::MonoItemStatic {
+                    name,
+                    allocation,
+                    body,
+                    ..
+                } => {
+                    let lines = ctx.static_lines(&name, allocation.as_ref(), body.as_ref());
+                    let lines = clip_lines(&ctx, lines);
+                    let (w, h) = text_box(&lines);
+                    draw_box(
+                        &mut body_svg,
+                        (CLUSTER_GAP, y),
+                        (w, h),
+                        COLOR_STATIC,
+                        ("black", 1),
+                        &lines,
+                    );
+                    width = width.max(w);
+                    y += h + CLUSTER_GAP;
+                }
+                MonoItemKind::MonoItemGlobalAsm { asm } => {
+                    let lines = clip_lines(&ctx, vec![asm.lines().collect::<String>()]);
+                    let (w, h) = text_box(&lines);
+                    draw_box(
+                        &mut body_svg,
+                        (CLUSTER_GAP, y),
+                        (w, h),
+                        "#ffe0ff",
+                        ("black", 1),
+                        &lines,
+                    );
+                    width = width.max(w);
+                    y += h + CLUSTER_GAP;
+                }
+            }
+        }
+
+        let total_width = width + 2 * CLUSTER_GAP;
+        let mut out = String::new();
+        out.push_str(&format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{w}\" height=\"{h}\" viewBox=\"0 0 {w} {h}\" font-family=\"monospace\" font-size=\"12\">\n",
+            w = total_width,
+            h = y
+        ));
+        out.push_str(&format!("<title>{}</title>\n", escape_xml(&self.name)));
+        out.push_str(
+            "<defs><marker id=\"arrow\" viewBox=\"0 0 10 10\" refX=\"10\" refY=\"5\" \
+             markerWidth=\"8\" markerHeight=\"8\" orient=\"auto\">\
+             <path d=\"M0,0 L10,5 L0,10 z\"/></marker></defs>\n",
+        );
+        out.push_str(&body_svg);
+        out.push_str("</svg>\n");
+        out
+    }
+}
+
+// =============================================================================
+// Block Collection
+// =============================================================================
+
+struct SvgNode {
+    lines: Vec<String>,
+    marks: BlockMarks,
+}
+
+struct SvgEdge {
+    from: usize,
+    to: usize,
+    label: String,
+    cleanup: bool,
+    /// `otherwise` edge that no enum variant can take
+    unreachable: bool,
+}
+
+/// Collects one body's blocks and edges for layout
+#[derive(Default)]
+struct SvgBlocks {
+    nodes: Vec<SvgNode>,
+    edges: Vec<SvgEdge>,
+    /// Block number of each node
+    ids: Vec<usize>,
+}
+
+impl SvgBlocks {
+    /// Make edges refer to nodes by position instead of block number; the
+    /// two differ where blocks were left out (`RESOLVE_UB_CHECKS`)
+    fn renumber(&mut self) {
+        if self.ids.iter().enumerate().all(|(pos, id)| pos == *id) {
+            return;
+        }
+        let position: HashMap<usize, usize> = self
+            .ids
+            .iter()
+            .enumerate()
+            .map(|(pos, id)| (*id, pos))
+            .collect();
+        self.edges
+            .retain_mut(|e| match (position.get(&e.from), position.get(&e.to)) {
+                (Some(from), Some(to)) => {
+                    e.from = *from;
+                    e.to = *to;
+                    true
+                }
+                _ => false,
+            });
+    }
+}
+
+impl BodyRenderer for SvgBlocks {
+    fn block(
+        &mut self,
+        idx: usize,
+        statements: Vec<String>,
+        terminator: String,
+        marks: BlockMarks,
+    ) {
+        let mut lines = vec![format!("bb{}:", idx)];
+        lines.extend(statements);
+        lines.push(terminator);
+        self.nodes.push(SvgNode { lines, marks });
+        self.ids.push(idx);
+    }
+
+    fn edge(&mut self, from: usize, edge: &TerminatorEdge, label: String, _: Option<&str>) {
+        self.edges.push(SvgEdge {
+            from,
+            to: edge.target,
+            label,
+            cleanup: edge.kind == EdgeKind::Cleanup,
+            unreachable: edge.kind == EdgeKind::UnreachableOtherwise,
+        });
+    }
+}
+
+// =============================================================================
+// Layout
+// =============================================================================
+
+/// Rank of every block: breadth-first distance from `bb0`; blocks not
+/// reachable from it go one rank below the deepest reachable one
+fn ranks(blocks: &SvgBlocks) -> Vec<usize> {
+    let n = blocks.nodes.len();
+    let mut rank: Vec<Option<usize>> = vec![None; n];
+    let mut queue = VecDeque::new();
+    if n > 0 {
+        rank[0] = Some(0);
+        queue.push_back(0);
+    }
+    while let Some(b) = queue.pop_front() {
+        for e in blocks.edges.iter().filter(|e| e.from == b && e.to < n) {
+            if rank[e.to].is_none() {
+                rank[e.to] = Some(rank[b].unwrap_or(0) + 1);
+                queue.push_back(e.to);
+            }
+        }
+    }
+    let unreached = rank.iter().flatten().max().map_or(0, |r| r + 1);
+    rank.into_iter().map(|r| r.unwrap_or(unreached)).collect()
+}
+
+/// Blocks per rank, each rank ordered by the barycenter of its predecessors
+fn layers(blocks: &SvgBlocks, rank: &[usize]) -> Vec<Vec<usize>> {
+    let depth = rank.iter().max().map_or(0, |r| r + 1);
+    let mut layers: Vec<Vec<usize>> = vec![vec![]; depth];
+    for (b, r) in rank.iter().enumerate() {
+        layers[*r].push(b);
+    }
+    for r in 1..depth {
+        let (done, rest) = layers.split_at_mut(r);
+        let previous = &done[r - 1];
+        let position = |b: usize| previous.iter().position(|p| *p == b);
+        let barycenter = |b: usize| {
+            let preds: Vec<usize> = blocks
+                .edges
+                .iter()
+                .filter(|e| e.to == b)
+                .filter_map(|e| position(e.from))
+                .collect();
+            if preds.is_empty() {
+                f64::MAX
+            } else {
+                preds.iter().sum::<usize>() as f64 / preds.len() as f64
+            }
+        };
+        rest[0].sort_by(|a, b| barycenter(*a).total_cmp(&barycenter(*b)));
+    }
+    layers
+}
+
+/// Draw one function cluster with its top edge at `top`; returns its size
+fn render_function(
+    out: &mut String,
+    name: &str,
+    color: &str,
+    blocks: &SvgBlocks,
+    top: usize,
+) -> (usize, usize) {
+    let rank = ranks(blocks);
+    let layers = layers(blocks, &rank);
+    let sizes: Vec<(usize, usize)> = blocks.nodes.iter().map(|n| text_box(&n.lines)).collect();
+
+    // node positions (top-left), relative to the cluster origin
+    let title = vec![name.to_string()];
+    let (title_width, title_height) = text_box(&title);
+    let mut pos = vec![(0, 0); blocks.nodes.len()];
+    let mut y = CLUSTER_PADDING + title_height;
+    let mut width = 0;
+    for layer in &layers {
+        let mut x = CLUSTER_PADDING;
+        let mut layer_height = 0;
+        for b in layer {
+            pos[*b] = (x, y);
+            x += sizes[*b].0 + NODE_GAP;
+            layer_height = layer_height.max(sizes[*b].1);
+        }
+        width = width.max(x);
+        y += layer_height + RANK_GAP;
+    }
+    // room on the right for back-edge arcs
+    let width = width.max(CLUSTER_PADDING + title_width) + CLUSTER_PADDING * 2;
+    let height = y;
+
+    let (cx, cy) = (CLUSTER_GAP, top);
+    out.push_str(&format!(
+        "<g>\n<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"grey\"/>\n",
+        cx, cy, width, height, color
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\" font-weight=\"bold\">{}</text>\n",
+        cx + CLUSTER_PADDING,
+        cy + CLUSTER_PADDING + LINE_HEIGHT / 2,
+        escape_xml(name)
+    ));
+
+    for e in &blocks.edges {
+        if e.from >= pos.len() || e.to >= pos.len() {
+            continue;
+        }
+        let (fx, fy) = pos[e.from];
+        let (tx, ty) = pos[e.to];
+        let (fw, fh) = sizes[e.from];
+        let (tw, _) = sizes[e.to];
+        let start = (cx + fx + fw / 2, cy + fy + fh);
+        let end = (cx + tx + tw / 2, cy + ty);
+        let dash = if e.cleanup || e.unreachable {
+            " stroke-dasharray=\"4 3\""
+        } else {
+            ""
+        };
+        let stroke = if e.unreachable { "grey" } else { "black" };
+        let (path, label_at) = if rank[e.to] > rank[e.from] {
+            (
+                format!("M{},{} L{},{}", start.0, start.1, end.0, end.1),
+                ((start.0 + end.0) / 2, (start.1 + end.1) / 2),
+            )
+        } else {
+            // back edge: arc out to the right of both nodes
+            let side = cx + (fx + fw).max(tx + tw) + NODE_GAP;
+            let from_side = (cx + fx + fw, cy + fy + fh / 2);
+            let to_side = (cx + tx + tw, cy + ty + sizes[e.to].1 / 2);
+            (
+                format!(
+                    "M{},{} C{},{} {},{} {},{}",
+                    from_side.0,
+                    from_side.1,
+                    side,
+                    from_side.1,
+                    side,
+                    to_side.1,
+                    to_side.0,
+                    to_side.1
+                ),
+                (side, (from_side.1 + to_side.1) / 2),
+            )
+        };
+        out.push_str(&format!(
+            "<path d=\"{}\" fill=\"none\" stroke=\"{}\" marker-end=\"url(#arrow)\"{}/>\n",
+            path, stroke, dash
+        ));
+        if !e.label.is_empty() {
+            out.push_str(&format!(
+                "<text x=\"{}\" y=\"{}\" font-size=\"10\" fill=\"{}\">{}</text>\n",
+                label_at.0 + 4,
+                label_at.1,
+                stroke,
+                escape_xml(&e.label)
+            ));
+        }
+    }
+
+    for (b, node) in blocks.nodes.iter().enumerate() {
+        let (x, y) = pos[b];
+        let (w, h) = sizes[b];
+        let stroke = match node.marks {
+            BlockMarks { bad_cast: true, .. } => ("red", 2),
+            BlockMarks { nounwind: true, .. } => ("black", 2),
+            _ => ("black", 1),
+        };
+        let fill = match node.marks {
+            BlockMarks { tainted: true, .. } => COLOR_TAINTED,
+            BlockMarks {
+                duplicate: Some(group),
+                ..
+            } => duplicate_color(group),
+            _ => "white",
+        };
+        draw_box(out, (cx + x, cy + y), (w, h), fill, stroke, &node.lines);
+    }
+
+    out.push_str("</g>\n");
+    (width, height)
+}
+
+// =============================================================================
+// Drawing Helpers
+// =============================================================================
+
+/// Lines cut to the `svg` line limit; block labels come cut from
+/// [`walk_body`] already
+fn clip_lines(ctx: &GraphContext, lines: Vec<String>) -> Vec<String> {
+    lines.into_iter().map(|l| ctx.limits.clip(l)).collect()
+}
+
+/// Size of a box holding the given (left-aligned) text lines
+fn text_box(lines: &[String]) -> (usize, usize) {
+    let chars = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    (
+        chars * CHAR_WIDTH + 2 * NODE_PADDING,
+        lines.len() * LINE_HEIGHT + 2 * NODE_PADDING,
+    )
+}
+
+/// Draw a filled box at `(x, y)` of size `(w, h)` with left-aligned text lines
+fn draw_box(
+    out: &mut String,
+    (x, y): (usize, usize),
+    (w, h): (usize, usize),
+    fill: &str,
+    (stroke, stroke_width): (&str, usize),
+    lines: &[String],
+) {
+    out.push_str(&format!(
+        "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\" stroke=\"{}\" stroke-width=\"{}\"/>\n",
+        x, y, w, h, fill, stroke, stroke_width
+    ));
+    out.push_str(&format!(
+        "<text x=\"{}\" y=\"{}\">",
+        x + NODE_PADDING,
+        y + NODE_PADDING
+    ));
+    for line in lines {
+        out.push_str(&format!(
+            "<tspan x=\"{}\" dy=\"{}\">{}</tspan>",
+            x + NODE_PADDING,
+            LINE_HEIGHT,
+            escape_xml(line)
+        ));
+    }
+    out.push_str("</text>\n");
+}
@@ -0,0 +1,290 @@
+//! Recognition of desugaring patterns in MIR bodies.
+//!
+//! Surface constructs such as `for` loops, the `?` operator and `.await`
+//! lower to a recognisable shape of blocks. [`block_notes`] finds these shapes
+//! and returns short notes for the blocks involved (`for-loop header over xs`),
+//! which [`super::visit::walk_body`] puts at the top of the block labels so
+//! that the graphs read closer to the source.
+
+use std::collections::BTreeMap;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    Body, ConstOperand, Local, Operand, Rvalue, StatementKind, SwitchTargets, TerminatorKind,
+    VarDebugInfoContents,
+};
+use stable_mir::ty::{GenericArgKind, GenericArgs, RigidTy, Ty};
+use stable_mir::CrateDef;
+
+use super::messages::{fill, msg};
+use super::util::callee_path;
+
+/// Notes for the blocks of `body` that take part in a recognised pattern
+pub fn block_notes(body: &Body) -> BTreeMap<usize, Vec<String>> {
+    let mut notes: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for_loops(body, &mut notes);
+    try_operators(body, &mut notes);
+    await_points(body, &mut notes);
+    notes
+}
+
+// =============================================================================
+// For Loops
+// =============================================================================
+
+/// `for x in xs` lowers to a header block calling `Iterator::next`, followed
+/// by a switch on the discriminant of the returned `Option`: `None` (0) leaves
+/// the loop, `Some` (1) enters the body.
+fn for_loops(body: &Body, notes: &mut BTreeMap<usize, Vec<String>>) {
+    for call in switched_calls(body, "Iterator", "next") {
+        let over = call
+            .args
+            .first()
+            .and_then(operand_local)
+            .and_then(|r| iterated_name(body, r))
+            .map(|name| fill(msg("pattern.for_over"), &[&name]))
+            .unwrap_or_default();
+        let mut note = |block: usize, role: &'static str| {
+            notes
+                .entry(block)
+                .or_default()
+                .push(fill(msg(role), &[&over]))
+        };
+        note(call.block, "pattern.for_header");
+        for (value, target) in call.targets.branches() {
+            match value {
+                0 => note(target, "pattern.for_exit"),
+                1 => note(target, "pattern.for_body"),
+                _ => {}
+            }
+        }
+    }
+}
+
+/// Name of the collection a `for` loop iterates over, given the local holding
+/// the `&mut` passed to `next`: follow the borrow to the iterator, find the
+/// `IntoIterator::into_iter` call producing it, and name that call's argument.
+fn iterated_name(body: &Body, next_arg: Local) -> Option<String> {
+    let mut iter = assigned_from(body, next_arg)?;
+    // the iterator is usually moved through a temporary or two
+    for _ in 0..3 {
+        let into_iter_arg = body.blocks.iter().find_map(|b| match &b.terminator.kind {
+            TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                ..
+            } if destination.local == iter
+                && callee_path(func)
+                    .is_some_and(|p| is_trait_method(&p, "IntoIterator", "into_iter")) =>
+            {
+                args.first().and_then(operand_local)
+            }
+            _ => None,
+        });
+        if let Some(arg) = into_iter_arg {
+            return local_name(body, arg)
+                .or_else(|| assigned_from(body, arg).and_then(|src| local_name(body, src)));
+        }
+        iter = assigned_from(body, iter)?;
+    }
+    None
+}
+
+// =============================================================================
+// `?` Operator
+// =============================================================================
+
+/// `expr?` lowers to a call of `Try::branch` and a switch on the returned
+/// `ControlFlow`: `Continue` (0) carries on with the unwrapped value, `Break`
+/// (1) converts the residual with `FromResidual::from_residual` and returns.
+fn try_operators(body: &Body, notes: &mut BTreeMap<usize, Vec<String>>) {
+    let residual = if returns_adt(body, "Option") {
+        "None"
+    } else {
+        "Err"
+    };
+    for call in switched_calls(body, "Try", "branch") {
+        let mut note = |block: usize, text: String| notes.entry(block).or_default().push(text);
+        note(call.switch_block, msg("pattern.try_check").to_string());
+        for (value, target) in call.targets.branches() {
+            match value {
+                0 => note(target, msg("pattern.try_ok").to_string()),
+                1 => note(target, fill(msg("pattern.try_return"), &[&residual])),
+                _ => {}
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Await Points
+// =============================================================================
+
+/// `fut.await` in an async body lowers to a loop calling `Future::poll` and
+/// switching on the returned `Poll`: `Ready` (0) continues with the output,
+/// `Pending` (1) yields to the executor. Await points are numbered in block
+/// order.
+fn await_points(body: &Body, notes: &mut BTreeMap<usize, Vec<String>>) {
+    for (n, call) in switched_calls(body, "Future", "poll")
+        .into_iter()
+        .enumerate()
+    {
+        let n = n + 1;
+        let future = call
+            .self_ty
+            .map(|ty| fill(msg("pattern.await_of"), &[&ty]))
+            .unwrap_or_default();
+        let mut note = |block: usize, text: String| notes.entry(block).or_default().push(text);
+        note(call.block, fill(msg("pattern.await"), &[&future, &n]));
+        for (value, target) in call.targets.branches() {
+            match value {
+                0 => note(target, fill(msg("pattern.await_ready"), &[&n])),
+                1 => note(target, fill(msg("pattern.await_pending"), &[&n])),
+                _ => {}
+            }
+        }
+    }
+}
+
+// =============================================================================
+// Helpers
+// =============================================================================
+
+/// A call whose result is immediately switched on by discriminant
+struct SwitchedCall<'b> {
+    /// Block ending in the call
+    block: usize,
+    args: &'b [Operand],
+    /// `Self` type of the called trait method
+    self_ty: Option<Ty>,
+    /// Block holding the switch on the result
+    switch_block: usize,
+    targets: &'b SwitchTargets,
+}
+
+/// Calls to `trait_name::method` that return to a block switching on the
+/// discriminant of the call's result
+fn switched_calls<'b>(body: &'b Body, trait_name: &str, method: &str) -> Vec<SwitchedCall<'b>> {
+    body.blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, block)| {
+            let TerminatorKind::Call {
+                func,
+                args,
+                destination,
+                target: Some(switch_block),
+                ..
+            } = &block.terminator.kind
+            else {
+                return None;
+            };
+            if !callee_path(func).is_some_and(|p| is_trait_method(&p, trait_name, method)) {
+                return None;
+            }
+            let self_ty = callee_args(func).and_then(|args| {
+                args.0.first().and_then(|arg| match arg {
+                    GenericArgKind::Type(ty) => Some(*ty),
+                    _ => None,
+                })
+            });
+            let switch = body.blocks.get(*switch_block)?;
+            let TerminatorKind::SwitchInt { discr, targets } = &switch.terminator.kind else {
+                return None;
+            };
+            let d = operand_local(discr)?;
+            switch
+                .statements
+                .iter()
+                .any(|s| {
+                    matches!(&s.kind, StatementKind::Assign(place, Rvalue::Discriminant(of))
+                        if place.local == d && of.local == destination.local)
+                })
+                .then_some(SwitchedCall {
+                    block: idx,
+                    args,
+                    self_ty,
+                    switch_block: *switch_block,
+                    targets,
+                })
+        })
+        .collect()
+}
+
+/// Generic arguments of a statically known callee (`Self` first for trait methods)
+fn callee_args(func: &Operand) -> Option<GenericArgs> {
+    match func {
+        Operand::Constant(ConstOperand { const_, .. }) => {
+            let ty = const_.ty();
+            ty.kind().fn_def().map(|(_, args)| args.clone())
+        }
+        _ => None,
+    }
+}
+
+/// Whether `path` names method `method` of trait `trait_name`
+fn is_trait_method(path: &str, trait_name: &str, method: &str) -> bool {
+    path.strip_suffix(method)
+        .and_then(|p| p.strip_suffix("::"))
+        .is_some_and(|p| p == trait_name || p.ends_with(&format!("::{}", trait_name)))
+}
+
+/// Whether the body returns an ADT named `name` (`Option`, `Result`)
+fn returns_adt(body: &Body, name: &str) -> bool {
+    match body.ret_local().ty.kind().rigid() {
+        Some(RigidTy::Adt(def, _)) => {
+            let path = def.name();
+            path == name || path.ends_with(&format!("::{}", name))
+        }
+        _ => false,
+    }
+}
+
+/// The local an operand reads, if it reads a whole local
+fn operand_local(op: &Operand) -> Option<Local> {
+    match op {
+        Operand::Copy(place) | Operand::Move(place) if place.projection.is_empty() => {
+            Some(place.local)
+        }
+        _ => None,
+    }
+}
+
+/// The local whose value (or address) is assigned to `local`, if `local` is
+/// assigned a plain copy, move, or borrow of a whole local
+fn assigned_from(body: &Body, local: Local) -> Option<Local> {
+    body.blocks
+        .iter()
+        .flat_map(|b| &b.statements)
+        .find_map(|s| match &s.kind {
+            StatementKind::Assign(place, rvalue)
+                if place.local == local && place.projection.is_empty() =>
+            {
+                match rvalue {
+                    Rvalue::Use(op) => operand_local(op),
+                    Rvalue::Ref(_, _, src) | Rvalue::AddressOf(_, src)
+                        if src.projection.is_empty() =>
+                    {
+                        Some(src.local)
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        })
+}
+
+/// Source-level name of a local, from the body's debug info
+fn local_name(body: &Body, local: Local) -> Option<String> {
+    body.var_debug_info
+        .iter()
+        .find_map(|info| match &info.value {
+            VarDebugInfoContents::Place(place)
+                if place.local == local && place.projection.is_empty() =>
+            {
+                Some(info.name.clone())
+            }
+            _ => None,
+        })
+}
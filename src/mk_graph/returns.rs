@@ -0,0 +1,158 @@
+//! Provenance of a function's return value.
+//!
+//! The return value lives in `_0`. [`return_sources`] collects the
+//! assignments and calls that define it, following copies of temporaries back
+//! to their own definitions, and describes each one by what produces the
+//! value: a constant, a parameter passed through, a call result, an aggregate,
+//! or a computation.
+
+use std::collections::HashMap;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    Body, Local, Operand, Rvalue, StatementKind, TerminatorKind, VarDebugInfoContents,
+};
+use stable_mir::ty::{IndexedVal, RigidTy};
+
+use super::clusters::aggregate_name;
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+use super::util::project;
+
+/// How many copies between temporaries are followed back from `_0`
+const MAX_DEPTH: usize = 4;
+
+/// Where a definition of a local gets its value from
+enum Def<'a> {
+    Rvalue(&'a Rvalue),
+    Call(&'a Operand),
+}
+
+/// Definitions of `_0` as `call to parse (bb3)`, `const 0_i32 (bb5)`, in
+/// block order; empty for functions returning `()`
+pub fn return_sources(ctx: &GraphContext, body: &Body) -> Vec<String> {
+    let returns_unit = matches!(
+        body.ret_local().ty.kind().rigid(),
+        Some(RigidTy::Tuple(fields)) if fields.is_empty()
+    );
+    if returns_unit {
+        return vec![];
+    }
+    let mut defs: HashMap<Local, Vec<(usize, Def)>> = HashMap::new();
+    let mut sources: Vec<(usize, String)> = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        for stmt in &block.statements {
+            match &stmt.kind {
+                StatementKind::Assign(place, rvalue) if place.projection.is_empty() => defs
+                    .entry(place.local)
+                    .or_default()
+                    .push((idx, Def::Rvalue(rvalue))),
+                // field by field construction of the return value
+                StatementKind::Assign(place, _) if place.local == 0 => {
+                    sources.push((idx, msg("returns.field_writes").to_string()))
+                }
+                StatementKind::SetDiscriminant {
+                    place,
+                    variant_index,
+                } if place.local == 0 => sources.push((
+                    idx,
+                    fill(msg("returns.variant"), &[&variant_index.to_index()]),
+                )),
+                _ => {}
+            }
+        }
+        if let TerminatorKind::Call {
+            func, destination, ..
+        } = &block.terminator.kind
+        {
+            if destination.projection.is_empty() {
+                defs.entry(destination.local)
+                    .or_default()
+                    .push((idx, Def::Call(func)));
+            }
+        }
+    }
+    describe(ctx, body, &defs, 0, 0, &mut sources);
+
+    sources.sort();
+    sources.dedup();
+    sources
+        .into_iter()
+        .map(|(block, source)| format!("{} (bb{})", source, block))
+        .collect()
+}
+
+/// `return value comes from: call to parse (bb3) or const 0_i32 (bb5)`
+pub fn return_summary(sources: &[String]) -> Option<String> {
+    let (last, rest) = sources.split_last()?;
+    let list = if rest.is_empty() {
+        last.clone()
+    } else {
+        fill(msg("returns.or"), &[&rest.join(", "), last])
+    };
+    Some(fill(msg("returns.comes_from"), &[&list]))
+}
+
+fn describe(
+    ctx: &GraphContext,
+    body: &Body,
+    defs: &HashMap<Local, Vec<(usize, Def)>>,
+    local: Local,
+    depth: usize,
+    sources: &mut Vec<(usize, String)>,
+) {
+    let name = |local: Local| -> String {
+        body.var_debug_info
+            .iter()
+            .find(|info| {
+                matches!(&info.value, VarDebugInfoContents::Place(p)
+                    if p.local == local && p.projection.is_empty())
+            })
+            .map_or_else(|| format!("_{}", local), |info| info.name.clone())
+    };
+    for (block, def) in defs.get(&local).into_iter().flatten() {
+        let source = match def {
+            Def::Call(func) => match ctx.resolve_call_target(func) {
+                Some(callee) => fill(msg("returns.call"), &[&ctx.short_fn_name(&callee)]),
+                None => msg("returns.indirect_call").to_string(),
+            },
+            Def::Rvalue(Rvalue::Use(Operand::Constant(c))) => ctx.render_const(&c.const_),
+            Def::Rvalue(Rvalue::Use(Operand::Copy(place) | Operand::Move(place))) => {
+                let is_param = (1..=body.arg_locals().len()).contains(&place.local);
+                if is_param {
+                    let param = project(name(place.local), &place.projection);
+                    fill(msg("returns.parameter"), &[&param])
+                } else if place.projection.is_empty()
+                    && depth < MAX_DEPTH
+                    && defs.contains_key(&place.local)
+                {
+                    describe(ctx, body, defs, place.local, depth + 1, sources);
+                    continue;
+                } else {
+                    project(name(place.local), &place.projection)
+                }
+            }
+            Def::Rvalue(Rvalue::Aggregate(kind, _)) => {
+                fill(msg("returns.aggregate"), &[&aggregate_name(ctx, kind)])
+            }
+            Def::Rvalue(
+                Rvalue::BinaryOp(..) | Rvalue::CheckedBinaryOp(..) | Rvalue::UnaryOp(..),
+            ) => msg("returns.arithmetic").to_string(),
+            Def::Rvalue(Rvalue::Ref(_, _, place) | Rvalue::AddressOf(_, place)) => fill(
+                msg("returns.reference"),
+                &[&project(name(place.local), &place.projection)],
+            ),
+            Def::Rvalue(Rvalue::Cast(_, op, _)) => {
+                let of = match op {
+                    Operand::Copy(place) | Operand::Move(place) => {
+                        project(name(place.local), &place.projection)
+                    }
+                    Operand::Constant(c) => ctx.render_const(&c.const_),
+                };
+                fill(msg("returns.cast"), &[&of])
+            }
+            Def::Rvalue(_) => msg("returns.computed").to_string(),
+        };
+        sources.push((*block, source));
+    }
+}
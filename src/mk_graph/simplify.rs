@@ -0,0 +1,195 @@
+//! Simplified view of the block labels for first-time readers.
+//!
+//! With `--simplified` (`SIMPLIFIED` set) the graph outputs leave out what
+//! the compiler adds around the code a reader wrote: `StorageLive`/
+//! `StorageDead` markers are hidden, branches on `UbChecks()` are resolved as
+//! with `RESOLVE_UB_CHECKS`, a temporary that only receives another local by
+//! move is merged into that local, and the remaining locals are shown by
+//! their variable names or, for temporaries, by names derived from how they
+//! are computed (`tmp_add1`, `tmp_call2`). The raw MIR stays the default.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    Body, Local, Operand, Rvalue, Statement, StatementKind, TerminatorKind, VarDebugInfoContents,
+};
+
+/// Whether `SIMPLIFIED` is set
+pub fn simplified_enabled() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("SIMPLIFIED").is_ok())
+}
+
+/// Names and merged moves of one body in the simplified view
+pub struct SimplifiedView {
+    /// Display name of every renamed local
+    names: HashMap<Local, String>,
+    /// Moves merged away, as (block, statement index)
+    merged: HashSet<(usize, usize)>,
+}
+
+impl SimplifiedView {
+    pub fn new(body: &Body) -> Self {
+        let mut names: HashMap<Local, String> = HashMap::new();
+        for info in &body.var_debug_info {
+            if let VarDebugInfoContents::Place(p) = &info.value {
+                if p.projection.is_empty() {
+                    names.entry(p.local).or_insert_with(|| info.name.clone());
+                }
+            }
+        }
+
+        // how often each local is assigned, by a statement or as a call destination
+        let mut assigned: HashMap<Local, usize> = HashMap::new();
+        for block in &body.blocks {
+            for stmt in &block.statements {
+                if let StatementKind::Assign(place, _) = &stmt.kind {
+                    *assigned.entry(place.local).or_default() += 1;
+                }
+            }
+            if let TerminatorKind::Call { destination, .. } = &block.terminator.kind {
+                *assigned.entry(destination.local).or_default() += 1;
+            }
+        }
+        let once = |local: Local| assigned.get(&local).copied().unwrap_or(0) <= 1;
+        let arg_count = body.arg_locals().len();
+        let is_temp = |local: Local, names: &HashMap<Local, String>| {
+            local > arg_count && !names.contains_key(&local)
+        };
+
+        // `_t = move _s` into a temporary assigned only there, from a local
+        // that is not assigned again: `_t` is `_s` under another number
+        let mut alias: HashMap<Local, Local> = HashMap::new();
+        let mut merged = HashSet::new();
+        for (b, block) in body.blocks.iter().enumerate() {
+            for (i, stmt) in block.statements.iter().enumerate() {
+                let StatementKind::Assign(dest, Rvalue::Use(Operand::Move(src))) = &stmt.kind
+                else {
+                    continue;
+                };
+                if dest.projection.is_empty()
+                    && src.projection.is_empty()
+                    && is_temp(dest.local, &names)
+                    && once(dest.local)
+                    && once(src.local)
+                {
+                    alias.insert(dest.local, src.local);
+                    merged.insert((b, i));
+                }
+            }
+        }
+
+        // derived names for the other temporaries, numbered per kind in
+        // block order
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut derive = |names: &mut HashMap<Local, String>, local: Local, kind: String| {
+            if is_temp(local, names) && !alias.contains_key(&local) {
+                let n = counts.entry(kind.clone()).or_default();
+                *n += 1;
+                names.insert(local, format!("tmp_{}{}", kind, n));
+            }
+        };
+        for block in &body.blocks {
+            for stmt in &block.statements {
+                if let StatementKind::Assign(place, rvalue) = &stmt.kind {
+                    if place.projection.is_empty() {
+                        derive(&mut names, place.local, rvalue_kind(rvalue));
+                    }
+                }
+            }
+            if let TerminatorKind::Call { destination, .. } = &block.terminator.kind {
+                if destination.projection.is_empty() {
+                    derive(&mut names, destination.local, "call".to_string());
+                }
+            }
+        }
+
+        // merged temporaries take the name of the local they came from
+        for (&dest, &src) in &alias {
+            let mut root = src;
+            let mut seen = HashSet::from([dest]);
+            while let Some(&next) = alias.get(&root) {
+                if !seen.insert(root) {
+                    break;
+                }
+                root = next;
+            }
+            let name = names
+                .get(&root)
+                .cloned()
+                .unwrap_or_else(|| format!("_{}", root));
+            names.insert(dest, name);
+        }
+
+        Self { names, merged }
+    }
+
+    /// Whether statement `stmt` (at index `i` of block `block`) is shown
+    pub fn shows(&self, block: usize, i: usize, stmt: &Statement) -> bool {
+        !matches!(
+            stmt.kind,
+            StatementKind::StorageLive(_) | StatementKind::StorageDead(_)
+        ) && !self.merged.contains(&(block, i))
+    }
+
+    /// `line` with every local `_N` replaced by its display name
+    pub fn rename(&self, line: String) -> String {
+        if self.names.is_empty() {
+            return line;
+        }
+        let mut out = String::with_capacity(line.len());
+        let mut chars = line.char_indices().peekable();
+        let mut prev: Option<char> = None;
+        while let Some((start, c)) = chars.next() {
+            let starts_local = c == '_'
+                && !prev.is_some_and(|p| p.is_alphanumeric() || p == '_')
+                && chars.peek().is_some_and(|(_, d)| d.is_ascii_digit());
+            if !starts_local {
+                out.push(c);
+                prev = Some(c);
+                continue;
+            }
+            let mut end = start + 1;
+            while let Some(&(i, d)) = chars.peek() {
+                if !d.is_ascii_digit() {
+                    break;
+                }
+                end = i + 1;
+                chars.next();
+            }
+            // `_5` but not `_5a`
+            let followed = chars
+                .peek()
+                .is_some_and(|(_, d)| d.is_alphanumeric() || *d == '_');
+            let name = line[start + 1..end]
+                .parse::<Local>()
+                .ok()
+                .and_then(|local| self.names.get(&local));
+            match name {
+                Some(name) if !followed => out.push_str(name),
+                _ => out.push_str(&line[start..end]),
+            }
+            prev = line[start..end].chars().last();
+        }
+        out
+    }
+}
+
+/// Short name of how `rvalue` computes its value: `add`, `ref`, `cast`, ...
+fn rvalue_kind(rvalue: &Rvalue) -> String {
+    match rvalue {
+        Rvalue::BinaryOp(op, ..) | Rvalue::CheckedBinaryOp(op, ..) => {
+            format!("{:?}", op).to_lowercase()
+        }
+        Rvalue::UnaryOp(op, _) => format!("{:?}", op).to_lowercase(),
+        Rvalue::Ref(..) | Rvalue::AddressOf(..) => "ref".to_string(),
+        Rvalue::Cast(..) => "cast".to_string(),
+        Rvalue::Len(_) => "len".to_string(),
+        Rvalue::Discriminant(_) => "discr".to_string(),
+        Rvalue::Aggregate(..) => "agg".to_string(),
+        Rvalue::Use(Operand::Constant(_)) => "const".to_string(),
+        _ => "tmp".to_string(),
+    }
+}
@@ -0,0 +1,65 @@
+//! Shape-only view of the control-flow graphs.
+//!
+//! With `--skeleton` (`SKELETON` set) the graph outputs draw every block as
+//! its number and role and leave out statements, operands, legends and
+//! analysis notes, and the JSON output becomes `*.smir.skeleton.json`
+//! listing just the blocks and edges of each function. Together with
+//! `--redact` this shares the structure of large proprietary functions
+//! without their contents.
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Terminator};
+
+use super::util::{terminator_edges, EdgeKind};
+use crate::printer::BlockRole;
+
+/// Whether `SKELETON` is set
+pub fn skeleton_enabled() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("SKELETON").is_ok())
+}
+
+/// Role of a block, named after its terminator kind (`goto`, `switch_int`,
+/// `call`, ...)
+pub fn block_role(term: &Terminator) -> &'static str {
+    BlockRole::of(term).name()
+}
+
+/// Kind of an edge without its operands: `normal`, `branch`, `otherwise`,
+/// `return` (from a call) or `cleanup`
+pub fn edge_role(kind: &EdgeKind) -> &'static str {
+    match kind {
+        EdgeKind::Normal => "normal",
+        EdgeKind::SwitchValue(_) => "branch",
+        EdgeKind::SwitchOtherwise | EdgeKind::UnreachableOtherwise => "otherwise",
+        EdgeKind::CallReturn(_) => "return",
+        EdgeKind::Cleanup => "cleanup",
+    }
+}
+
+/// Which blocks of `body` can only run while unwinding: those reachable
+/// from the target of a cleanup edge
+pub fn cleanup_blocks(body: &Body) -> Vec<bool> {
+    let n = body.blocks.len();
+    let mut cleanup = vec![false; n];
+    let mut pending: Vec<usize> = body
+        .blocks
+        .iter()
+        .flat_map(|b| terminator_edges(&b.terminator))
+        .filter(|e| e.kind == EdgeKind::Cleanup)
+        .map(|e| e.target)
+        .collect();
+    while let Some(idx) = pending.pop() {
+        if idx >= n || cleanup[idx] {
+            continue;
+        }
+        cleanup[idx] = true;
+        pending.extend(
+            terminator_edges(&body.blocks[idx].terminator)
+                .into_iter()
+                .map(|e| e.target),
+        );
+    }
+    cleanup
+}
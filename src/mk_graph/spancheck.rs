@@ -0,0 +1,130 @@
+//! Validation of the recorded spans against the source files.
+//!
+//! Spans are recorded as `(file, lo_line, lo_col, hi_line, hi_col)` while the
+//! compiler runs, but the outputs that show source text read the files later,
+//! from the working directory. [`check_spans`] sorts every recorded span into
+//! one of the [`SpanCategory`]s: spans into a file that can be read and has
+//! the lines, spans lowered from a macro expansion (located at the macro's
+//! call site), spans without a location, and spans whose file is missing
+//! (e.g. the standard library under a remapped `/rustc/...` path) or too
+//! short for them, which usually means the file changed since compilation.
+
+use std::collections::HashMap;
+
+use super::output::annotate::MacroOrigins;
+use crate::printer::SmirJson;
+
+/// What a recorded span points to
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SpanCategory {
+    /// A line range of a file that can be read
+    File,
+    /// Code lowered from a macro expansion, counted at its call site
+    MacroExpansion,
+    /// Compiler-generated code without a source location
+    NoLocation,
+    /// A file that cannot be read, or lines it does not have
+    Missing,
+}
+
+impl SpanCategory {
+    pub const ALL: [SpanCategory; 4] = [
+        SpanCategory::File,
+        SpanCategory::MacroExpansion,
+        SpanCategory::NoLocation,
+        SpanCategory::Missing,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SpanCategory::File => "file",
+            SpanCategory::MacroExpansion => "macro expansion",
+            SpanCategory::NoLocation => "no location",
+            SpanCategory::Missing => "missing",
+        }
+    }
+}
+
+/// The outcome of checking one span
+pub struct SpanCheck {
+    /// Index of the span in the `spans` table
+    pub span: usize,
+    pub category: SpanCategory,
+    /// File and first line the span is shown at (the call site for macro
+    /// expansions)
+    pub file: String,
+    pub line: usize,
+    /// The macro the span was expanded from
+    pub macro_name: Option<String>,
+    /// Why the span's source text cannot be shown, if it cannot
+    pub problem: Option<String>,
+}
+
+/// Line counts of the source files, read once each from the working directory
+#[derive(Default)]
+pub struct SourceLines {
+    files: HashMap<String, Result<usize, String>>,
+}
+
+impl SourceLines {
+    /// Why lines `lo..=hi` of `file` cannot be shown, if they cannot
+    pub fn problem(&mut self, file: &str, lo: usize, hi: usize) -> Option<String> {
+        let count = self.files.entry(file.to_string()).or_insert_with(|| {
+            std::fs::read_to_string(file)
+                .map(|text| text.lines().count())
+                .map_err(|e| e.to_string())
+        });
+        match count {
+            Err(e) => Some(format!("cannot read file: {}", e)),
+            Ok(_) if lo == 0 || hi < lo => Some(format!("invalid line range {}-{}", lo, hi)),
+            Ok(n) if hi > *n => Some(format!(
+                "line {} past the end of the file ({} lines)",
+                hi, n
+            )),
+            Ok(_) => None,
+        }
+    }
+}
+
+/// Check every span recorded in `smir`, in the order of the `spans` table
+pub fn check_spans(smir: &SmirJson, macros: &MacroOrigins) -> Vec<SpanCheck> {
+    let mut lines = SourceLines::default();
+    smir.spans
+        .iter()
+        .map(|(idx, (file, lo_line, _, hi_line, _))| {
+            if file == "no-location" {
+                return SpanCheck {
+                    span: *idx,
+                    category: SpanCategory::NoLocation,
+                    file: file.clone(),
+                    line: *lo_line,
+                    macro_name: None,
+                    problem: None,
+                };
+            }
+            if let Some((name, (site, site_lo, _, site_hi, _))) = macros.get(idx) {
+                return SpanCheck {
+                    span: *idx,
+                    category: SpanCategory::MacroExpansion,
+                    file: site.clone(),
+                    line: *site_lo,
+                    macro_name: Some(name.clone()),
+                    problem: lines.problem(site, *site_lo, *site_hi),
+                };
+            }
+            let problem = lines.problem(file, *lo_line, *hi_line);
+            SpanCheck {
+                span: *idx,
+                category: if problem.is_some() {
+                    SpanCategory::Missing
+                } else {
+                    SpanCategory::File
+                },
+                file: file.clone(),
+                line: *lo_line,
+                macro_name: None,
+                problem,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,106 @@
+//! Uses of locals whose storage may not be live.
+//!
+//! Locals other than the return place and the arguments get their storage
+//! with `StorageLive` and lose it with `StorageDead`; locals without either
+//! statement live for the whole body. [`storage_notes`] runs a forward
+//! dataflow over all control flow for the locals whose storage may be dead
+//! (not yet `StorageLive`, or `StorageDead` since) on some path, and notes
+//! every block that uses such a local. Valid MIR never does this, so a note
+//! points at an invariant the compiler guarantees in some other way (such as
+//! a path that cannot be taken) or at a gap in how the body was recorded.
+
+use std::collections::{BTreeMap, HashSet};
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Local, StatementKind};
+
+use super::liveness::{statement_effects, terminator_effects, Effects};
+use super::messages::{fill, msg};
+use super::util::terminator_targets;
+
+/// Notes for the blocks of `body` that use a local whose storage may be dead
+pub fn storage_notes(body: &Body) -> BTreeMap<usize, Vec<String>> {
+    let n = body.blocks.len();
+    // locals with explicit storage; the rest are always live
+    let managed: HashSet<Local> = body
+        .blocks
+        .iter()
+        .flat_map(|b| &b.statements)
+        .filter_map(|s| match &s.kind {
+            StatementKind::StorageLive(l) | StatementKind::StorageDead(l) => Some(*l),
+            _ => None,
+        })
+        .collect();
+    let mut notes: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    if managed.is_empty() || n == 0 {
+        return notes;
+    }
+
+    // locals whose storage may be dead on entry to each block
+    let mut maybe_dead: Vec<Option<HashSet<Local>>> = vec![None; n];
+    let mut visited = vec![false; n];
+    maybe_dead[0] = Some(managed.clone());
+    let mut pending = vec![0];
+    while let Some(idx) = pending.pop() {
+        visited[idx] = true;
+        let mut dead = maybe_dead[idx].clone().unwrap_or_default();
+        for stmt in &body.blocks[idx].statements {
+            step(&mut dead, &stmt.kind);
+        }
+        for succ in terminator_targets(&body.blocks[idx].terminator) {
+            if succ >= n {
+                continue;
+            }
+            let entry = maybe_dead[succ].get_or_insert_with(HashSet::new);
+            let before = entry.len();
+            entry.extend(dead.iter().copied());
+            if entry.len() != before || !visited[succ] {
+                pending.push(succ);
+            }
+        }
+    }
+
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let Some(mut dead) = maybe_dead[idx].clone() else {
+            continue; // unreachable
+        };
+        let mut flagged: Vec<Local> = vec![];
+        let mut check = |dead: &HashSet<Local>, effects: Effects| {
+            for local in effects.reads.into_iter().chain(effects.write) {
+                if dead.contains(&local) && !flagged.contains(&local) {
+                    flagged.push(local);
+                }
+            }
+        };
+        for stmt in &block.statements {
+            if !matches!(
+                stmt.kind,
+                StatementKind::StorageLive(_) | StatementKind::StorageDead(_)
+            ) {
+                check(&dead, statement_effects(stmt));
+            }
+            step(&mut dead, &stmt.kind);
+        }
+        check(&dead, terminator_effects(&block.terminator));
+        for local in flagged {
+            notes
+                .entry(idx)
+                .or_default()
+                .push(fill(msg("storage.dead_use"), &[&local]));
+        }
+    }
+    notes
+}
+
+/// Update the maybe-dead set over one statement
+fn step(dead: &mut HashSet<Local>, kind: &StatementKind) {
+    match kind {
+        StatementKind::StorageLive(l) => {
+            dead.remove(l);
+        }
+        StatementKind::StorageDead(l) => {
+            dead.insert(*l);
+        }
+        _ => {}
+    }
+}
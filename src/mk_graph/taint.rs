@@ -0,0 +1,383 @@
+//! Intra-procedural taint tracking over MIR locals.
+//!
+//! Taint flows forward through assignments (an rvalue reading a tainted local
+//! taints the assigned local) and calls (a tainted argument taints the
+//! destination), iterated to a fixed point. The analysis is flow-insensitive
+//! and only follows direct borrows, which keeps it cheap and predictable: it
+//! points a reviewer at code, it does not prove the absence of a flow.
+//!
+//! Two checks are built on it: the constant-time check flags branches and
+//! table lookups that depend on the parameters named in `SECRET_PARAMS`, and
+//! the flow check reports data reaching the calls named in `TAINT_SINKS` from
+//! the locals named in `TAINT_SOURCES`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{
+    Body, Local, Operand, Place, ProjectionElem, Rvalue, StatementKind, TerminatorKind,
+    VarDebugInfoContents,
+};
+use stable_mir::ty::Span;
+
+use super::context::GraphContext;
+use super::messages::{fill, msg};
+use super::util::callee_path;
+
+/// Parameter names given in `SECRET_PARAMS` (comma-separated)
+pub fn secret_params() -> &'static [String] {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Vec<String>> = OnceLock::new();
+    VAR.get_or_init(|| env_list("SECRET_PARAMS"))
+}
+
+/// Source and sink patterns from `TAINT_SOURCES` and `TAINT_SINKS`
+/// (comma-separated)
+pub struct TaintConfig {
+    /// Local names, or argument positions (`1` for the first argument)
+    pub sources: Vec<String>,
+    /// Substrings of the paths or symbols of sink functions
+    pub sinks: Vec<String>,
+}
+
+pub fn taint_config() -> &'static TaintConfig {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<TaintConfig> = OnceLock::new();
+    VAR.get_or_init(|| TaintConfig {
+        sources: env_list("TAINT_SOURCES"),
+        sinks: env_list("TAINT_SINKS"),
+    })
+}
+
+fn env_list(var: &str) -> Vec<String> {
+    std::env::var(var)
+        .map(|v| {
+            v.split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// A place in a body flagged by an analysis
+pub struct Finding {
+    /// Rule identifier (`ct-branch`, `ct-index`, ...)
+    pub rule: &'static str,
+    pub message: String,
+    pub block: usize,
+    pub span: Span,
+}
+
+// =============================================================================
+// Propagation
+// =============================================================================
+
+/// All locals that (transitively) receive data from one of `sources`
+pub fn tainted_locals(body: &Body, sources: &[Local]) -> HashSet<Local> {
+    taint_origins(body, sources).into_keys().collect()
+}
+
+/// For every tainted local, the tainted local it received data from and the
+/// block where that happened (`None` for the sources themselves)
+fn taint_origins(body: &Body, sources: &[Local]) -> HashMap<Local, Option<(Local, usize)>> {
+    let mut origins: HashMap<Local, Option<(Local, usize)>> =
+        sources.iter().map(|l| (*l, None)).collect();
+    loop {
+        let before = origins.len();
+        for (idx, block) in body.blocks.iter().enumerate() {
+            for stmt in &block.statements {
+                if let StatementKind::Assign(place, rvalue) = &stmt.kind {
+                    if let Some(from) = rvalue_locals(rvalue)
+                        .into_iter()
+                        .find(|l| origins.contains_key(l))
+                    {
+                        origins.entry(place.local).or_insert(Some((from, idx)));
+                    }
+                }
+            }
+            if let TerminatorKind::Call {
+                args, destination, ..
+            } = &block.terminator.kind
+            {
+                if let Some(from) = args
+                    .iter()
+                    .flat_map(operand_locals)
+                    .find(|l| origins.contains_key(l))
+                {
+                    origins
+                        .entry(destination.local)
+                        .or_insert(Some((from, idx)));
+                }
+            }
+        }
+        if origins.len() == before {
+            return origins;
+        }
+    }
+}
+
+/// Locals read by an rvalue, including index locals of its places
+fn rvalue_locals(rvalue: &Rvalue) -> Vec<Local> {
+    use Rvalue::*;
+    match rvalue {
+        Use(op) | Repeat(op, _) | Cast(_, op, _) | UnaryOp(_, op) | ShallowInitBox(op, _) => {
+            operand_locals(op)
+        }
+        BinaryOp(_, a, b) | CheckedBinaryOp(_, a, b) => {
+            let mut locals = operand_locals(a);
+            locals.extend(operand_locals(b));
+            locals
+        }
+        Aggregate(_, ops) => ops.iter().flat_map(operand_locals).collect(),
+        Ref(_, _, p) | AddressOf(_, p) | Len(p) | Discriminant(p) | CopyForDeref(p) => {
+            place_locals(p)
+        }
+        NullaryOp(..) | ThreadLocalRef(_) => vec![],
+    }
+}
+
+fn operand_locals(op: &Operand) -> Vec<Local> {
+    match op {
+        Operand::Copy(p) | Operand::Move(p) => place_locals(p),
+        Operand::Constant(_) => vec![],
+    }
+}
+
+fn place_locals(place: &Place) -> Vec<Local> {
+    let mut locals = vec![place.local];
+    locals.extend(index_locals(place));
+    locals
+}
+
+/// Locals used to index into a place (`_1[_5]`)
+fn index_locals(place: &Place) -> impl Iterator<Item = Local> + '_ {
+    place.projection.iter().filter_map(|p| match p {
+        ProjectionElem::Index(l) => Some(*l),
+        _ => None,
+    })
+}
+
+/// Argument locals whose debug-info name is in `names`
+pub fn named_args(body: &Body, names: &[String]) -> Vec<Local> {
+    let args = 1..=body.arg_locals().len();
+    body.var_debug_info
+        .iter()
+        .filter(|info| names.contains(&info.name))
+        .filter_map(|info| match &info.value {
+            VarDebugInfoContents::Place(p) if p.projection.is_empty() => Some(p.local),
+            _ => None,
+        })
+        .filter(|l| args.contains(l))
+        .collect()
+}
+
+// =============================================================================
+// Constant-Time Check
+// =============================================================================
+
+/// Branches and table lookups depending on the `SECRET_PARAMS` of `body`
+pub fn constant_time_findings(body: &Body) -> Vec<Finding> {
+    let sources = named_args(body, secret_params());
+    if sources.is_empty() {
+        return vec![];
+    }
+    let tainted = tainted_locals(body, &sources);
+    let mut findings = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        for stmt in &block.statements {
+            let StatementKind::Assign(place, rvalue) = &stmt.kind else {
+                continue;
+            };
+            let mut places = vec![place];
+            places.extend(rvalue_places(rvalue));
+            if let Some(l) = places
+                .into_iter()
+                .flat_map(index_locals)
+                .find(|l| tainted.contains(l))
+            {
+                findings.push(Finding {
+                    rule: "ct-index",
+                    message: fill(msg("taint.ct_index"), &[&l]),
+                    block: idx,
+                    span: stmt.span,
+                });
+            }
+        }
+        if let TerminatorKind::SwitchInt { discr, .. } = &block.terminator.kind {
+            if let Some(l) = operand_locals(discr)
+                .into_iter()
+                .find(|l| tainted.contains(l))
+            {
+                findings.push(Finding {
+                    rule: "ct-branch",
+                    message: fill(msg("taint.ct_branch"), &[&l]),
+                    block: idx,
+                    span: block.terminator.span,
+                });
+            }
+        }
+    }
+    findings
+}
+
+/// Places read by an rvalue
+fn rvalue_places(rvalue: &Rvalue) -> Vec<&Place> {
+    use Rvalue::*;
+    match rvalue {
+        Use(op) | Repeat(op, _) | Cast(_, op, _) | UnaryOp(_, op) | ShallowInitBox(op, _) => {
+            operand_place(op).into_iter().collect()
+        }
+        BinaryOp(_, a, b) | CheckedBinaryOp(_, a, b) => operand_place(a)
+            .into_iter()
+            .chain(operand_place(b))
+            .collect(),
+        Aggregate(_, ops) => ops.iter().filter_map(operand_place).collect(),
+        Ref(_, _, p) | AddressOf(_, p) | Len(p) | Discriminant(p) | CopyForDeref(p) => vec![p],
+        NullaryOp(..) | ThreadLocalRef(_) => vec![],
+    }
+}
+
+fn operand_place(op: &Operand) -> Option<&Place> {
+    match op {
+        Operand::Copy(p) | Operand::Move(p) => Some(p),
+        Operand::Constant(_) => None,
+    }
+}
+
+// =============================================================================
+// Source-to-Sink Flows
+// =============================================================================
+
+/// Data flowing from a configured source local into a sink call
+pub struct TaintFlow {
+    /// Name of the source local
+    pub source: String,
+    /// Path or symbol of the called sink
+    pub sink: String,
+    /// Block ending in the sink call
+    pub block: usize,
+    pub span: Span,
+    /// Blocks where the data moved between locals, from source to sink
+    pub blocks: Vec<usize>,
+}
+
+/// Flows from the `TAINT_SOURCES` of `body` into calls of `TAINT_SINKS`
+pub fn taint_flows(body: &Body, ctx: &GraphContext) -> Vec<TaintFlow> {
+    let config = taint_config();
+    if config.sources.is_empty() || config.sinks.is_empty() {
+        return vec![];
+    }
+    let sources = source_locals(body, &config.sources);
+    if sources.is_empty() {
+        return vec![];
+    }
+    let origins = taint_origins(body, &sources);
+
+    let mut flows = vec![];
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let TerminatorKind::Call { func, args, .. } = &block.terminator.kind else {
+            continue;
+        };
+        let names = [callee_path(func), ctx.resolve_call_target(func)];
+        let Some(sink) = names
+            .into_iter()
+            .flatten()
+            .find(|n| config.sinks.iter().any(|s| n.contains(s.as_str())))
+        else {
+            continue;
+        };
+        let Some(arg) = args
+            .iter()
+            .flat_map(operand_locals)
+            .find(|l| origins.contains_key(l))
+        else {
+            continue;
+        };
+
+        // walk the origins back to the source
+        let mut blocks = vec![idx];
+        let mut local = arg;
+        while let Some(Some((from, at))) = origins.get(&local) {
+            if !blocks.contains(at) {
+                blocks.push(*at);
+            }
+            local = *from;
+        }
+        blocks.reverse();
+        flows.push(TaintFlow {
+            source: local_name(body, local).unwrap_or_else(|| format!("_{}", local)),
+            sink,
+            block: idx,
+            span: block.terminator.span,
+            blocks,
+        });
+    }
+    flows
+}
+
+/// Locals matching the source patterns: by debug-info name, or by argument
+/// position
+fn source_locals(body: &Body, patterns: &[String]) -> Vec<Local> {
+    let arg_count = body.arg_locals().len();
+    let mut locals: Vec<Local> = patterns
+        .iter()
+        .filter_map(|p| p.parse::<usize>().ok())
+        .filter(|n| (1..=arg_count).contains(n))
+        .collect();
+    for info in &body.var_debug_info {
+        if let VarDebugInfoContents::Place(p) = &info.value {
+            if p.projection.is_empty() && patterns.contains(&info.name) {
+                locals.push(p.local);
+            }
+        }
+    }
+    locals
+}
+
+fn local_name(body: &Body, local: Local) -> Option<String> {
+    body.var_debug_info
+        .iter()
+        .find_map(|info| match &info.value {
+            VarDebugInfoContents::Place(p) if p.local == local && p.projection.is_empty() => {
+                Some(info.name.clone())
+            }
+            _ => None,
+        })
+}
+
+/// Flows as findings, reported at the sink call
+pub fn flow_findings(flows: &[TaintFlow]) -> Vec<Finding> {
+    flows
+        .iter()
+        .map(|f| Finding {
+            rule: "taint-flow",
+            message: fill(
+                msg("taint.flow"),
+                &[
+                    &f.source,
+                    &f.sink,
+                    &f.blocks
+                        .iter()
+                        .map(|b| format!("bb{}", b))
+                        .collect::<Vec<_>>()
+                        .join(" -> "),
+                ],
+            ),
+            block: f.block,
+            span: f.span,
+        })
+        .collect()
+}
+
+/// Findings as graph label notes, by block
+pub fn finding_notes(findings: &[Finding]) -> BTreeMap<usize, Vec<String>> {
+    let mut notes: BTreeMap<usize, Vec<String>> = BTreeMap::new();
+    for f in findings {
+        notes
+            .entry(f.block)
+            .or_default()
+            .push(format!("{}: {}", f.rule, f.message));
+    }
+    notes
+}
@@ -0,0 +1,128 @@
+//! Branches on `UbChecks`.
+//!
+//! Library preconditions (alignment in `ptr::read`, lengths in
+//! `slice::from_raw_parts`, ...) are guarded by `if ub_checks() { .. }`,
+//! which stays in MIR as `_n = UbChecks()` and a `switchInt` on `_n`; codegen
+//! replaces `UbChecks()` by whether UB checks are enabled (`-Zub-checks`, by
+//! default with debug assertions). [`ub_check_switches`] finds these switches
+//! so that the checking branch can be annotated. With `RESOLVE_UB_CHECKS` set
+//! (or `--simplified`, see [`super::simplify`]), the graph outputs instead
+//! follow the branch the crate's own build takes (`ub_checks` in
+//! `*.smir.json`) and leave out the blocks only the other branch reaches.
+
+use std::collections::HashSet;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, Local, NullOp, Operand, Rvalue, StatementKind, TerminatorKind};
+
+use super::simplify::simplified_enabled;
+use super::util::terminator_targets;
+
+/// Whether `RESOLVE_UB_CHECKS` is set, or the simplified view (which
+/// resolves them too) is selected
+pub fn resolve_ub_checks() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("RESOLVE_UB_CHECKS").is_ok() || simplified_enabled())
+}
+
+/// A `switchInt` on the value of `UbChecks()`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UbCheckSwitch {
+    /// Block ending in the switch
+    pub block: usize,
+    /// Successor when UB checks are enabled
+    pub enabled: usize,
+    /// Successor when UB checks are disabled
+    pub disabled: usize,
+}
+
+impl UbCheckSwitch {
+    /// Successor taken when `UbChecks()` is `ub_checks`
+    pub fn taken(&self, ub_checks: bool) -> usize {
+        if ub_checks {
+            self.enabled
+        } else {
+            self.disabled
+        }
+    }
+}
+
+/// The switches of `body` on a local assigned from `UbChecks()`, whose two
+/// branches go to different blocks
+pub fn ub_check_switches(body: &Body) -> Vec<UbCheckSwitch> {
+    let flags: HashSet<Local> = body
+        .blocks
+        .iter()
+        .flat_map(|b| &b.statements)
+        .filter_map(|s| match &s.kind {
+            StatementKind::Assign(place, Rvalue::NullaryOp(NullOp::UbChecks, _))
+                if place.projection.is_empty() =>
+            {
+                Some(place.local)
+            }
+            _ => None,
+        })
+        .collect();
+    if flags.is_empty() {
+        return vec![];
+    }
+    body.blocks
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, block)| {
+            let TerminatorKind::SwitchInt { discr, targets } = &block.terminator.kind else {
+                return None;
+            };
+            let (Operand::Copy(place) | Operand::Move(place)) = discr else {
+                return None;
+            };
+            if !place.projection.is_empty() || !flags.contains(&place.local) {
+                return None;
+            }
+            let branch = |value: u128| {
+                targets
+                    .branches()
+                    .find(|(v, _)| *v == value)
+                    .map(|(_, t)| t)
+                    .unwrap_or_else(|| targets.otherwise())
+            };
+            let (enabled, disabled) = (branch(1), branch(0));
+            (enabled != disabled).then_some(UbCheckSwitch {
+                block: idx,
+                enabled,
+                disabled,
+            })
+        })
+        .collect()
+}
+
+/// Which blocks of `body` remain reachable from `bb0` when every switch in
+/// `switches` takes its branch for `ub_checks`
+pub fn live_blocks(body: &Body, switches: &[UbCheckSwitch], ub_checks: bool) -> Vec<bool> {
+    let n = body.blocks.len();
+    let mut live = vec![false; n];
+    let mut pending = vec![0];
+    while let Some(idx) = pending.pop() {
+        if idx >= n || live[idx] {
+            continue;
+        }
+        live[idx] = true;
+        match switches.iter().find(|s| s.block == idx) {
+            Some(switch) => pending.push(switch.taken(ub_checks)),
+            None => pending.extend(terminator_targets(&body.blocks[idx].terminator)),
+        }
+    }
+    live
+}
+
+/// Which blocks of `body` the graph outputs draw: all of them, or with
+/// `RESOLVE_UB_CHECKS` those [`live_blocks`] under `ub_checks`
+pub fn shown_blocks(body: &Body, ub_checks: bool) -> Vec<bool> {
+    let switches = ub_check_switches(body);
+    if resolve_ub_checks() && !switches.is_empty() {
+        live_blocks(body, &switches, ub_checks)
+    } else {
+        vec![true; body.blocks.len()]
+    }
+}
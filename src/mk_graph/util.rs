@@ -1,15 +1,16 @@
 //! Utility functions and traits for graph generation.
 
-use std::hash::{DefaultHasher, Hash, Hasher};
-
 use crate::compat::stable_mir;
 use stable_mir::mir::{
-    AggregateKind, BorrowKind, ConstOperand, Mutability, NonDivergingIntrinsic, NullOp, Operand,
-    Place, ProjectionElem, Rvalue, Terminator, TerminatorKind, UnwindAction,
+    AggregateKind, BorrowKind, CastKind, ConstOperand, Mutability, NonDivergingIntrinsic, NullOp,
+    Operand, Place, ProjectionElem, Rvalue, Terminator, TerminatorKind, UnwindAction,
 };
 use stable_mir::ty::{IndexedVal, RigidTy};
+use stable_mir::CrateDef;
+
+use crate::printer::{FnSymType, Item, MonoItemKind};
 
-use crate::printer::FnSymType;
+use super::messages::msg;
 
 // =============================================================================
 // GraphLabelString Trait
@@ -73,7 +74,7 @@ impl GraphLabelString for Rvalue {
                 format!("{} ({})", kind.label(), os.join(", "))
             }
             BinaryOp(binop, op1, op2) => format!("{:?}({}, {})", binop, op1.label(), op2.label()),
-            Cast(kind, op, _ty) => format!("Cast-{:?} {}", kind, op.label()),
+            Cast(kind, op, ty) => format!("{} {} as {}", cast_kind_name(kind), op.label(), ty),
             CheckedBinaryOp(binop, op1, op2) => {
                 format!("chkd-{:?}({}, {})", binop, op1.label(), op2.label())
             }
@@ -128,7 +129,8 @@ impl GraphLabelString for NonDivergingIntrinsic {
 // Projection Helpers
 // =============================================================================
 
-fn project(local: String, ps: &[ProjectionElem]) -> String {
+/// Apply the projections of a place to the rendering of its local
+pub fn project(local: String, ps: &[ProjectionElem]) -> String {
     ps.iter().fold(local, decorate)
 }
 
@@ -162,7 +164,19 @@ fn decorate(thing: String, p: &ProjectionElem) -> String {
 
 /// Shorten a function name for display
 pub fn short_fn_name(name: &str) -> String {
-    name.rsplit("::").next().unwrap_or(name).to_string()
+    last_segments(name, 1)
+}
+
+/// Keep the last `n` `::`-separated segments of a path
+pub fn last_segments(name: &str, n: usize) -> String {
+    let segments: Vec<&str> = name.rsplitn(n + 1, "::").collect();
+    let kept = segments.len().min(n);
+    segments[..kept]
+        .iter()
+        .rev()
+        .copied()
+        .collect::<Vec<_>>()
+        .join("::")
 }
 
 /// Check if a name is unqualified (no :: separators)
@@ -179,27 +193,41 @@ pub fn function_string(f: FnSymType) -> String {
     }
 }
 
-/// Format a name with line breaks for display
-pub fn name_lines(name: &str) -> String {
-    name.split_inclusive(" ")
-        .flat_map(|s| s.as_bytes().chunks(25))
-        .map(|bs| core::str::from_utf8(bs).unwrap().to_string())
-        .collect::<Vec<String>>()
-        .join("\\n")
+/// Check whether any item is a function (with or without a body)
+pub fn has_functions(items: &[Item]) -> bool {
+    items
+        .iter()
+        .any(|i| matches!(i.mono_item_kind, MonoItemKind::MonoItemFn { .. }))
+}
+
+/// Break a name into display lines after spaces and every 25 characters
+pub fn name_lines(name: &str) -> Vec<String> {
+    name.split_inclusive(' ')
+        .flat_map(|s| {
+            let chars: Vec<char> = s.chars().collect();
+            chars
+                .chunks(25)
+                .map(|cs| cs.iter().collect::<String>())
+                .collect::<Vec<_>>()
+        })
+        .collect()
 }
 
-/// Generate a consistent short name (hash-based) for a function
+/// 48-bit FNV-1a hash of a name. Unlike `DefaultHasher`, the result does not
+/// depend on the Rust release, so node IDs stay the same across toolchains.
+pub fn stable_hash(name: &str) -> u64 {
+    crate::printer::stable_hash(name) & 0xffff_ffff_ffff
+}
+
+/// Node ID of a function (or other named item) in DOT and D2 outputs: a short
+/// hash, so any name gives a valid ID; the name itself goes in the label
 pub fn short_name(function_name: &str) -> String {
-    let mut h = DefaultHasher::new();
-    function_name.hash(&mut h);
-    format!("X{:x}", h.finish())
+    format!("X{:012x}", stable_hash(function_name))
 }
 
-/// Generate a consistent block name within a function
+/// Node ID of a block within a function
 pub fn block_name(function_name: &str, id: usize) -> String {
-    let mut h = DefaultHasher::new();
-    function_name.hash(&mut h);
-    format!("X{:x}_{}", h.finish(), id)
+    format!("X{:012x}_{}", stable_hash(function_name), id)
 }
 
 // =============================================================================
@@ -211,6 +239,60 @@ pub fn escape_d2(s: &str) -> String {
     s.replace('\\', "\\\\")
         .replace('"', "\\\"")
         .replace('$', "\\$")
+        .replace('\n', "\\n")
+}
+
+/// Escape text for a quoted DOT string (`label="..."`). Backslashes are
+/// escaped too, so the text cannot produce DOT's own `\l`/`\n` line breaks.
+pub fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Escape each line for DOT and join them with `sep` (`"\\l"` for left-aligned
+/// lines, `"\\n"` for centered ones)
+pub fn dot_lines<S: AsRef<str>>(lines: &[S], sep: &str) -> String {
+    lines
+        .iter()
+        .map(|line| escape_dot(line.as_ref()))
+        .collect::<Vec<_>>()
+        .join(sep)
+}
+
+/// Escape each line for D2 and join them into one multi-line label
+pub fn d2_lines<S: AsRef<str>>(lines: &[S]) -> String {
+    lines
+        .iter()
+        .map(|line| escape_d2(line.as_ref()))
+        .collect::<Vec<_>>()
+        .join("\\n")
+}
+
+/// Escape special characters for XML/SVG text and attribute values
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// =============================================================================
+// Config File Helpers
+// =============================================================================
+
+/// `line` of a config file without its comment: `#` at the start or after
+/// whitespace to the end, so values like `"#336699"` or `{closure#0}` keep
+/// their `#`
+pub fn strip_comment(line: &str) -> &str {
+    let mut prev = ' ';
+    for (i, c) in line.char_indices() {
+        if c == '#' && prev.is_whitespace() {
+            return &line[..i];
+        }
+        prev = c;
+    }
+    line
 }
 
 // =============================================================================
@@ -225,43 +307,230 @@ pub fn bytes_to_u64_le(bytes: &[u8]) -> u64 {
         .fold(0u64, |acc, (i, &b)| acc | ((b as u64) << (i * 8)))
 }
 
+/// Convert byte slice to u64 in the byte order of the target
+pub fn bytes_to_u64(bytes: &[u8], big_endian: bool) -> u64 {
+    if big_endian {
+        bytes.iter().fold(0u64, |acc, &b| (acc << 8) | b as u64)
+    } else {
+        bytes_to_u64_le(bytes)
+    }
+}
+
+// =============================================================================
+// Cast Kinds
+// =============================================================================
+
+/// Short name of a cast kind, as it appears in labels
+pub fn cast_kind_name(kind: &CastKind) -> &'static str {
+    use stable_mir::mir::PointerCoercion;
+    use CastKind::*;
+    match kind {
+        PointerExposeAddress => "expose-addr",
+        PointerWithExposedProvenance => "with-exposed-provenance",
+        CastKind::PointerCoercion(coercion) => match coercion {
+            PointerCoercion::ReifyFnPointer => "reify-fn-ptr",
+            PointerCoercion::UnsafeFnPointer => "unsafe-fn-ptr",
+            PointerCoercion::ClosureFnPointer(_) => "closure-fn-ptr",
+            PointerCoercion::MutToConstPointer => "mut-to-const",
+            PointerCoercion::ArrayToPointer => "array-to-ptr",
+            PointerCoercion::Unsize => "unsize",
+        },
+        DynStar => "dyn-star",
+        IntToInt => "int-to-int",
+        FloatToInt => "float-to-int",
+        FloatToFloat => "float-to-float",
+        IntToFloat => "int-to-float",
+        PtrToPtr => "ptr-to-ptr",
+        FnPtrToPtr => "fn-ptr-to-ptr",
+        Transmute => "transmute",
+    }
+}
+
+/// What a cast of this kind does to the value, for annotating labels
+pub fn cast_note(kind: &CastKind) -> &'static str {
+    use stable_mir::mir::PointerCoercion;
+    use CastKind::*;
+    msg(match kind {
+        PointerExposeAddress => "cast.expose_address",
+        PointerWithExposedProvenance => "cast.exposed_provenance",
+        CastKind::PointerCoercion(coercion) => match coercion {
+            PointerCoercion::ReifyFnPointer => "cast.reify_fn_pointer",
+            PointerCoercion::UnsafeFnPointer => "cast.unsafe_fn_pointer",
+            PointerCoercion::ClosureFnPointer(_) => "cast.closure_fn_pointer",
+            PointerCoercion::MutToConstPointer => "cast.mut_to_const",
+            PointerCoercion::ArrayToPointer => "cast.array_to_pointer",
+            PointerCoercion::Unsize => "cast.unsize",
+        },
+        DynStar => "cast.dyn_star",
+        IntToInt => "cast.int_to_int",
+        FloatToInt => "cast.float_to_int",
+        FloatToFloat => "cast.to_float",
+        IntToFloat => "cast.to_float",
+        PtrToPtr => "cast.ptr_to_ptr",
+        FnPtrToPtr => "cast.fn_ptr_to_ptr",
+        Transmute => "cast.transmute",
+    })
+}
+
+// =============================================================================
+// Call Categories
+// =============================================================================
+
+/// Coarse origin of a called function, used to style call edges
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CallCategory {
+    /// Code outside the standard library crates
+    User,
+    /// `core`, `alloc` and `std`, apart from the categories below
+    Std,
+    /// Compiler intrinsics
+    Intrinsic,
+    /// Panic machinery (`core::panicking`, `std::panicking`, `begin_panic`)
+    Panic,
+    /// Heap allocation (`alloc::alloc`, `std::alloc`, `__rust_alloc` and friends)
+    Alloc,
+}
+
+impl CallCategory {
+    /// Classify a callee by its definition path (`core::panicking::panic`)
+    pub fn from_path(path: &str) -> Self {
+        let krate = path.split("::").next().unwrap_or_default();
+        let in_module = |module: &str| {
+            path.strip_prefix(krate)
+                .and_then(|rest| rest.strip_prefix("::"))
+                .is_some_and(|rest| rest.starts_with(&format!("{}::", module)))
+        };
+        let is_std = matches!(krate, "core" | "alloc" | "std");
+        if is_std && in_module("intrinsics") {
+            CallCategory::Intrinsic
+        } else if (is_std && (in_module("panicking") || in_module("panic")))
+            || path.ends_with("begin_panic")
+        {
+            CallCategory::Panic
+        } else if (is_std && in_module("alloc")) || path.starts_with("__rust_") {
+            CallCategory::Alloc
+        } else if is_std {
+            CallCategory::Std
+        } else {
+            CallCategory::User
+        }
+    }
+
+    /// Lower-case name, also used for the DOT role `call_<name>`
+    pub fn name(self) -> &'static str {
+        match self {
+            CallCategory::User => "user",
+            CallCategory::Std => "std",
+            CallCategory::Intrinsic => "intrinsic",
+            CallCategory::Panic => "panic",
+            CallCategory::Alloc => "alloc",
+        }
+    }
+
+    /// Default edge color; user calls keep the graph's default
+    pub fn color(self) -> Option<&'static str> {
+        match self {
+            CallCategory::User => None,
+            CallCategory::Std => Some("dimgray"),
+            CallCategory::Intrinsic => Some("blue"),
+            CallCategory::Panic => Some("red"),
+            CallCategory::Alloc => Some("darkorange"),
+        }
+    }
+}
+
+/// Definition path of a statically known callee (`core::iter::Iterator::next`)
+pub fn callee_path(func: &Operand) -> Option<String> {
+    match func {
+        Operand::Constant(ConstOperand { const_, .. }) => {
+            let ty = const_.ty();
+            ty.kind().fn_def().map(|(def, _)| def.name())
+        }
+        _ => None,
+    }
+}
+
 // =============================================================================
 // Terminator Helpers
 // =============================================================================
 
-/// Get target block indices from a terminator
-pub fn terminator_targets(term: &Terminator) -> Vec<usize> {
+/// Role of a control-flow edge leaving a block
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    /// Unconditional successor (`Goto`, `Drop`, `Assert`, asm fall-through)
+    Normal,
+    /// `SwitchInt` branch taken for the given raw discriminant value
+    SwitchValue(u128),
+    /// `SwitchInt` fallback branch
+    SwitchOtherwise,
+    /// `SwitchInt` fallback branch of a `match` on an enum whose other
+    /// branches cover every variant. Never produced by [`terminator_edges`];
+    /// [`super::visit::walk_body`] sets it where it knows the enum.
+    UnreachableOtherwise,
+    /// Return from a call; carries the destination place label
+    CallReturn(String),
+    /// Unwind into a cleanup block
+    Cleanup,
+}
+
+/// A control-flow edge from a terminator to a block of the same body
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TerminatorEdge {
+    pub target: usize,
+    pub kind: EdgeKind,
+}
+
+impl TerminatorEdge {
+    fn new(target: usize, kind: EdgeKind) -> Self {
+        Self { target, kind }
+    }
+}
+
+/// Get the outgoing control-flow edges of a terminator.
+///
+/// This is the single place that knows which terminator kinds lead where;
+/// every emitter derives its edges (and [`terminator_targets`]) from it.
+pub fn terminator_edges(term: &Terminator) -> Vec<TerminatorEdge> {
     use TerminatorKind::*;
+    // only `Cleanup` continues in this body; the other actions leave it
+    // (Continue), abort (Terminate), or cannot happen (Unreachable)
+    let cleanup = |unwind: &UnwindAction| match unwind {
+        UnwindAction::Cleanup(t) => Some(TerminatorEdge::new(*t, EdgeKind::Cleanup)),
+        UnwindAction::Continue | UnwindAction::Unreachable | UnwindAction::Terminate => None,
+    };
     match &term.kind {
-        Goto { target } => vec![*target],
+        Goto { target } => vec![TerminatorEdge::new(*target, EdgeKind::Normal)],
         SwitchInt { targets, .. } => {
-            let mut result: Vec<usize> = targets.branches().map(|(_, t)| t).collect();
-            result.push(targets.otherwise());
+            let mut result: Vec<TerminatorEdge> = targets
+                .branches()
+                .map(|(v, t)| TerminatorEdge::new(t, EdgeKind::SwitchValue(v)))
+                .collect();
+            result.push(TerminatorEdge::new(
+                targets.otherwise(),
+                EdgeKind::SwitchOtherwise,
+            ));
             result
         }
         Resume {} | Abort {} | Return {} | Unreachable {} => vec![],
-        Drop { target, unwind, .. } => {
-            let mut result = vec![*target];
-            if let UnwindAction::Cleanup(t) = unwind {
-                result.push(*t);
-            }
+        Drop { target, unwind, .. } | Assert { target, unwind, .. } => {
+            let mut result = vec![TerminatorEdge::new(*target, EdgeKind::Normal)];
+            result.extend(cleanup(unwind));
             result
         }
-        Call { target, unwind, .. } => {
+        Call {
+            destination,
+            target,
+            unwind,
+            ..
+        } => {
             let mut result = vec![];
             if let Some(t) = target {
-                result.push(*t);
-            }
-            if let UnwindAction::Cleanup(t) = unwind {
-                result.push(*t);
-            }
-            result
-        }
-        Assert { target, unwind, .. } => {
-            let mut result = vec![*target];
-            if let UnwindAction::Cleanup(t) = unwind {
-                result.push(*t);
+                result.push(TerminatorEdge::new(
+                    *t,
+                    EdgeKind::CallReturn(destination.label()),
+                ));
             }
+            result.extend(cleanup(unwind));
             result
         }
         InlineAsm {
@@ -271,12 +540,296 @@ pub fn terminator_targets(term: &Terminator) -> Vec<usize> {
         } => {
             let mut result = vec![];
             if let Some(t) = destination {
-                result.push(*t);
-            }
-            if let UnwindAction::Cleanup(t) = unwind {
-                result.push(*t);
+                result.push(TerminatorEdge::new(*t, EdgeKind::Normal));
             }
+            result.extend(cleanup(unwind));
             result
         }
     }
 }
+
+/// The unwind action of a terminator that can unwind
+pub fn terminator_unwind(term: &Terminator) -> Option<&UnwindAction> {
+    use TerminatorKind::*;
+    match &term.kind {
+        Drop { unwind, .. }
+        | Call { unwind, .. }
+        | Assert { unwind, .. }
+        | InlineAsm { unwind, .. } => Some(unwind),
+        Goto { .. } | SwitchInt { .. } | Resume {} | Abort {} | Return {} | Unreachable {} => None,
+    }
+}
+
+/// Annotation for terminators that cannot unwind into the caller or a
+/// cleanup block: `Terminate` aborts on unwind, `Unreachable` promises the
+/// callee never unwinds
+pub fn unwind_note(term: &Terminator) -> Option<&'static str> {
+    match terminator_unwind(term)? {
+        UnwindAction::Terminate => Some(msg("unwind.abort")),
+        UnwindAction::Unreachable => Some(msg("unwind.impossible")),
+        UnwindAction::Continue | UnwindAction::Cleanup(_) => None,
+    }
+}
+
+/// Get target block indices from a terminator
+pub fn terminator_targets(term: &Terminator) -> Vec<usize> {
+    terminator_edges(term)
+        .into_iter()
+        .map(|edge| edge.target)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stable_mir::mir::{AssertMessage, InlineAsmOperand, SwitchTargets};
+    use stable_mir::ty::Span;
+
+    fn place(local: usize) -> Place {
+        Place {
+            local,
+            projection: vec![],
+        }
+    }
+
+    fn terminator(kind: TerminatorKind) -> Terminator {
+        Terminator {
+            kind,
+            span: Span::to_val(0),
+        }
+    }
+
+    /// Every unwind action, with cleanup block `bb9`
+    fn unwind_actions() -> [UnwindAction; 4] {
+        [
+            UnwindAction::Continue,
+            UnwindAction::Unreachable,
+            UnwindAction::Terminate,
+            UnwindAction::Cleanup(9),
+        ]
+    }
+
+    /// Terminators of every kind, with every unwind action, with and without
+    /// a return target, and switches with repeated targets
+    fn all_terminators() -> Vec<Terminator> {
+        use TerminatorKind::*;
+        let mut kinds = vec![Goto { target: 1 }, Resume, Abort, Return, Unreachable];
+        for branches in [
+            vec![],
+            vec![(0, 1)],
+            vec![(0, 1), (1, 2)],
+            vec![(0, 3), (5, 3)],
+        ] {
+            for otherwise in [3, 4] {
+                kinds.push(SwitchInt {
+                    discr: Operand::Copy(place(1)),
+                    targets: SwitchTargets::new(branches.clone(), otherwise),
+                });
+            }
+        }
+        for unwind in unwind_actions() {
+            kinds.push(Drop {
+                place: place(1),
+                target: 2,
+                unwind,
+            });
+            kinds.push(Assert {
+                cond: Operand::Copy(place(1)),
+                expected: true,
+                msg: AssertMessage::BoundsCheck {
+                    len: Operand::Copy(place(2)),
+                    index: Operand::Copy(place(3)),
+                },
+                target: 2,
+                unwind,
+            });
+            for target in [None, Some(2), Some(9)] {
+                kinds.push(Call {
+                    func: Operand::Copy(place(4)),
+                    args: vec![Operand::Move(place(5))],
+                    destination: place(0),
+                    target,
+                    unwind,
+                });
+                kinds.push(InlineAsm {
+                    template: "nop".to_string(),
+                    operands: vec![InlineAsmOperand {
+                        in_value: None,
+                        out_place: Some(place(1)),
+                        raw_rpr: String::new(),
+                    }],
+                    options: String::new(),
+                    line_spans: String::new(),
+                    destination: target,
+                    unwind,
+                });
+            }
+        }
+        kinds.into_iter().map(terminator).collect()
+    }
+
+    /// Position of a terminator kind in stable_mir's `TerminatorKind`. The
+    /// match has no wildcard, so a kind added by a toolchain bump fails to
+    /// compile here until it is handled and added to `all_terminators`.
+    fn kind_index(kind: &TerminatorKind) -> usize {
+        use TerminatorKind::*;
+        match kind {
+            Goto { .. } => 0,
+            SwitchInt { .. } => 1,
+            Resume => 2,
+            Abort => 3,
+            Return => 4,
+            Unreachable => 5,
+            Drop { .. } => 6,
+            Call { .. } => 7,
+            Assert { .. } => 8,
+            InlineAsm { .. } => 9,
+        }
+    }
+
+    #[test]
+    fn every_terminator_kind_maps_to_edges() {
+        use TerminatorKind::*;
+        let mut seen = [false; 10];
+        for (i, term) in all_terminators().iter().enumerate() {
+            seen[kind_index(&term.kind)] = true;
+            let edges = terminator_edges(term);
+            match &term.kind {
+                Resume | Abort | Return | Unreachable => {
+                    assert!(edges.is_empty(), "terminator {} leaves the body", i)
+                }
+                Goto { .. } | SwitchInt { .. } | Drop { .. } | Assert { .. } => {
+                    assert!(!edges.is_empty(), "terminator {} continues", i)
+                }
+                Call { target, .. }
+                | InlineAsm {
+                    destination: target,
+                    ..
+                } => assert_eq!(
+                    edges.iter().any(|e| e.kind != EdgeKind::Cleanup),
+                    target.is_some(),
+                    "terminator {} returns to its target",
+                    i
+                ),
+            }
+        }
+        assert!(seen.iter().all(|s| *s), "kinds not built: {:?}", seen);
+    }
+
+    #[test]
+    fn terminator_edges_match_mir_successors() {
+        // `Place` prints through the compiler, so failures name the index
+        // into `all_terminators` instead of the terminator
+        for (i, term) in all_terminators().iter().enumerate() {
+            let mut ours = terminator_targets(term);
+            ours.sort_unstable();
+            let mut theirs = term.successors();
+            theirs.sort_unstable();
+            assert_eq!(ours, theirs, "edges of terminator {}", i);
+        }
+    }
+
+    #[test]
+    fn terminator_edges_are_labelled_by_kind() {
+        for (i, term) in all_terminators().iter().enumerate() {
+            for edge in terminator_edges(term) {
+                let expected = match &term.kind {
+                    TerminatorKind::SwitchInt { .. } => continue,
+                    _ if edge.kind == EdgeKind::Cleanup => {
+                        assert_eq!(terminator_unwind(term), Some(&UnwindAction::Cleanup(9)));
+                        continue;
+                    }
+                    TerminatorKind::Call { .. } => EdgeKind::CallReturn(place(0).label()),
+                    _ => EdgeKind::Normal,
+                };
+                assert_eq!(edge.kind, expected, "edge of terminator {}", i);
+            }
+        }
+    }
+
+    #[test]
+    fn switch_edges_carry_their_values() {
+        let term = terminator(TerminatorKind::SwitchInt {
+            discr: Operand::Copy(place(1)),
+            targets: SwitchTargets::new(vec![(0, 1), (7, 1)], 2),
+        });
+        assert_eq!(
+            terminator_edges(&term),
+            [
+                TerminatorEdge::new(1, EdgeKind::SwitchValue(0)),
+                TerminatorEdge::new(1, EdgeKind::SwitchValue(7)),
+                TerminatorEdge::new(2, EdgeKind::SwitchOtherwise),
+            ]
+        );
+    }
+
+    #[test]
+    fn escape_dot_quotes_and_backslashes() {
+        assert_eq!(escape_dot("main::{{closure}}#0"), "main::{{closure}}#0");
+        assert_eq!(escape_dot(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(escape_dot(r"a\lb"), r"a\\lb");
+        assert_eq!(escape_dot("Vec<u8> $x"), "Vec<u8> $x");
+        assert_eq!(escape_dot("one\ntwo"), r"one\ntwo");
+        assert_eq!(escape_dot("größe"), "größe");
+    }
+
+    #[test]
+    fn escape_d2_quotes_backslashes_and_dollars() {
+        assert_eq!(escape_d2("main::{{closure}}#0"), "main::{{closure}}#0");
+        assert_eq!(escape_d2(r#"say "hi""#), r#"say \"hi\""#);
+        assert_eq!(escape_d2(r"a\b"), r"a\\b");
+        assert_eq!(escape_d2("Vec<u8>"), "Vec<u8>");
+        assert_eq!(escape_d2("${x}"), r"\${x}");
+        assert_eq!(escape_d2("one\ntwo"), r"one\ntwo");
+    }
+
+    #[test]
+    fn dot_lines_escape_each_line_and_join() {
+        let lines = ["_1 = \"a\"", r"_2 = b\c", "_3 = <T as Tr>::f"];
+        assert_eq!(
+            dot_lines(&lines, "\\l"),
+            r#"_1 = \"a\"\l_2 = b\\c\l_3 = <T as Tr>::f"#
+        );
+        assert_eq!(dot_lines(&["x\ny"], "\\n"), r"x\ny");
+        assert_eq!(dot_lines::<&str>(&[], "\\l"), "");
+    }
+
+    #[test]
+    fn d2_lines_escape_each_line_and_join() {
+        let lines = ["_1 = \"a\"", "$x", r"a\b"];
+        assert_eq!(d2_lines(&lines), r#"_1 = \"a\"\n\$x\na\\b"#);
+        assert_eq!(d2_lines::<String>(&[]), "");
+    }
+
+    #[test]
+    fn name_lines_break_after_spaces_and_every_25_characters() {
+        assert_eq!(name_lines("main::{{closure}}#0"), ["main::{{closure}}#0"]);
+        assert_eq!(
+            name_lines("<Foo as Bar>::baz"),
+            ["<Foo ", "as ", "Bar>::baz"]
+        );
+        let long = "a".repeat(30);
+        assert_eq!(name_lines(&long), ["a".repeat(25), "a".repeat(5)]);
+    }
+
+    #[test]
+    fn name_lines_count_characters_not_bytes() {
+        // 24 ASCII characters, then multibyte ones across the boundary
+        let name = format!("{}äöü€", "x".repeat(24));
+        let lines = name_lines(&name);
+        assert_eq!(lines, [format!("{}ä", "x".repeat(24)), "öü€".to_string()]);
+        assert_eq!(lines.concat(), name);
+        assert!(lines.iter().all(|l| l.chars().count() <= 25));
+    }
+
+    #[test]
+    fn strip_comment_needs_whitespace_before_hash() {
+        assert_eq!(strip_comment("# comment"), "");
+        assert_eq!(strip_comment("color = \"#336699\""), "color = \"#336699\"");
+        assert_eq!(
+            strip_comment("main::{closure#0}  # skip"),
+            "main::{closure#0}  "
+        );
+        assert_eq!(strip_comment("x = 1 #comment"), "x = 1 ");
+    }
+}
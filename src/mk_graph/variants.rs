@@ -0,0 +1,52 @@
+//! Variant routing of `match`es on enums.
+//!
+//! A `match` on an enum lowers to a `SwitchInt` on the value's discriminant,
+//! with one branch per discriminant value and an `otherwise` branch for the
+//! rest. [`switched_variants`] recovers the enum behind such a switch, so
+//! the graph edges can name the variants they are taken for, and tell when
+//! the branches already cover every variant and `otherwise` cannot be taken.
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{BasicBlock, Body, Operand, Rvalue, StatementKind, TerminatorKind};
+use stable_mir::ty::RigidTy;
+
+use super::context::GraphContext;
+use super::index::TypeKind;
+
+/// `(discriminant, name)` of every variant of the enum whose discriminant
+/// `block`'s `SwitchInt` switches on, in declaration order
+pub fn switched_variants(
+    ctx: &GraphContext,
+    body: &Body,
+    block: &BasicBlock,
+) -> Option<Vec<(u128, String)>> {
+    let TerminatorKind::SwitchInt {
+        discr: Operand::Copy(discr) | Operand::Move(discr),
+        ..
+    } = &block.terminator.kind
+    else {
+        return None;
+    };
+    let enum_place = block.statements.iter().rev().find_map(|s| match &s.kind {
+        StatementKind::Assign(place, Rvalue::Discriminant(of))
+            if place.local == discr.local && place.projection.is_empty() =>
+        {
+            Some(of)
+        }
+        _ => None,
+    })?;
+    let ty = enum_place.ty(body.locals()).ok()?;
+    let Some(RigidTy::Adt(def, _)) = ty.kind().rigid().cloned() else {
+        return None;
+    };
+    let TypeKind::Enum { variants } = &ctx.types.get(ty)?.kind else {
+        return None;
+    };
+    Some(
+        variants
+            .iter()
+            .zip(def.variants())
+            .map(|(info, def)| (info.discriminant, def.name()))
+            .collect(),
+    )
+}
@@ -0,0 +1,314 @@
+//! Format-independent walk over MIR bodies for the graph emitters.
+//!
+//! [`walk_body`] renders block labels through [`GraphContext`] and extracts
+//! edges through [`terminator_edges`]; an emitter only implements
+//! [`BodyRenderer`] to decide how nodes and edges are written. Supporting a
+//! new statement or terminator kind therefore happens in the context and in
+//! `terminator_edges`, not once per output format.
+
+use std::collections::HashSet;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::{Body, TerminatorKind};
+
+use super::casts::cast_findings;
+use super::cfg::{irreducible_regions, successors};
+use super::context::GraphContext;
+use super::copies::copy_chains;
+use super::counts::count_note;
+use super::duplicates::{duplicate_groups, duplicate_notes};
+use super::liveness::dead_stores;
+use super::messages::{fill, msg};
+use super::patterns::block_notes;
+use super::simplify::{simplified_enabled, SimplifiedView};
+use super::skeleton::{block_role, cleanup_blocks, skeleton_enabled};
+use super::storage::storage_notes;
+use super::taint::{constant_time_findings, finding_notes, flow_findings, taint_flows};
+use super::ubchecks::{resolve_ub_checks, shown_blocks, ub_check_switches};
+use super::util::{terminator_edges, unwind_note, EdgeKind, TerminatorEdge};
+use super::variants::switched_variants;
+
+/// Properties of a block that emitters highlight
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BlockMarks {
+    /// The terminator's unwind action is `Terminate` or `Unreachable`
+    pub nounwind: bool,
+    /// The block lies on a flow from a `TAINT_SOURCES` local to a
+    /// `TAINT_SINKS` call
+    pub tainted: bool,
+    /// Index of the group of duplicated blocks the block belongs to
+    pub duplicate: Option<usize>,
+    /// A transmute or pointer cast in the block has mismatched layouts
+    pub bad_cast: bool,
+}
+
+/// Output-format specific sink for the blocks and edges of one body
+pub trait BodyRenderer {
+    /// Emit basic block `idx` with its rendered statements and terminator
+    fn block(&mut self, idx: usize, statements: Vec<String>, terminator: String, marks: BlockMarks);
+
+    /// Emit a control-flow edge leaving block `from`.
+    /// `label` is the rendered edge text, empty for plain successors;
+    /// `location` is the source location (`file:line`) of the terminator
+    /// that chooses between several successors, if known.
+    fn edge(&mut self, from: usize, edge: &TerminatorEdge, label: String, location: Option<&str>);
+}
+
+/// Analyses whose notes [`walk_body`] adds to the graphs
+const GRAPH_NOTE_KINDS: &[&str] = &[
+    "taint",
+    "constant-time",
+    "dead-stores",
+    "casts",
+    "storage",
+    "copies",
+    "duplicates",
+    "irreducible",
+];
+
+/// Whether `GRAPH_NOTES` (comma-separated names from [`GRAPH_NOTE_KINDS`],
+/// or `all`) turns on the notes of analysis `kind` in graph outputs
+pub fn graph_notes(kind: &str) -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Vec<String>> = OnceLock::new();
+    VAR.get_or_init(|| {
+        let kinds: Vec<String> = std::env::var("GRAPH_NOTES")
+            .unwrap_or_default()
+            .split(',')
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+        for kind in &kinds {
+            if kind != "all" && !GRAPH_NOTE_KINDS.contains(&kind.as_str()) {
+                eprintln!(
+                    "GRAPH_NOTES: ignoring unknown analysis '{}' (known: {}, all)",
+                    kind,
+                    GRAPH_NOTE_KINDS.join(", ")
+                );
+            }
+        }
+        kinds
+    })
+    .iter()
+    .any(|k| k == kind || k == "all")
+}
+
+/// Walk all blocks of `body` (of the function with symbol name `symbol`),
+/// feeding rendered blocks and edges to `renderer`
+pub fn walk_body<R: BodyRenderer>(ctx: &GraphContext, symbol: &str, body: &Body, renderer: &mut R) {
+    if skeleton_enabled() {
+        walk_skeleton(body, renderer);
+        return;
+    }
+    let mut notes = block_notes(body);
+    let flows = if graph_notes("taint") {
+        taint_flows(body, ctx)
+    } else {
+        vec![]
+    };
+    let mut findings = if graph_notes("constant-time") {
+        constant_time_findings(body)
+    } else {
+        vec![]
+    };
+    findings.extend(flow_findings(&flows));
+    if graph_notes("dead-stores") {
+        findings.extend(dead_stores(body));
+    }
+    let casts = if graph_notes("casts") {
+        cast_findings(body, ctx)
+    } else {
+        vec![]
+    };
+    let bad_casts: HashSet<usize> = casts.iter().map(|f| f.block).collect();
+    findings.extend(casts);
+    for (idx, findings) in finding_notes(&findings) {
+        notes.entry(idx).or_default().extend(findings);
+    }
+    let regions = if graph_notes("irreducible") {
+        irreducible_regions(&successors(body))
+    } else {
+        vec![]
+    };
+    for region in regions {
+        let blocks: Vec<String> = region.iter().map(|b| format!("bb{}", b)).collect();
+        let note = fill(msg("cfg.irreducible"), &[&blocks.join(", ")]);
+        for idx in region {
+            notes.entry(idx).or_default().push(note.clone());
+        }
+    }
+    if graph_notes("storage") {
+        for (idx, storage) in storage_notes(body) {
+            notes.entry(idx).or_default().extend(storage);
+        }
+    }
+    if graph_notes("copies") {
+        for chain in copy_chains(body, ctx) {
+            notes.entry(chain.block).or_default().push(chain.note());
+        }
+    }
+    let duplicates = if graph_notes("duplicates") {
+        duplicate_groups(body, ctx)
+    } else {
+        vec![]
+    };
+    for (idx, dups) in duplicate_notes(&duplicates) {
+        notes.entry(idx).or_default().extend(dups);
+    }
+    for idx in 0..body.blocks.len() {
+        if let Some(count) = count_note(symbol, idx) {
+            notes.entry(idx).or_default().push(count);
+        }
+    }
+    let simplified = simplified_enabled().then(|| SimplifiedView::new(body));
+    let rename = |line: String| match &simplified {
+        Some(view) => view.rename(line),
+        None => line,
+    };
+    let ub_switches = ub_check_switches(body);
+    let resolve = resolve_ub_checks() && !ub_switches.is_empty();
+    for switch in &ub_switches {
+        if resolve {
+            let note = if ctx.ub_checks {
+                msg("ub_checks.enabled")
+            } else {
+                msg("ub_checks.disabled")
+            };
+            notes
+                .entry(switch.block)
+                .or_default()
+                .push(note.to_string());
+        }
+        notes
+            .entry(switch.enabled)
+            .or_default()
+            .push(msg("ub_checks.enabled_branch").to_string());
+    }
+    let live = shown_blocks(body, ctx.ub_checks);
+    for (idx, block) in body.blocks.iter().enumerate() {
+        if !live[idx] {
+            continue;
+        }
+        // pattern notes (`[for-loop header over xs]`) go above the statements
+        let statements = notes
+            .remove(&idx)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|note| rename(format!("[{}]", note)))
+            .chain(
+                block
+                    .statements
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, s)| simplified.as_ref().is_none_or(|v| v.shows(idx, *i, s)))
+                    .map(|(_, s)| rename(ctx.render_stmt(s, body.locals()))),
+            )
+            .map(|line| ctx.limits.clip(line))
+            .collect();
+        let marks = BlockMarks {
+            nounwind: unwind_note(&block.terminator).is_some(),
+            tainted: flows.iter().any(|f| f.blocks.contains(&idx)),
+            duplicate: duplicates.iter().position(|g| g.blocks.contains(&idx)),
+            bad_cast: bad_casts.contains(&idx),
+        };
+        renderer.block(
+            idx,
+            statements,
+            ctx.limits
+                .clip(rename(ctx.render_terminator(&block.terminator))),
+            marks,
+        );
+
+        let discr_ty = match &block.terminator.kind {
+            TerminatorKind::SwitchInt { discr, .. } => discr.ty(body.locals()).ok(),
+            _ => None,
+        };
+        let mut edges = terminator_edges(&block.terminator);
+        // a single successor is not a decision worth locating
+        let location = if edges.len() > 1 {
+            ctx.source_location(&block.terminator.span)
+        } else {
+            None
+        };
+        // variants of a matched enum that no branch value selects
+        let variants = switched_variants(ctx, body, block);
+        let unmatched: Vec<&str> = variants
+            .iter()
+            .flatten()
+            .filter(|(discr, _)| {
+                !edges
+                    .iter()
+                    .any(|e| e.kind == EdgeKind::SwitchValue(*discr))
+            })
+            .map(|(_, name)| name.as_str())
+            .collect();
+        if variants.is_some() && unmatched.is_empty() {
+            for edge in &mut edges {
+                if edge.kind == EdgeKind::SwitchOtherwise {
+                    edge.kind = EdgeKind::UnreachableOtherwise;
+                }
+            }
+        }
+        let variant_name = |value: u128| {
+            variants
+                .iter()
+                .flatten()
+                .find(|(discr, _)| *discr == value)
+                .map(|(_, name)| name.clone())
+        };
+        // the branch not taken under the build's UB check setting
+        let dead = ub_switches
+            .iter()
+            .find(|s| resolve && s.block == idx)
+            .map(|s| s.taken(!ctx.ub_checks));
+        for edge in edges {
+            if dead == Some(edge.target) {
+                continue;
+            }
+            let label = match &edge.kind {
+                EdgeKind::Normal => String::new(),
+                EdgeKind::SwitchValue(value) => {
+                    let rendered = ctx.render_switch_value(discr_ty, *value);
+                    match variant_name(*value) {
+                        Some(name) => format!("{} ({})", name, rendered),
+                        None => rendered,
+                    }
+                }
+                EdgeKind::SwitchOtherwise if !unmatched.is_empty() => {
+                    fill(msg("match.other"), &[&unmatched.join(", ")])
+                }
+                EdgeKind::SwitchOtherwise => msg("edge.other").to_string(),
+                EdgeKind::UnreachableOtherwise => msg("match.other_unreachable").to_string(),
+                EdgeKind::CallReturn(destination) => rename(destination.clone()),
+                EdgeKind::Cleanup => msg("edge.cleanup").to_string(),
+            };
+            renderer.edge(idx, &edge, label, location.as_deref());
+        }
+    }
+}
+
+/// Feed `renderer` the blocks of `body` as `bb3 call` (with `(cleanup)` on
+/// blocks that only run while unwinding) and edges labeled by their kind
+/// alone, without statements, operands or notes
+fn walk_skeleton<R: BodyRenderer>(body: &Body, renderer: &mut R) {
+    let cleanup = cleanup_blocks(body);
+    for (idx, block) in body.blocks.iter().enumerate() {
+        let mut label = format!("bb{} {}", idx, block_role(&block.terminator));
+        if cleanup[idx] {
+            label = format!("{} (cleanup)", label);
+        }
+        let marks = BlockMarks {
+            nounwind: unwind_note(&block.terminator).is_some(),
+            ..BlockMarks::default()
+        };
+        renderer.block(idx, vec![], label, marks);
+        for edge in terminator_edges(&block.terminator) {
+            let label = match edge.kind {
+                EdgeKind::SwitchOtherwise => msg("edge.other").to_string(),
+                EdgeKind::Cleanup => msg("edge.cleanup").to_string(),
+                _ => String::new(),
+            };
+            renderer.edge(idx, &edge, label, None);
+        }
+    }
+}
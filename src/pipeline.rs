@@ -0,0 +1,320 @@
+//! Output pipeline: post-processing and writing of rendered documents.
+//!
+//! Emitters render their output into a [`Document`] and hand it to a
+//! [`Pipeline`], which runs the document through its [`PostProcessor`]s in
+//! order and writes the result to the destination chosen by the compiler
+//! session (a file next to the crate's MIR output, or standard output), or to
+//! the destination given for the document's format in `OUTPUT_TO`.
+//!
+//! Downstream crates can add their own steps (filters, redactors, footers)
+//! by implementing [`PostProcessor`] and driving an emitter with a custom
+//! pipeline:
+//!
+//! ```ignore
+//! stable_mir_driver(&args, |tcx| {
+//!     let dot = collect_smir(tcx).to_dot_file();
+//!     Pipeline::from_env()
+//!         .with(MyRedactor)
+//!         .write(tcx, Document::new("smir.dot", dot))
+//!         .expect("failed to write smir.dot");
+//! });
+//! ```
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::compat::middle::ty::TyCtxt;
+use crate::compat::output::{is_stream, mir_output_path, OutputDest};
+
+/// A rendered output document
+pub struct Document {
+    /// Extension of the output file (`smir.dot`, `main.annotated.rs`), which
+    /// replaces the extension of the crate's MIR output path and names the
+    /// format of `text`
+    pub extension: String,
+    pub text: String,
+}
+
+impl Document {
+    pub fn new(extension: impl Into<String>, text: impl Into<String>) -> Self {
+        Self {
+            extension: extension.into(),
+            text: text.into(),
+        }
+    }
+
+    /// Format of the document: the last component of its extension (`dot`,
+    /// `json`, `rs`)
+    pub fn format(&self) -> &str {
+        self.extension.rsplit('.').next().unwrap_or_default()
+    }
+
+    /// Line comment delimiters of the document's format, if it has comments
+    pub fn comment_syntax(&self) -> Option<(&'static str, &'static str)> {
+        match self.format() {
+            "dot" | "rs" => Some(("// ", "")),
+            "d2" => Some(("# ", "")),
+            "svg" | "md" => Some(("<!-- ", " -->")),
+            _ => None,
+        }
+    }
+}
+
+/// A transformation of a document before it is written
+pub trait PostProcessor {
+    fn process(&self, doc: Document) -> Document;
+}
+
+/// Appends a comment line to documents whose format has comments
+pub struct Footer(pub String);
+
+impl PostProcessor for Footer {
+    fn process(&self, mut doc: Document) -> Document {
+        match doc.comment_syntax() {
+            Some((open, close)) => {
+                if !doc.text.ends_with('\n') {
+                    doc.text.push('\n');
+                }
+                doc.text.push_str(&format!("{}{}{}\n", open, self.0, close));
+            }
+            None => eprintln!(
+                "OUTPUT_FOOTER: {} has no comment syntax, footer not added",
+                doc.extension
+            ),
+        }
+        doc
+    }
+}
+
+/// Strips indentation, trailing whitespace and blank lines from documents
+/// whose format ignores them (DOT, D2, SVG). Markdown, CSV and annotated
+/// sources would change meaning and are left as they are, as is JSON, which
+/// is written without whitespace already.
+pub struct Minify;
+
+impl PostProcessor for Minify {
+    fn process(&self, mut doc: Document) -> Document {
+        if !matches!(doc.format(), "dot" | "d2" | "svg") {
+            if doc.format() != "json" {
+                eprintln!(
+                    "OUTPUT_MINIFY: whitespace is significant in {}, not minified",
+                    doc.extension
+                );
+            }
+            return doc;
+        }
+        doc.text = doc
+            .text
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>()
+            .join("\n");
+        doc.text.push('\n');
+        doc
+    }
+}
+
+/// Post-processors applied, in order, to every document before it is written
+#[derive(Default)]
+pub struct Pipeline {
+    processors: Vec<Box<dyn PostProcessor>>,
+}
+
+impl Pipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The pipeline configured by the environment: [`Minify`] if
+    /// `OUTPUT_MINIFY` is set, then a [`Footer`] with the text of
+    /// `OUTPUT_FOOTER`, if set
+    pub fn from_env() -> Self {
+        let mut pipeline = Self::new();
+        if std::env::var("OUTPUT_MINIFY").is_ok() {
+            pipeline = pipeline.with(Minify);
+        }
+        if let Ok(footer) = std::env::var("OUTPUT_FOOTER") {
+            pipeline = pipeline.with(Footer(footer));
+        }
+        pipeline
+    }
+
+    /// Add a post-processor after the existing ones
+    pub fn with(mut self, processor: impl PostProcessor + 'static) -> Self {
+        self.processors.push(Box::new(processor));
+        self
+    }
+
+    /// Run `doc` through the post-processors
+    pub fn process(&self, doc: Document) -> Document {
+        self.processors
+            .iter()
+            .fold(doc, |doc, processor| processor.process(doc))
+    }
+
+    /// Post-process `doc` and write it to its output destination
+    pub fn write(&self, tcx: TyCtxt<'_>, doc: Document) -> io::Result<()> {
+        let doc = self.process(doc);
+        let mut out = open(destination(tcx, &doc))?;
+        out.write_all(doc.text.as_bytes())?;
+        out.flush()
+    }
+
+    /// Write the document `render` produces piece by piece as `extension`.
+    /// Without post-processors the pieces go straight to the destination,
+    /// so the document is never held in memory as a whole; post-processors
+    /// see whole documents, so with any of them it is collected and written
+    /// as by [`Pipeline::write`].
+    pub fn stream(
+        &self,
+        tcx: TyCtxt<'_>,
+        extension: &str,
+        render: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+    ) -> io::Result<()> {
+        if !self.processors.is_empty() {
+            let mut bytes = Vec::new();
+            render(&mut bytes)?;
+            let text = String::from_utf8(bytes)
+                .unwrap_or_else(|e| panic!("{} is not valid UTF-8: {}", extension, e));
+            return self.write(tcx, Document::new(extension, text));
+        }
+        let mut out = open(destination(tcx, &Document::new(extension, "")))?;
+        render(&mut out)?;
+        out.flush()
+    }
+}
+
+/// A buffered writer to `dest`; errors creating a file name its path
+fn open(dest: OutputDest) -> io::Result<io::BufWriter<Box<dyn Write>>> {
+    let out: Box<dyn Write> = match dest {
+        OutputDest::Stdout => Box::new(io::stdout().lock()),
+        OutputDest::File(path) => Box::new(File::create(&path).map_err(|e| {
+            io::Error::new(e.kind(), format!("cannot create {}: {}", path.display(), e))
+        })?),
+    };
+    Ok(io::BufWriter::new(out))
+}
+
+/// Destinations by format from `OUTPUT_TO`: comma-separated `format=path`
+/// entries (`dot=/dev/fd/3,json=-`), where `-` is standard output
+fn output_targets() -> &'static HashMap<String, String> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<HashMap<String, String>> = OnceLock::new();
+    VAR.get_or_init(|| match std::env::var("OUTPUT_TO") {
+        Ok(spec) => parse_targets(&spec),
+        Err(_) => HashMap::new(),
+    })
+}
+
+fn parse_targets(spec: &str) -> HashMap<String, String> {
+    spec.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once('=') {
+            Some((format, path)) => (format.trim().to_string(), path.trim().to_string()),
+            None => panic!("OUTPUT_TO entry {:?} is not of the form format=path", entry),
+        })
+        .collect()
+}
+
+/// Where `doc` is written: the path given for its format in `OUTPUT_TO`,
+/// used as is, or else the MIR output path with the document's extension
+pub fn destination(tcx: TyCtxt<'_>, doc: &Document) -> OutputDest {
+    match output_targets().get(doc.format()) {
+        Some(path) if path == "-" => OutputDest::Stdout,
+        Some(path) => OutputDest::File(PathBuf::from(path)),
+        None => mir_output_path(tcx, &doc.extension),
+    }
+}
+
+/// Whether all documents of `doc`'s format go to one place (standard output,
+/// a stream, or a fixed `OUTPUT_TO` path), where several documents could not
+/// be told apart
+pub fn is_shared_destination(tcx: TyCtxt<'_>, doc: &Document) -> bool {
+    output_targets().contains_key(doc.format())
+        || match mir_output_path(tcx, &doc.extension) {
+            OutputDest::Stdout => true,
+            OutputDest::File(path) => is_stream(&path),
+        }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_targets_reads_format_path_pairs() {
+        let targets = parse_targets(" dot = /dev/fd/3 ,json=-,, d2=out/graph.d2,");
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets["dot"], "/dev/fd/3");
+        assert_eq!(targets["json"], "-");
+        assert_eq!(targets["d2"], "out/graph.d2");
+        assert!(parse_targets("").is_empty());
+        // the last entry for a format wins
+        assert_eq!(parse_targets("dot=a,dot=b")["dot"], "b");
+    }
+
+    #[test]
+    #[should_panic(expected = "is not of the form format=path")]
+    fn parse_targets_rejects_entries_without_a_path() {
+        parse_targets("dot=a.dot,json");
+    }
+
+    #[test]
+    fn format_is_the_last_extension_component() {
+        assert_eq!(Document::new("smir.dot", "").format(), "dot");
+        assert_eq!(Document::new("main.annotated.rs", "").format(), "rs");
+        assert_eq!(Document::new("md", "").format(), "md");
+    }
+
+    #[test]
+    fn minify_strips_whitespace_of_graph_formats() {
+        let text = "digraph {\n  a -> b;   \n\n\t  b;\n}";
+        for extension in ["smir.dot", "smir.d2", "smir.svg"] {
+            let doc = Minify.process(Document::new(extension, text));
+            assert_eq!(doc.text, "digraph {\na -> b;\nb;\n}\n", "{}", extension);
+        }
+    }
+
+    #[test]
+    fn minify_leaves_other_formats_untouched() {
+        let text = "{ \"a\": 1 }\n\n  indented\n";
+        for extension in ["smir.json", "spans.md", "main.annotated.rs", "arith.csv"] {
+            let doc = Minify.process(Document::new(extension, text));
+            assert_eq!(doc.text, text, "{}", extension);
+        }
+    }
+
+    #[test]
+    fn footer_uses_the_comment_syntax_of_the_format() {
+        let footer = Footer("built by ci".to_string());
+        for (extension, text, expected) in [
+            ("smir.dot", "digraph {}\n", "digraph {}\n// built by ci\n"),
+            (
+                "main.annotated.rs",
+                "fn main() {}",
+                "fn main() {}\n// built by ci\n",
+            ),
+            ("smir.d2", "a -> b\n", "a -> b\n# built by ci\n"),
+            ("smir.svg", "<svg/>\n", "<svg/>\n<!-- built by ci -->\n"),
+            ("spans.md", "# Spans\n", "# Spans\n<!-- built by ci -->\n"),
+            ("smir.json", "{}", "{}"),
+        ] {
+            let doc = footer.process(Document::new(extension, text));
+            assert_eq!(doc.text, expected, "{}", extension);
+        }
+    }
+
+    #[test]
+    fn pipeline_runs_processors_in_order() {
+        let pipeline = Pipeline::new()
+            .with(Footer("first   ".to_string()))
+            .with(Minify)
+            .with(Footer("second".to_string()));
+        let doc = pipeline.process(Document::new("smir.dot", "  digraph {}\n"));
+        assert_eq!(doc.text, "digraph {}\n// first\n// second\n");
+    }
+}
@@ -12,9 +12,12 @@
 
 use crate::compat::middle::ty::TyCtxt;
 use crate::compat::mono_collect::mono_collect;
+use crate::compat::output::{target_triple, ub_checks};
 use crate::compat::stable_mir;
+use crate::mk_graph::analysis::{analyze, analyze_enabled};
+use crate::timings::time;
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use stable_mir::mir::alloc::GlobalAlloc;
 use stable_mir::mir::mono::MonoItem;
@@ -22,11 +25,12 @@ use stable_mir::mir::visit::MirVisitor;
 use stable_mir::ty::IndexedVal;
 use stable_mir::CrateDef;
 
-use super::items::{get_foreign_module_details, mk_item};
+use super::fingerprint::fingerprint;
+use super::items::{get_foreign_module_details, mk_item, MonoItemKind};
 use super::mir_visitor::{maybe_add_to_link_map, BodyAnalyzer, UnevalConstInfo};
 use super::schema::{
-    AllocInfo, AllocMap, CollectedCrate, DerivedInfo, Item, LinkMap, SmirJson, SmirJsonDebugInfo,
-    SpanMap,
+    rustc_commit, AllocInfo, AllocMap, CollectedCrate, DerivedInfo, FnSymType, Item, LinkMap,
+    SmirJson, SmirJsonDebugInfo, SourceFileInfo, SpanMap, TraitDispatch, TraitImpl, FORMAT_VERSION,
 };
 use super::ty_visitor::TyCollector;
 use super::types::mk_type_metadata;
@@ -53,9 +57,46 @@ fn warn_missing_body(mono_item: &MonoItem) {
     }
 }
 
+/// Whether a mono item is named by a `QUERY_FN` or `SKIP_FNS` entry: a
+/// function whose path is `query`, ends in `::query`, or whose symbol name is
+/// `query`
+fn matches_query(tcx: TyCtxt<'_>, item: &MonoItem, query: &str) -> bool {
+    match item {
+        MonoItem::Fn(inst) => {
+            let name = inst.name();
+            name == query
+                || name.ends_with(&format!("::{}", query))
+                || mono_item_name(tcx, item) == query
+        }
+        MonoItem::Static(_) | MonoItem::GlobalAsm(_) => false,
+    }
+}
+
+/// Whether the kind of a mono item is selected in `ITEM_KINDS`
+fn kind_selected(item: &MonoItem) -> bool {
+    let kind = match item {
+        MonoItem::Fn(_) => "fn",
+        MonoItem::Static(_) => "static",
+        MonoItem::GlobalAsm(_) => "global_asm",
+    };
+    super::item_kinds().map_or(true, |kinds| kinds.iter().any(|k| k == kind))
+}
+
 fn collect_items(tcx: TyCtxt<'_>) -> HashMap<String, (MonoItem, Item)> {
     // get initial set of mono_items
-    let items = mono_collect(tcx);
+    let mut items = mono_collect(tcx);
+    items.retain(kind_selected);
+    // filter before building items, so unselected bodies are never retrieved
+    if let Some(query) = super::query_fn() {
+        items.retain(|item| matches_query(tcx, item, query));
+        if items.is_empty() {
+            eprintln!("QUERY_FN: no function matches {}", query);
+        }
+    }
+    let skip = super::skip_fns();
+    if !skip.is_empty() {
+        items.retain(|item| !skip.iter().any(|s| matches_query(tcx, item, s)));
+    }
     items
         .iter()
         .map(|item| {
@@ -77,7 +118,10 @@ fn enqueue_unevaluated_consts(
     unevaluated_consts: &mut HashMap<stable_mir::ty::ConstDef, String>,
 ) {
     for info in discovered {
-        if known_names.contains(&info.item_name) || pending.contains_key(&info.item_name) {
+        if known_names.contains(&info.item_name)
+            || pending.contains_key(&info.item_name)
+            || !kind_selected(&info.mono_item)
+        {
             continue;
         }
         debug_log_println!("Adding unevaluated const body for: {}", info.item_name);
@@ -266,6 +310,26 @@ fn assemble_smir(tcx: TyCtxt<'_>, collected: CollectedCrate, derived: DerivedInf
             })
             .then_with(|| a.0 .0.to_index().cmp(&b.0 .0.to_index()))
     });
+    let fn_names: HashMap<usize, String> = functions
+        .iter()
+        .map(|(key, sym)| {
+            let name = match sym {
+                // the hash at the end of a symbol changes between builds, so
+                // functions are named by their path with generic arguments
+                FnSymType::NormalSym(_) => format!("{}", key.0),
+                FnSymType::NoOpSym(name) | FnSymType::IntrinsicSym(name) => name.clone(),
+            };
+            (key.0.to_index(), name)
+        })
+        .collect();
+    for item in &mut items {
+        if let MonoItemKind::MonoItemFn {
+            body: Some(body), ..
+        } = &item.mono_item_kind
+        {
+            item.fingerprint = Some(fingerprint(body, &fn_names));
+        }
+    }
     items.sort();
     types.sort_by(|a, b| {
         format!("{}", a.0)
@@ -274,30 +338,97 @@ fn assemble_smir(tcx: TyCtxt<'_>, collected: CollectedCrate, derived: DerivedInf
     });
     spans.sort_by(|a, b| a.1.cmp(&b.1));
 
+    let files: std::collections::BTreeSet<&str> = spans.iter().map(|(_, d)| d.0.as_str()).collect();
+    let source_files = files
+        .into_iter()
+        .filter_map(|file| {
+            let (krate, lines, hash) = crate::compat::spans::source_file_meta(tcx, file)?;
+            Some(SourceFileInfo {
+                file: file.to_string(),
+                krate,
+                lines,
+                hash,
+            })
+        })
+        .collect();
+
     let mut uneval_consts: Vec<_> = unevaluated_consts.into_iter().collect();
     uneval_consts.sort_by(|a, b| a.1.cmp(&b.1));
+    let traits = trait_dispatch(&items);
 
     SmirJson {
+        format_version: FORMAT_VERSION,
+        rustc_commit: rustc_commit().to_string(),
         name: local_crate.name,
         crate_id,
         allocs,
         functions,
         uneval_consts,
         items,
+        traits,
         types,
         spans,
+        source_files,
         debug,
+        analysis: None,
+        target: target_triple(tcx),
+        ub_checks: ub_checks(tcx),
         machine: stable_mir::target::MachineInfo::target(),
     }
 }
 
+/// `(method name, symbol name)` pairs by implementing type
+type TraitImpls<'a> = BTreeMap<&'a str, Vec<(String, String)>>;
+
+/// Group the trait methods among `items` by trait and implementing type
+fn trait_dispatch(items: &[Item]) -> Vec<TraitDispatch> {
+    let mut traits: BTreeMap<&str, TraitImpls> = BTreeMap::new();
+    for item in items {
+        if let Some(m) = &item.trait_method {
+            traits
+                .entry(&m.trait_name)
+                .or_default()
+                .entry(&m.self_ty)
+                .or_default()
+                .push((m.method.clone(), item.symbol_name.clone()));
+        }
+    }
+    traits
+        .into_iter()
+        .map(|(trait_name, impls)| TraitDispatch {
+            trait_name: trait_name.to_string(),
+            impls: impls
+                .into_iter()
+                .map(|(self_ty, mut methods)| {
+                    methods.sort();
+                    TraitImpl {
+                        self_ty: self_ty.to_string(),
+                        methods,
+                    }
+                })
+                .collect(),
+        })
+        .collect()
+}
+
 pub fn collect_smir(tcx: TyCtxt<'_>) -> SmirJson {
     // Phase 1+2: Collect all mono items from rustc and analyze their bodies
     // in a single pass. Each body is walked exactly once. Transitive item
     // discovery (unevaluated constants) is handled by a fixpoint loop.
-    let initial_items = collect_items(tcx);
-    let (collected, derived) = collect_and_analyze_items(tcx, initial_items);
+    let initial_items = time("collect items", || collect_items(tcx));
+    let (collected, derived) = time("analyze bodies", || {
+        collect_and_analyze_items(tcx, initial_items)
+    });
 
     // Phase 3: Assemble the final output (pure data transformation)
-    assemble_smir(tcx, collected, derived)
+    let mut smir = time("assemble", || assemble_smir(tcx, collected, derived));
+    if super::redact_enabled() {
+        super::redact::redact(&mut smir);
+    }
+
+    // Derived block roles and function properties for `--analyze`
+    if analyze_enabled() {
+        smir.analysis = Some(time("analyze", || analyze(&smir)));
+    }
+    smir
 }
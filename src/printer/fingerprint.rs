@@ -0,0 +1,253 @@
+//! Content fingerprints of function bodies.
+//!
+//! A fingerprint hashes the serialized body with everything left out that
+//! changes between builds without the function changing: spans, debug info
+//! names, and the interned ids of types, constants and allocations. Block
+//! targets, switch values, constant bytes, field, variant and index numbers
+//! are kept. The return place and arguments keep their numbers, while the
+//! other locals are renumbered in the order they are first used, so two
+//! bodies that differ only in how temporaries are numbered match, but
+//! `_1 = _2 + _3` and `_3 = _2 + _1` do not. Function types are replaced by
+//! the path of the function they resolve to, so calls to different functions
+//! still differ. Equal bodies in two builds get equal fingerprints wherever
+//! the items end up in the file.
+
+use std::collections::HashMap;
+
+use crate::compat::serde_json;
+use crate::compat::stable_mir;
+use serde_json::Value;
+use stable_mir::mir::Body;
+
+use super::util::stable_hash;
+
+/// Keys whose whole subtree keeps its numbers
+const VALUE_KEYS: &[&str] = &[
+    "bytes",
+    "branches",
+    "ConstantIndex",
+    "Subslice",
+    "Downcast",
+    "variant_index",
+    "arg_count",
+    "align",
+];
+
+/// Keys whose number is a block target
+const TARGET_KEYS: &[&str] = &["target", "otherwise", "Cleanup", "destination"];
+
+/// Keys whose number is a local
+const LOCAL_KEYS: &[&str] = &["local", "StorageLive", "StorageDead", "Index", "spread_arg"];
+
+/// Keys left out entirely: source locations, debug names, interned user type
+/// annotations and constant ids, and the local declarations, which are
+/// hashed separately in canonical order
+const SKIPPED_KEYS: &[&str] = &["span", "var_debug_info", "user_ty", "id", "locals"];
+
+/// Fingerprint of `body` as 16 hex digits; `fn_names` maps the interned
+/// index of a function type to the function's path
+pub(super) fn fingerprint(body: &Body, fn_names: &HashMap<usize, String>) -> String {
+    let value = serde_json::to_value(body).expect("serde_json failed to write body");
+    let mut shape = Shape {
+        fn_names,
+        arg_count: body.arg_locals().len() as u64,
+        locals: HashMap::new(),
+        out: String::new(),
+    };
+    shape.walk(&value, false);
+    shape.local_decls(&value["locals"]);
+    format!("{:016x}", stable_hash(&shape.out))
+}
+
+/// The text that is hashed, and the canonical numbering of the locals
+struct Shape<'a> {
+    fn_names: &'a HashMap<usize, String>,
+    arg_count: u64,
+    /// Canonical number of each local after the arguments, by first use
+    locals: HashMap<u64, u64>,
+    out: String,
+}
+
+impl Shape<'_> {
+    /// The canonical number of local `n`
+    fn canonical(&mut self, n: u64) -> u64 {
+        if n <= self.arg_count {
+            return n;
+        }
+        let next = self.arg_count + 1 + self.locals.len() as u64;
+        *self.locals.entry(n).or_insert(next)
+    }
+
+    fn local(&mut self, n: &Value) {
+        if let Some(n) = n.as_u64() {
+            let c = self.canonical(n);
+            self.out.push_str(&format!("_{}", c));
+        }
+    }
+
+    /// A function type as the path of the function, other types not at all
+    fn ty(&mut self, ty: &Value) {
+        let name = ty.as_u64().and_then(|n| self.fn_names.get(&(n as usize)));
+        if let Some(name) = name {
+            self.out.push_str(name);
+        }
+    }
+
+    /// The mutability of every local declaration, in canonical order;
+    /// locals that are never used are numbered after the used ones
+    fn local_decls(&mut self, decls: &Value) {
+        let Some(decls) = decls.as_array() else {
+            return;
+        };
+        let mut canonical: Vec<(u64, String)> = decls
+            .iter()
+            .enumerate()
+            .map(|(n, decl)| (self.canonical(n as u64), decl["mutability"].to_string()))
+            .collect();
+        canonical.sort();
+        self.out.push_str("locals[");
+        for (_, mutability) in canonical {
+            self.out.push_str(&mutability);
+            self.out.push(',');
+        }
+        self.out.push(']');
+    }
+
+    fn walk(&mut self, v: &Value, keep_numbers: bool) {
+        match v {
+            Value::Object(map) => {
+                for (k, v) in map {
+                    let k = k.as_str();
+                    if SKIPPED_KEYS.contains(&k) {
+                        continue;
+                    }
+                    self.out.push_str(k);
+                    self.out.push('{');
+                    match (k, v) {
+                        (_, Value::Number(n)) if TARGET_KEYS.contains(&k) => {
+                            self.out.push_str(&n.to_string())
+                        }
+                        (_, Value::Number(_)) if LOCAL_KEYS.contains(&k) => self.local(v),
+                        ("ty", _) => self.ty(v),
+                        // field index and field type
+                        ("Field", Value::Array(parts)) => {
+                            self.out.push_str(&parts[0].to_string());
+                            self.ty(&parts[1]);
+                        }
+                        // interned ADT, variant, generic arguments, user
+                        // type annotation and active union field
+                        ("Adt", Value::Array(parts)) => {
+                            self.out.push_str(&parts[1].to_string());
+                            self.walk(&parts[2], false);
+                            self.out.push_str(&parts[4].to_string());
+                        }
+                        // (offset, allocation) pairs of pointers in constants
+                        ("ptrs", Value::Array(ptrs)) => {
+                            for ptr in ptrs {
+                                self.out.push_str(&ptr[0].to_string());
+                                self.out.push(',');
+                            }
+                        }
+                        _ => self.walk(v, keep_numbers || VALUE_KEYS.contains(&k)),
+                    }
+                    self.out.push('}');
+                }
+            }
+            Value::Array(items) => {
+                self.out.push('[');
+                for item in items {
+                    self.walk(item, keep_numbers);
+                    self.out.push(',');
+                }
+                self.out.push(']');
+            }
+            Value::String(s) => {
+                self.out.push_str(s);
+                self.out.push(' ');
+            }
+            Value::Number(n) if keep_numbers => self.out.push_str(&n.to_string()),
+            Value::Bool(b) => self.out.push_str(if *b { "true " } else { "false " }),
+            Value::Number(_) | Value::Null => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use stable_mir::mir::{
+        BasicBlock, BinOp, LocalDecl, Mutability, Operand, Place, Rvalue, Statement, StatementKind,
+        Terminator, TerminatorKind,
+    };
+    use stable_mir::ty::{IndexedVal, Span, Ty};
+
+    fn place(local: usize) -> Place {
+        Place {
+            local,
+            projection: vec![],
+        }
+    }
+
+    /// `_dest = lhs + rhs; return` in a body with `arg_count` arguments and
+    /// six locals, every span set to `span`
+    fn body(arg_count: usize, dest: usize, lhs: usize, rhs: usize, span: usize) -> Body {
+        let span = Span::to_val(span);
+        let assign = Statement {
+            kind: StatementKind::Assign(
+                place(dest),
+                Rvalue::BinaryOp(
+                    BinOp::Add,
+                    Operand::Copy(place(lhs)),
+                    Operand::Copy(place(rhs)),
+                ),
+            ),
+            span,
+        };
+        let block = BasicBlock {
+            statements: vec![assign],
+            terminator: Terminator {
+                kind: TerminatorKind::Return,
+                span,
+            },
+        };
+        let locals = (0..6)
+            .map(|_| LocalDecl {
+                ty: Ty::to_val(0),
+                span,
+                mutability: Mutability::Mut,
+            })
+            .collect();
+        Body::new(vec![block], locals, arg_count, vec![], None, span)
+    }
+
+    fn print(body: &Body) -> String {
+        fingerprint(body, &HashMap::new())
+    }
+
+    #[test]
+    fn temporaries_are_renumbered() {
+        assert_eq!(print(&body(1, 3, 1, 1, 0)), print(&body(1, 5, 1, 1, 0)));
+    }
+
+    #[test]
+    fn arguments_keep_their_numbers() {
+        assert_ne!(print(&body(2, 0, 1, 2, 0)), print(&body(2, 0, 2, 1, 0)));
+    }
+
+    #[test]
+    fn uses_of_one_local_differ_from_two() {
+        assert_ne!(print(&body(1, 0, 3, 3, 0)), print(&body(1, 0, 3, 4, 0)));
+    }
+
+    #[test]
+    fn spans_are_ignored() {
+        assert_eq!(print(&body(2, 3, 1, 2, 0)), print(&body(2, 3, 1, 2, 7)));
+    }
+
+    #[test]
+    fn fingerprints_are_16_hex_digits() {
+        let fp = print(&body(1, 0, 1, 1, 0));
+        assert_eq!(fp.len(), 16);
+        assert!(fp.chars().all(|c| c.is_ascii_hexdigit()));
+    }
+}
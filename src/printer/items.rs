@@ -22,7 +22,9 @@ use stable_mir::{CrateDef, CrateItem};
 
 use crate::compat::bridge::mono_instance;
 
-use super::schema::{BodyDetails, ForeignItem, ForeignModule, GenericData, Item, ItemDetails};
+use super::schema::{
+    BodyDetails, ForeignItem, ForeignModule, GenericData, Item, ItemDetails, TraitMethod,
+};
 
 #[derive(Serialize, Clone)]
 pub enum MonoItemKind {
@@ -81,19 +83,26 @@ pub(super) fn mk_item(tcx: TyCtxt<'_>, item: MonoItem, sym_name: String) -> (Mon
             let internal_id = crate::compat::types::internal_def_id(tcx, id);
             let body = inst.body();
             let details = get_item_details(tcx, internal_id, Some(inst), body.as_ref());
+            let trait_method = crate::compat::bridge::trait_method(tcx, &inst).map(
+                |(trait_name, self_ty, method)| TraitMethod {
+                    trait_name,
+                    self_ty,
+                    method,
+                },
+            );
             let mono_item = MonoItem::Fn(inst);
-            (
-                mono_item,
-                Item::new(
-                    sym_name.clone(),
-                    MonoItemKind::MonoItemFn {
-                        name: name.clone(),
-                        id,
-                        body,
-                    },
-                    details,
-                ),
-            )
+            let mut item = Item::new(
+                sym_name.clone(),
+                MonoItemKind::MonoItemFn {
+                    name: name.clone(),
+                    id,
+                    body,
+                },
+                details,
+            );
+            item.trait_method = trait_method;
+            item.attributes = crate::compat::types::codegen_attrs(tcx, internal_id);
+            (mono_item, item)
         }
         MonoItem::Static(static_def) => {
             let internal_id = crate::compat::types::internal_def_id(tcx, static_def.def_id());
@@ -110,19 +119,18 @@ pub(super) fn mk_item(tcx: TyCtxt<'_>, item: MonoItem, sym_name: String) -> (Mon
             let inst = mono_instance(tcx, static_def.def_id());
             let body = inst.body();
             let mono_item = MonoItem::Static(static_def);
-            (
-                mono_item,
-                Item::new(
-                    sym_name,
-                    MonoItemKind::MonoItemStatic {
-                        name: static_def.name(),
-                        id: static_def.def_id(),
-                        allocation: alloc,
-                        body,
-                    },
-                    get_item_details(tcx, internal_id, None, None),
-                ),
-            )
+            let mut item = Item::new(
+                sym_name,
+                MonoItemKind::MonoItemStatic {
+                    name: static_def.name(),
+                    id: static_def.def_id(),
+                    allocation: alloc,
+                    body,
+                },
+                get_item_details(tcx, internal_id, None, None),
+            );
+            item.attributes = crate::compat::types::codegen_attrs(tcx, internal_id);
+            (mono_item, item)
         }
         MonoItem::GlobalAsm(ref asm) => {
             let asm_str = format!("{:#?}", asm);
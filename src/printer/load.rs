@@ -0,0 +1,131 @@
+//! Loading `*.smir.json` files for offline analysis.
+//!
+//! [`SmirJson`](super::SmirJson) holds stable_mir types that can only be
+//! serialized, so files are loaded back as a [`serde_json::Value`] document.
+//! [`load_smir_json`] checks the format version and the shape of every item
+//! before handing the document out, so tools can index into it without
+//! guarding every access, and a broken file is reported with the item that
+//! failed rather than as a missing field deep inside an analysis.
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use crate::compat::serde_json;
+use serde_json::Value;
+
+use super::schema::check_format_version;
+
+/// Why a `*.smir.json` file could not be loaded
+#[derive(Debug)]
+pub enum LoadError {
+    Io(PathBuf, std::io::Error),
+    Parse(PathBuf, serde_json::Error),
+    /// The file was written at another format version, or before versioning
+    Version(PathBuf, String),
+    /// A required top-level field is missing or has the wrong type
+    Field(PathBuf, &'static str),
+    /// Item `index` (with its symbol name, if readable) is malformed
+    Item {
+        path: PathBuf,
+        index: usize,
+        symbol: Option<String>,
+        reason: String,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::Io(path, e) => write!(f, "Failed to read {}: {}", path.display(), e),
+            LoadError::Parse(path, e) => write!(f, "Failed to parse {}: {}", path.display(), e),
+            LoadError::Version(path, e) => write!(f, "{}: {}", path.display(), e),
+            LoadError::Field(path, field) => {
+                write!(
+                    f,
+                    "{}: missing or malformed field `{}`",
+                    path.display(),
+                    field
+                )
+            }
+            LoadError::Item {
+                path,
+                index,
+                symbol,
+                reason,
+            } => {
+                write!(f, "{}: item {}", path.display(), index)?;
+                if let Some(symbol) = symbol {
+                    write!(f, " ({})", symbol)?;
+                }
+                write!(f, ": {}", reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for LoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LoadError::Io(_, e) => Some(e),
+            LoadError::Parse(_, e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// Read and validate a `*.smir.json` file written by this version
+pub fn load_smir_json(path: impl AsRef<Path>) -> Result<Value, LoadError> {
+    let path = path.as_ref();
+    let text = std::fs::read_to_string(path).map_err(|e| LoadError::Io(path.into(), e))?;
+    let json: Value = serde_json::from_str(&text).map_err(|e| LoadError::Parse(path.into(), e))?;
+    check_format_version(&json).map_err(|e| LoadError::Version(path.into(), e))?;
+
+    let field = |name: &'static str, valid: fn(&Value) -> bool| {
+        json.get(name)
+            .filter(|v| valid(v))
+            .ok_or_else(|| LoadError::Field(path.into(), name))
+    };
+    field("name", Value::is_string)?;
+    for name in ["allocs", "functions", "types", "spans"] {
+        field(name, Value::is_array)?;
+    }
+    let items = field("items", Value::is_array)?;
+    for (index, item) in items.as_array().into_iter().flatten().enumerate() {
+        check_item(item).map_err(|reason| LoadError::Item {
+            path: path.into(),
+            index,
+            symbol: item["symbol_name"].as_str().map(str::to_string),
+            reason,
+        })?;
+    }
+    Ok(json)
+}
+
+/// Check the fields that item consumers rely on
+fn check_item(item: &Value) -> Result<(), String> {
+    if !item["symbol_name"].is_string() {
+        return Err("no symbol_name".to_string());
+    }
+    let kind = item["mono_item_kind"]
+        .as_object()
+        .filter(|kind| kind.len() == 1)
+        .ok_or("mono_item_kind is not a single variant")?;
+    let (variant, fields) = kind.iter().next().unwrap();
+    match variant.as_str() {
+        "MonoItemFn" => {
+            if !fields["name"].is_string() {
+                return Err("MonoItemFn without a name".to_string());
+            }
+            let body = &fields["body"];
+            if !body.is_null() && !body["blocks"].is_array() {
+                return Err("MonoItemFn body without blocks".to_string());
+            }
+            Ok(())
+        }
+        "MonoItemStatic" if !fields["name"].is_string() => {
+            Err("MonoItemStatic without a name".to_string())
+        }
+        "MonoItemStatic" | "MonoItemGlobalAsm" => Ok(()),
+        other => Err(format!("unknown mono_item_kind {}", other)),
+    }
+}
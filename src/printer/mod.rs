@@ -13,15 +13,19 @@
 //! | [`items`] | Constructing `(MonoItem, Item)` pairs and extracting debug-level details |
 //! | [`mir_visitor`] | `BodyAnalyzer`: single-pass MIR body traversal collecting calls, allocs, types, spans |
 //! | [`ty_visitor`] | `TyCollector`: recursively collects reachable types with layout info (some special kinds are traversed but not stored) |
+//! | [`fingerprint`] | Per-function content hashes that survive renumbering and item reordering |
 //! | [`link_map`] | Function resolution map: type + instance kind to symbol name |
+//! | [`redact`] | Hashing of local identifiers, source paths and string contents for `--redact` |
 //! | [`types`] | Type helpers and [`TypeMetadata`](schema::TypeMetadata) construction |
 //! | [`util`] | Name resolution, attribute queries, and small collection utilities |
 
-use std::io::Write;
-use std::{fs::File, io};
+use std::collections::{HashMap, HashSet};
 
 use crate::compat::middle::ty::TyCtxt;
 use crate::compat::serde_json;
+use crate::mk_graph::util::strip_comment;
+use crate::pipeline::{is_shared_destination, Document, Pipeline};
+use crate::timings::time;
 
 // Macros must be defined before module declarations (textual scoping)
 macro_rules! def_env_var {
@@ -37,6 +41,73 @@ macro_rules! def_env_var {
 def_env_var!(debug_enabled, DEBUG);
 def_env_var!(link_items_enabled, LINK_ITEMS);
 def_env_var!(link_instance_enabled, LINK_INST);
+def_env_var!(redact_enabled, REDACT);
+
+/// Function name given in `QUERY_FN`, restricting collection to that function
+fn query_fn() -> Option<&'static str> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Option<String>> = OnceLock::new();
+    VAR.get_or_init(|| std::env::var("QUERY_FN").ok())
+        .as_deref()
+}
+
+/// Functions listed in the file named by `SKIP_FNS`, excluded from collection.
+/// One name per line, matched like `QUERY_FN`; blank lines and `#` comments
+/// (at the start of a line or after whitespace, so `main::{closure#0}` is a
+/// name) are ignored.
+fn skip_fns() -> &'static [String] {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Vec<String>> = OnceLock::new();
+    VAR.get_or_init(|| match std::env::var("SKIP_FNS") {
+        Ok(path) => std::fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("Failed to read SKIP_FNS file {}: {}", path, e))
+            .lines()
+            .map(|line| strip_comment(line).trim())
+            .filter(|line| !line.is_empty())
+            .map(str::to_string)
+            .collect(),
+        Err(_) => vec![],
+    })
+}
+
+/// Mono item kinds selected in `ITEM_KINDS` (comma-separated `fn`, `static`,
+/// `global_asm`), or `None` to collect all kinds
+fn item_kinds() -> Option<&'static [String]> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Option<Vec<String>>> = OnceLock::new();
+    VAR.get_or_init(|| {
+        let kinds: Vec<String> = std::env::var("ITEM_KINDS")
+            .ok()?
+            .split(',')
+            .map(|kind| kind.trim().to_string())
+            .filter(|kind| !kind.is_empty())
+            .collect();
+        for kind in &kinds {
+            if !matches!(kind.as_str(), "fn" | "static" | "global_asm") {
+                panic!(
+                    "Unknown item kind {} in ITEM_KINDS (expected fn, static or global_asm)",
+                    kind
+                );
+            }
+        }
+        Some(kinds)
+    })
+    .as_deref()
+}
+
+/// Pass selectors given in `MIR_PASSES` (comma-separated), switching the JSON
+/// output to one file per function named like `-Zdump-mir` dumps
+fn mir_passes() -> Option<&'static str> {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<Option<String>> = OnceLock::new();
+    VAR.get_or_init(|| std::env::var("MIR_PASSES").ok())
+        .as_deref()
+}
+
+/// The MIR phase Stable MIR bodies come from, named as in `-Zdump-mir` files.
+/// Instance bodies are the optimized runtime MIR; earlier phases are not
+/// reachable through Stable MIR.
+pub const MIR_PHASE: &str = "runtime-optimized";
 
 macro_rules! debug_log_println {
     ($($args:tt)*) => {
@@ -46,9 +117,12 @@ macro_rules! debug_log_println {
 }
 
 mod collect;
+mod fingerprint;
 mod items;
 mod link_map;
+mod load;
 mod mir_visitor;
+mod redact;
 mod schema;
 mod ty_visitor;
 mod types;
@@ -57,23 +131,112 @@ mod util;
 // Re-exports preserving the public API
 pub use collect::collect_smir;
 pub use items::MonoItemKind;
-pub use schema::{AllocInfo, FnSymType, Item, LinkMapKey, SmirJson, TypeMetadata};
-pub(crate) use util::hash;
+pub use load::{load_smir_json, LoadError};
+pub use schema::{
+    check_format_version, rustc_commit, AllocInfo, BlockAnalysis, BlockRole, FnSymType,
+    FunctionAnalysis, FunctionProperty, Item, LinkMapKey, SmirJson, SourceFileInfo, TraitDispatch,
+    TraitImpl, TraitMethod, TypeMetadata, FORMAT_VERSION,
+};
+pub(crate) use util::{hash, stable_hash};
 
 pub fn emit_smir(tcx: TyCtxt<'_>) {
-    let smir_json =
-        serde_json::to_string(&collect_smir(tcx)).expect("serde_json failed to write result");
+    if let Some(passes) = mir_passes() {
+        return emit_smir_per_function(tcx, passes);
+    }
+    let smir = collect_smir(tcx);
+    let smir_json = time("render smir.json", || {
+        serde_json::to_string(&smir).expect("serde_json failed to write result")
+    });
+    time("write", || {
+        Pipeline::from_env().write(tcx, Document::new("smir.json", smir_json))
+    })
+    .unwrap_or_else(|e| panic!("Failed to write smir.json: {}", e));
+}
 
-    match crate::compat::output::mir_output_path(tcx, "smir.json") {
-        crate::compat::output::OutputDest::Stdout => {
-            write!(&io::stdout(), "{}", smir_json).expect("Failed to write smir.json");
-        }
-        crate::compat::output::OutputDest::File(path) => {
-            let mut b = io::BufWriter::new(
-                File::create(&path)
-                    .unwrap_or_else(|e| panic!("Failed to create {}: {}", path.display(), e)),
+/// Write one `<crate>.<fn>.<phase>.smir.json` per function, following the
+/// `-Zdump-mir` file naming. Each file holds the crate-wide tables (types,
+/// allocs, spans, ...) and the single function's item. Functions sharing a
+/// path (instances of a generic function) get a hash of their symbol name
+/// appended to `<fn>`.
+fn emit_smir_per_function(tcx: TyCtxt<'_>, passes: &str) {
+    for pass in passes.split(',').map(str::trim) {
+        if !matches!(
+            pass,
+            "runtime-optimized" | "optimized" | "PreCodegen" | "all"
+        ) {
+            panic!(
+                "MIR pass selector {:?} is not available: Stable MIR only exposes the {} phase \
+                 (select it with `runtime-optimized`, `optimized`, `PreCodegen`, or `all`)",
+                pass, MIR_PHASE
             );
-            write!(b, "{}", smir_json).expect("Failed to write smir.json");
         }
     }
+
+    let smir = collect_smir(tcx);
+    let crate_name = smir.name.clone();
+    let mut names: Vec<Option<String>> = smir
+        .items
+        .iter()
+        .map(|item| match &item.mono_item_kind {
+            MonoItemKind::MonoItemFn { name, .. } => Some(dump_name(&crate_name, name)),
+            _ => None,
+        })
+        .collect();
+    // instances of a generic function share its path; they are told apart by
+    // a hash of their symbol names
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for name in names.iter().flatten() {
+        *counts.entry(name.as_str()).or_default() += 1;
+    }
+    let shared: HashSet<String> = counts
+        .into_iter()
+        .filter(|(_, n)| *n > 1)
+        .map(|(name, _)| name.to_string())
+        .collect();
+    for (name, item) in names.iter_mut().zip(&smir.items) {
+        if let Some(name) = name.as_mut().filter(|name| shared.contains(name.as_str())) {
+            name.push_str(&format!("-{}", symbol_hash(&item.symbol_name)));
+        }
+    }
+    let mut value = serde_json::to_value(&smir).expect("serde_json failed to write result");
+    let items = match value["items"].take() {
+        serde_json::Value::Array(items) => items,
+        _ => vec![],
+    };
+
+    if is_shared_destination(tcx, &Document::new("smir.json", "")) {
+        panic!(
+            "MIR_PASSES writes one file per function and cannot multiplex them onto one output; \
+             use --out-dir instead of -o - or OUTPUT_TO"
+        );
+    }
+    let pipeline = Pipeline::from_env();
+    for (item, name) in items.into_iter().zip(names) {
+        let Some(name) = name else {
+            continue;
+        };
+        value["items"] = serde_json::Value::Array(vec![item]);
+        let text = serde_json::to_string(&value).expect("serde_json failed to write result");
+        // the output path is `<crate>.mir`, so this names `<crate>.<fn>.<phase>.smir.json`
+        let extension = format!("{}.{}.smir.json", name, MIR_PHASE);
+        pipeline
+            .write(tcx, Document::new(extension.clone(), text))
+            .unwrap_or_else(|e| panic!("Failed to write {}: {}", extension, e));
+    }
+}
+
+/// Eight hex digits of the 64-bit FNV-1a hash of a symbol name, to tell apart
+/// the dump files of functions sharing a path
+fn symbol_hash(symbol: &str) -> String {
+    format!("{:08x}", stable_hash(symbol) >> 32)
+}
+
+/// Function path as `-Zdump-mir` spells it in file names: without the crate
+/// prefix, `::` replaced by `-` (`{impl#0}-fmt`, `main-{closure#0}`)
+fn dump_name(crate_name: &str, name: &str) -> String {
+    let local = name
+        .strip_prefix(crate_name)
+        .and_then(|rest| rest.strip_prefix("::"))
+        .unwrap_or(name);
+    local.replace("::", "-").replace(['/', ' '], "_")
 }
@@ -0,0 +1,359 @@
+//! Redaction of proprietary names and contents from a collected crate.
+//!
+//! With `--redact` (`REDACT` set), identifiers of the local crate, source file
+//! paths, and the contents of string constants are replaced by hashes after
+//! collection, so every output format built from the [`SmirJson`] shows the
+//! MIR structure without the source it came from.
+//!
+//! - Identifiers: every identifier occurring in the names of the crate's own
+//!   items, types and locals, but in no name from other crates, becomes
+//!   `id_<hash>`. `core::fmt::Debug` stays readable, `mycrate::secret` becomes
+//!   `id_1a2b3c4d::id_5e6f7a8b`. The same identifier always gets the same
+//!   hash, so names stay cross-referencable.
+//! - Symbols: mangled symbol names with a path segment that is a redacted
+//!   identifier become `sym_<hash>`, consistently in `items` and `functions`.
+//!   Only whole identifiers count, so a local `new` does not redact
+//!   `renew`.
+//! - Paths: source files outside the toolchain's sources (`/rustc/...`)
+//!   become `redacted/<hash>.<ext>`.
+//! - Strings: the bytes of `str` allocations are overwritten with hash digits
+//!   of the same length.
+//!
+//! Hashes are 64-bit FNV-1a, so the same input redacts the same way with
+//! every toolchain. They are not salted: short or guessable names can be
+//! recovered by hashing candidates, so redaction hides names from readers,
+//! not from a determined attacker.
+
+use std::collections::HashSet;
+
+use crate::compat::stable_mir;
+use stable_mir::mir::alloc::GlobalAlloc;
+use stable_mir::ty::RigidTy;
+
+use super::items::MonoItemKind;
+use super::schema::{FnSymType, SmirJson, TypeMetadata};
+use super::util::stable_hash;
+
+/// Redact `smir` in place
+pub(super) fn redact(smir: &mut SmirJson) {
+    let redactor = Redactor::new(smir);
+
+    smir.name = redactor.text(&smir.name);
+    for item in &mut smir.items {
+        item.symbol_name = redactor.symbol(&item.symbol_name);
+        // the details hold a textual dump of the body, with all names
+        item.details = None;
+        if let Some(m) = &mut item.trait_method {
+            m.trait_name = redactor.text(&m.trait_name);
+            m.self_ty = redactor.text(&m.self_ty);
+            m.method = redactor.text(&m.method);
+        }
+        match &mut item.mono_item_kind {
+            MonoItemKind::MonoItemFn { name, body, .. } => {
+                *name = redactor.text(name);
+                for info in body.iter_mut().flat_map(|b| &mut b.var_debug_info) {
+                    info.name = redactor.text(&info.name);
+                }
+            }
+            MonoItemKind::MonoItemStatic { name, .. } => *name = redactor.text(name),
+            MonoItemKind::MonoItemGlobalAsm { asm } => *asm = "<redacted>".to_string(),
+        }
+    }
+    for dispatch in &mut smir.traits {
+        dispatch.trait_name = redactor.text(&dispatch.trait_name);
+        for imp in &mut dispatch.impls {
+            imp.self_ty = redactor.text(&imp.self_ty);
+            for (method, symbol) in &mut imp.methods {
+                *method = redactor.text(method);
+                *symbol = redactor.symbol(symbol);
+            }
+        }
+    }
+    for (_, sym) in &mut smir.functions {
+        if let FnSymType::NormalSym(name) = sym {
+            *name = redactor.symbol(name);
+        }
+    }
+    for (_, name) in &mut smir.uneval_consts {
+        *name = redactor.text(name);
+    }
+    for (_, metadata) in &mut smir.types {
+        match metadata {
+            TypeMetadata::EnumType { name, .. }
+            | TypeMetadata::StructType { name, .. }
+            | TypeMetadata::UnionType { name, .. }
+            | TypeMetadata::DynType { name, .. }
+            | TypeMetadata::FunType(name) => *name = redactor.text(name),
+            _ => {}
+        }
+    }
+    for (_, span) in &mut smir.spans {
+        span.0 = redactor.path(&span.0);
+    }
+    for file in &mut smir.source_files {
+        file.file = redactor.path(&file.file);
+        file.krate = redactor.text(&file.krate);
+    }
+    for alloc in &mut smir.allocs {
+        let is_str = matches!(alloc.ty().kind().rigid(), Some(RigidTy::Str));
+        if let (true, GlobalAlloc::Memory(memory)) = (is_str, alloc.global_alloc_mut()) {
+            let bytes: Vec<u8> = memory.bytes.iter().map(|b| b.unwrap_or(0)).collect();
+            let digits = format!("{:016x}", stable_hash(&bytes));
+            let mut fill = digits.bytes().cycle();
+            for byte in memory.bytes.iter_mut().flatten() {
+                *byte = fill.next().unwrap_or(b'0');
+            }
+        }
+    }
+    if smir.debug.take().is_some() {
+        eprintln!("--redact: debug information is not redacted and was dropped");
+    }
+}
+
+struct Redactor {
+    /// Identifiers private to the local crate
+    idents: HashSet<String>,
+    /// The subset of `idents` naming items, which symbol names spell out
+    item_idents: HashSet<String>,
+}
+
+impl Redactor {
+    fn new(smir: &SmirJson) -> Self {
+        let prefix = format!("{}::", smir.name);
+        let mut local: HashSet<String> = HashSet::new();
+        let mut foreign: HashSet<String> = HashSet::new();
+        let mut add = |name: &str| {
+            let set = if name == smir.name || name.starts_with(&prefix) {
+                &mut local
+            } else {
+                &mut foreign
+            };
+            set.extend(idents(name).map(str::to_string));
+        };
+
+        add(&smir.name);
+        for item in &smir.items {
+            match &item.mono_item_kind {
+                MonoItemKind::MonoItemFn { name, .. }
+                | MonoItemKind::MonoItemStatic { name, .. } => add(name),
+                MonoItemKind::MonoItemGlobalAsm { .. } => {}
+            }
+        }
+        for (_, metadata) in &smir.types {
+            match metadata {
+                TypeMetadata::EnumType { name, .. }
+                | TypeMetadata::StructType { name, .. }
+                | TypeMetadata::UnionType { name, .. } => add(name),
+                _ => {}
+            }
+        }
+        let item_idents: HashSet<String> = local.difference(&foreign).cloned().collect();
+
+        // local variable names of the crate's own functions
+        for item in &smir.items {
+            if let MonoItemKind::MonoItemFn {
+                name,
+                body: Some(body),
+                ..
+            } = &item.mono_item_kind
+            {
+                if name.starts_with(&prefix) {
+                    local.extend(body.var_debug_info.iter().map(|info| info.name.clone()));
+                }
+            }
+        }
+
+        Self {
+            idents: local.difference(&foreign).cloned().collect(),
+            item_idents,
+        }
+    }
+
+    /// Replace the local identifiers in `text`, keeping everything else
+    fn text(&self, text: &str) -> String {
+        let mut out = String::new();
+        let mut rest = text;
+        while let Some(start) = rest.find(is_ident_char) {
+            out.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let end = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+            let ident = &rest[..end];
+            if self.idents.contains(ident) {
+                out.push_str(&format!("id_{:08x}", stable_hash(ident) as u32));
+            } else {
+                out.push_str(ident);
+            }
+            rest = &rest[end..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// Hash a mangled symbol name if it spells out a local identifier
+    fn symbol(&self, symbol: &str) -> String {
+        if symbol_idents(symbol).any(|ident| self.item_idents.contains(ident)) {
+            format!("sym_{:016x}", stable_hash(symbol))
+        } else {
+            symbol.to_string()
+        }
+    }
+
+    /// Hash a source file path outside the toolchain sources
+    fn path(&self, path: &str) -> String {
+        if path.starts_with("/rustc/") || path == "no-location" {
+            return path.to_string();
+        }
+        match path.rsplit_once('.') {
+            Some((_, ext)) if !ext.contains('/') => {
+                format!("redacted/{:08x}.{}", stable_hash(path) as u32, ext)
+            }
+            _ => format!("redacted/{:08x}", stable_hash(path) as u32),
+        }
+    }
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// The identifiers in a path or type name
+fn idents(name: &str) -> impl Iterator<Item = &str> {
+    name.split(|c| !is_ident_char(c))
+        .filter(|s| s.chars().next().is_some_and(|c| !c.is_ascii_digit()))
+}
+
+/// The identifiers a symbol name spells out. Mangled names (`_ZN...E`, or
+/// `_R...` in the v0 scheme) are split into their length-prefixed path
+/// segments first, so an identifier never matches part of a longer one or a
+/// length digit; segments such as `_$LT$impl$u20$...$GT$` contribute every
+/// identifier in them. Unmangled names (`#[no_mangle]`) are split as paths.
+fn symbol_idents(symbol: &str) -> impl Iterator<Item = &str> {
+    let segments = match symbol
+        .strip_prefix("_ZN")
+        .or_else(|| symbol.strip_prefix("__ZN"))
+    {
+        Some(rest) => length_prefixed(rest, false),
+        None => match symbol.strip_prefix("_R") {
+            Some(rest) => length_prefixed(rest, true),
+            None => vec![symbol],
+        },
+    };
+    segments.into_iter().flat_map(idents)
+}
+
+/// The `<length><bytes>` segments of a mangled name. The legacy scheme is a
+/// plain sequence of segments. In the v0 scheme they are interleaved with
+/// tags and base-62 numbers, and a `_` may separate the length from the
+/// identifier; rather than parsing the grammar, every run of digits is tried
+/// as a length, which finds each identifier and at worst some extra strings.
+fn length_prefixed(mut rest: &str, v0: bool) -> Vec<&str> {
+    let mut segments = vec![];
+    loop {
+        if v0 {
+            match rest.find(|c: char| c.is_ascii_digit()) {
+                Some(start) => rest = &rest[start..],
+                None => break,
+            }
+        }
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let Ok(len) = rest[..digits].parse::<usize>() else {
+            break;
+        };
+        rest = &rest[digits..];
+        let body = if v0 {
+            rest.strip_prefix('_').unwrap_or(rest)
+        } else {
+            rest
+        };
+        match body.get(..len) {
+            Some(segment) => {
+                segments.push(segment);
+                if !v0 {
+                    rest = &body[len..];
+                }
+            }
+            None if v0 => {}
+            None => break,
+        }
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn redactor() -> Redactor {
+        let set = |names: &[&str]| names.iter().map(|s| s.to_string()).collect();
+        Redactor {
+            idents: set(&["secret", "new", "count"]),
+            item_idents: set(&["secret", "new"]),
+        }
+    }
+
+    fn id(ident: &str) -> String {
+        format!("id_{:08x}", stable_hash(ident) as u32)
+    }
+
+    #[test]
+    fn text_replaces_whole_identifiers() {
+        let r = redactor();
+        assert_eq!(
+            r.text("mycrate::secret::<u8>"),
+            format!("mycrate::{}::<u8>", id("secret"))
+        );
+        assert_eq!(r.text("secrets renew count_"), "secrets renew count_");
+        assert_eq!(r.text("count + 1"), format!("{} + 1", id("count")));
+        assert_eq!(r.text(""), "");
+    }
+
+    #[test]
+    fn text_is_stable() {
+        // the FNV-1a hash of "secret", the same with every toolchain
+        assert_eq!(redactor().text("secret"), "id_c020c951");
+    }
+
+    #[test]
+    fn symbol_matches_legacy_segments() {
+        let r = redactor();
+        let local = "_ZN7mycrate6secret17h0123456789abcdefE";
+        assert_eq!(r.symbol(local), format!("sym_{:016x}", stable_hash(local)));
+        let foreign = "_ZN5alloc3vec12Vec$LT$T$GT$7new_in17h0123456789abcdefE";
+        assert_eq!(r.symbol(foreign), foreign);
+        let renew = "_ZN4core5renew17h0123456789abcdefE";
+        assert_eq!(r.symbol(renew), renew);
+        let method = "_ZN5alloc3vec12Vec$LT$T$GT$3new17h0123456789abcdefE";
+        assert_ne!(r.symbol(method), method);
+        let in_impl =
+            "_ZN48_$LT$mycrate..secret..Thing$u20$as$u20$Clone$GT$5clone17hfedcba9876543210E";
+        assert_ne!(r.symbol(in_impl), in_impl);
+    }
+
+    #[test]
+    fn symbol_matches_v0_and_unmangled_names() {
+        let r = redactor();
+        let v0 = "_RNvCs12_7mycrate6secret";
+        assert_ne!(r.symbol(v0), v0);
+        let v0_foreign = "_RNvCs1234_4core7secrets";
+        assert_eq!(r.symbol(v0_foreign), v0_foreign);
+        assert_ne!(r.symbol("secret"), "secret");
+        assert_eq!(r.symbol("main"), "main");
+        // `count` names a local variable, not an item
+        let local_var = "_ZN7mycrate5count17h0123456789abcdefE";
+        assert_eq!(r.symbol(local_var), local_var);
+    }
+
+    #[test]
+    fn path_keeps_toolchain_sources_and_extensions() {
+        let r = redactor();
+        let std = "/rustc/a2545fd6fc66b4323f555223a860c451885d1d2b/library/core/src/fmt/mod.rs";
+        assert_eq!(r.path(std), std);
+        assert_eq!(r.path("no-location"), "no-location");
+        let hashed = format!("{:08x}", stable_hash("src/secret.rs") as u32);
+        assert_eq!(r.path("src/secret.rs"), format!("redacted/{}.rs", hashed));
+        let no_ext = format!("{:08x}", stable_hash("./v1.0/Makefile") as u32);
+        assert_eq!(r.path("./v1.0/Makefile"), format!("redacted/{}", no_ext));
+    }
+}
@@ -6,6 +6,7 @@
 
 use crate::compat::bridge::OpaqueInstanceKind;
 use crate::compat::serde;
+use crate::compat::serde_json;
 use crate::compat::stable_mir;
 
 use std::collections::{HashMap, HashSet};
@@ -282,7 +283,43 @@ pub(super) struct ForeignModule {
 pub struct Item {
     pub symbol_name: String,
     pub mono_item_kind: MonoItemKind,
-    details: Option<ItemDetails>,
+    pub(super) details: Option<ItemDetails>,
+    /// Content hash of a function's body that ignores spans, ids and local
+    /// numbering, to match functions across builds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fingerprint: Option<String>,
+    /// The trait method a function implements
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trait_method: Option<TraitMethod>,
+    /// Codegen attributes (`inline`, `cold`, `no_mangle`, ...)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attributes: Vec<&'static str>,
+}
+
+/// A trait method implemented by a function item
+#[derive(Serialize, Clone)]
+pub struct TraitMethod {
+    /// Path of the trait
+    pub trait_name: String,
+    /// The implementing type, with the instance's generic arguments
+    pub self_ty: String,
+    pub method: String,
+}
+
+/// The monomorphized methods of one trait, by implementing type: which
+/// function a call through the trait reaches for each type
+#[derive(Serialize)]
+pub struct TraitDispatch {
+    pub trait_name: String,
+    /// Sorted by type
+    pub impls: Vec<TraitImpl>,
+}
+
+#[derive(Serialize)]
+pub struct TraitImpl {
+    pub self_ty: String,
+    /// `(method name, symbol name)` pairs, sorted by method name
+    pub methods: Vec<(String, String)>,
 }
 
 impl Item {
@@ -295,6 +332,9 @@ impl Item {
             symbol_name,
             mono_item_kind,
             details,
+            fingerprint: None,
+            trait_method: None,
+            attributes: vec![],
         }
     }
 
@@ -383,6 +423,10 @@ impl AllocInfo {
     pub fn global_alloc(&self) -> &GlobalAlloc {
         &self.global_alloc
     }
+
+    pub(super) fn global_alloc_mut(&mut self) -> &mut GlobalAlloc {
+        &mut self.global_alloc
+    }
 }
 
 /// Structured metadata about a Rust type, suitable for execution or verification.
@@ -447,6 +491,111 @@ pub enum TypeMetadata {
 /// Span location data: `(filename, start_line, start_col, end_line, end_col)`.
 pub type SourceData = crate::compat::spans::SourceData;
 
+/// Metadata of a source file that spans point into, for detecting source
+/// files that changed since the `*.smir.json` was written.
+#[derive(Serialize)]
+pub struct SourceFileInfo {
+    /// File name as it appears in [`SourceData`]
+    pub file: String,
+    /// Crate the file belongs to
+    #[serde(rename = "crate")]
+    pub krate: String,
+    pub lines: usize,
+    /// Hash of the file contents as `algorithm:hex`
+    pub hash: String,
+}
+
+/// Facts derived from one function's body, written with `--analyze`
+#[derive(Serialize)]
+pub struct FunctionAnalysis {
+    pub symbol: String,
+    pub properties: Vec<FunctionProperty>,
+    /// One entry per basic block, in block order
+    pub blocks: Vec<BlockAnalysis>,
+}
+
+/// A property of a function as a whole
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum FunctionProperty {
+    /// Calls itself, directly or through other functions of the crate
+    Recursive,
+    /// Has a cycle in its non-cleanup control flow
+    Loops,
+    /// Has a loop that can be entered at more than one block
+    Irreducible,
+    /// Calls through a function pointer or closure value
+    IndirectCalls,
+    /// Some block calls into the panic machinery or asserts
+    MayPanic,
+}
+
+/// Role of a block, named after its terminator kind
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockRole {
+    Goto,
+    SwitchInt,
+    Resume,
+    Abort,
+    Return,
+    Unreachable,
+    Drop,
+    Call,
+    Assert,
+    InlineAsm,
+}
+
+/// Facts derived from one basic block
+#[derive(Serialize)]
+pub struct BlockAnalysis {
+    pub role: BlockRole,
+    /// The block only runs while unwinding
+    pub cleanup: bool,
+    /// The block calls into the panic machinery or asserts
+    pub panics: bool,
+    /// The block is the enabled branch of a switch on `UbChecks()`
+    pub ub_check: bool,
+}
+
+/// Version of the `*.smir.json` layout. Bump whenever a change to the
+/// structures below would break consumers reading older files.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// The rustc commit backing the pinned toolchain, from the
+/// `metadata.rustc-commit` field of `rust-toolchain.toml`.
+pub fn rustc_commit() -> &'static str {
+    include_str!("../../rust-toolchain.toml")
+        .lines()
+        .find_map(|line| {
+            let (key, value) = line.split_once('=')?;
+            (key.trim() == "rustc-commit").then(|| value.trim().trim_matches('"'))
+        })
+        .unwrap_or("unknown")
+}
+
+/// Check the `format_version` of a parsed `*.smir.json` document against
+/// the version this build writes, explaining how to fix a mismatch.
+pub fn check_format_version(json: &serde_json::Value) -> Result<(), String> {
+    let found = json.get("format_version").and_then(|v| v.as_u64());
+    match found {
+        Some(v) if v == FORMAT_VERSION as u64 => Ok(()),
+        Some(v) => Err(format!(
+            "smir.json format version {} is not supported (expected {}); \
+             regenerate the file with stable_mir_json at format version {} (rustc {})",
+            v,
+            FORMAT_VERSION,
+            FORMAT_VERSION,
+            rustc_commit()
+        )),
+        None => Err(format!(
+            "smir.json has no format_version field (written before versioning was \
+             introduced); regenerate the file with stable_mir_json at format version {}",
+            FORMAT_VERSION
+        )),
+    }
+}
+
 /// Top-level output structure serialized as the `*.smir.json` file.
 ///
 /// Contains all information extracted from the crate's Stable MIR:
@@ -457,15 +606,31 @@ pub type SourceData = crate::compat::spans::SourceData;
 /// sorted where applicable to improve output determinism across runs.
 #[derive(Serialize)]
 pub struct SmirJson {
+    /// Layout version of this file ([`FORMAT_VERSION`])
+    pub format_version: u32,
+    /// Commit of the rustc whose Stable MIR was serialized
+    pub rustc_commit: String,
     pub name: String,
     pub crate_id: u64,
     pub allocs: Vec<AllocInfo>,
     pub functions: Vec<(LinkMapKey, FnSymType)>,
     pub uneval_consts: Vec<(ConstDef, String)>,
     pub items: Vec<Item>,
+    /// Dispatch tables of the traits whose methods were monomorphized
+    pub traits: Vec<TraitDispatch>,
     pub types: Vec<(stable_mir::ty::Ty, TypeMetadata)>,
     pub spans: Vec<(usize, SourceData)>,
+    pub source_files: Vec<SourceFileInfo>,
     pub debug: Option<SmirJsonDebugInfo>,
+    /// Block roles and function properties, with `--analyze`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub analysis: Option<Vec<FunctionAnalysis>>,
+    /// Target triple the crate was compiled for
+    pub target: String,
+    /// Whether `UbChecks` is true in this build, selecting the branch that
+    /// library precondition checks take
+    pub ub_checks: bool,
+    /// Endianness and pointer width of the target
     pub machine: stable_mir::target::MachineInfo,
 }
 
@@ -13,6 +13,18 @@ pub(crate) fn hash<T: std::hash::Hash>(obj: T) -> u64 {
     hasher.finish()
 }
 
+/// 64-bit FNV-1a hash of `bytes`. Unlike `DefaultHasher`, the result is the
+/// same on every platform and Rust release, so it can name things in outputs
+/// that are compared across builds.
+pub(crate) fn stable_hash(bytes: impl AsRef<[u8]>) -> u64 {
+    bytes
+        .as_ref()
+        .iter()
+        .fold(0xcbf2_9ce4_8422_2325_u64, |h, b| {
+            (h ^ *b as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
 pub(super) fn take_any<K: Clone + std::hash::Hash + std::cmp::Eq, V>(
     map: &mut HashMap<K, V>,
 ) -> Option<(K, V)> {
@@ -30,3 +42,15 @@ pub(super) fn fn_inst_for_ty(ty: stable_mir::ty::Ty, direct_call: bool) -> Optio
         .ok()
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::stable_hash;
+
+    #[test]
+    fn stable_hash_is_fnv1a() {
+        assert_eq!(stable_hash(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(stable_hash("a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(stable_hash("foobar"), 0x8594_4171_f739_67e8);
+    }
+}
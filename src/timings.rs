@@ -0,0 +1,97 @@
+//! Phase timings and trace spans for the driver.
+//!
+//! [`time`] runs one phase of the work (collection, type indexing, rendering
+//! an output, writing it) inside a tracing span, and [`time_fn`] does the same
+//! for rendering a single function. The spans reach the logger the driver
+//! installs from `RUSTC_LOG`, so `RUSTC_LOG=stable_mir_json=debug` traces
+//! every phase as it happens. With `--timings` (`TIMINGS` set) the durations
+//! are also recorded and [`report`] prints them to standard error once the
+//! callback has finished: the phases in the order they ran, then the
+//! functions that took longest to render.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::compat::tracing;
+
+/// Number of functions listed in the report
+const SLOWEST_FUNCTIONS: usize = 10;
+
+/// Phases in the order they finished
+static PHASES: Mutex<Vec<(String, Duration)>> = Mutex::new(Vec::new());
+
+/// Render time of each function, summed over all outputs that draw it
+static FUNCTIONS: Mutex<Option<HashMap<String, Duration>>> = Mutex::new(None);
+
+/// Whether `TIMINGS` is set
+pub fn timings_enabled() -> bool {
+    use std::sync::OnceLock;
+    static VAR: OnceLock<bool> = OnceLock::new();
+    *VAR.get_or_init(|| std::env::var("TIMINGS").is_ok())
+}
+
+/// Run phase `phase` of the work, timing it
+pub fn time<T>(phase: &str, f: impl FnOnce() -> T) -> T {
+    let _span = tracing::info_span!("phase", name = phase).entered();
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    tracing::debug!(?elapsed, "finished {}", phase);
+    if timings_enabled() {
+        PHASES.lock().unwrap().push((phase.to_string(), elapsed));
+    }
+    result
+}
+
+/// Render function `name`, timing it
+pub fn time_fn<T>(name: &str, f: impl FnOnce() -> T) -> T {
+    let _span = tracing::debug_span!("function", name).entered();
+    let start = Instant::now();
+    let result = f();
+    if timings_enabled() {
+        let mut functions = FUNCTIONS.lock().unwrap();
+        *functions
+            .get_or_insert_with(HashMap::new)
+            .entry(name.to_string())
+            .or_default() += start.elapsed();
+    }
+    result
+}
+
+/// Print the recorded timings to standard error, if `TIMINGS` is set
+pub fn report() {
+    if !timings_enabled() {
+        return;
+    }
+    let phases = std::mem::take(&mut *PHASES.lock().unwrap());
+    let mut functions: Vec<(String, Duration)> = FUNCTIONS
+        .lock()
+        .unwrap()
+        .take()
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+    functions.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    let width = phases
+        .iter()
+        .map(|(phase, _)| phase.len())
+        .chain(std::iter::once(16))
+        .max()
+        .unwrap_or_default();
+    eprintln!("timings:");
+    for (phase, elapsed) in &phases {
+        eprintln!("  {:width$}  {}", phase, millis(*elapsed), width = width);
+    }
+    if !functions.is_empty() {
+        eprintln!("slowest functions to render (of {}):", functions.len());
+        for (name, elapsed) in functions.iter().take(SLOWEST_FUNCTIONS) {
+            eprintln!("  {:>10}  {}", millis(*elapsed), name);
+        }
+    }
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.1} ms", d.as_secs_f64() * 1000.0)
+}
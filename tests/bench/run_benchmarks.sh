@@ -0,0 +1,136 @@
+#!/usr/bin/env bash
+set -euo pipefail
+
+usage() {
+  cat <<'EOF2'
+Usage: run_benchmarks.sh [--baseline FILE] [--threshold PCT] [--large-fns N] [OUT_FILE]
+
+Runs the driver in every output mode (--json, --dot, --d2, --svg) over three
+fixtures and records wall time and peak memory per run as TSV:
+
+  small   tests/integration/programs/binop.rs
+  medium  every program in tests/integration/programs, one after another
+  large   a generated crate with N functions (default 2000)
+
+Options:
+  --baseline FILE   Compare against an earlier OUT_FILE and fail if any run
+                    is slower than the baseline by more than --threshold.
+  --threshold PCT   Allowed slowdown in percent (default 20).
+  --large-fns N     Number of functions in the generated large fixture.
+  --help, -h        Show this help.
+
+Environment:
+  RUN_SMIR          Driver binary (default: ./target/release/stable_mir_json).
+  TIME              GNU time binary used to measure peak RSS (default: /usr/bin/time).
+EOF2
+}
+
+die() {
+  printf 'Error: %s\n' "$*" >&2
+  exit 1
+}
+
+log() {
+  printf '%s\n' "$*" >&2
+}
+
+BASELINE=""
+THRESHOLD=20
+LARGE_FNS=2000
+OUT_FILE="bench-results.tsv"
+
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --baseline) BASELINE="$2"; shift 2 ;;
+    --threshold) THRESHOLD="$2"; shift 2 ;;
+    --large-fns) LARGE_FNS="$2"; shift 2 ;;
+    --help|-h) usage; exit 0 ;;
+    -*) die "unknown option $1" ;;
+    *) OUT_FILE="$1"; shift ;;
+  esac
+done
+
+RUN_SMIR="${RUN_SMIR:-./target/release/stable_mir_json}"
+TIME="${TIME:-/usr/bin/time}"
+[ -x "$RUN_SMIR" ] || die "driver not found at $RUN_SMIR (run 'cargo build --release' first)"
+[ -x "$TIME" ] || die "GNU time not found at $TIME"
+
+PROGRAMS_DIR="tests/integration/programs"
+WORK_DIR=$(mktemp -d)
+trap 'rm -rf "$WORK_DIR"' EXIT
+
+# -------------------------
+# Fixtures
+# -------------------------
+
+generate_large() {
+  local file="$1" n="$2"
+  {
+    echo "fn f0(x: u64) -> u64 { x.wrapping_add(1) }"
+    for i in $(seq 1 "$((n - 1))"); do
+      echo "fn f$i(x: u64) -> u64 { if x % 3 == 0 { f$((i - 1))(x / 3) } else { x.wrapping_mul($i) } }"
+    done
+    echo "fn main() { let mut acc = 0u64;"
+    for i in $(seq 0 "$((n - 1))"); do
+      echo "  acc = acc.wrapping_add(f$i(acc));"
+    done
+    echo "  std::process::exit((acc % 2) as i32); }"
+  } > "$file"
+}
+
+generate_large "$WORK_DIR/large.rs" "$LARGE_FNS"
+
+fixture_files() {
+  case "$1" in
+    small) echo "$PROGRAMS_DIR/binop.rs" ;;
+    medium) find "$PROGRAMS_DIR" -maxdepth 1 -type f -name '*.rs' | sort ;;
+    large) echo "$WORK_DIR/large.rs" ;;
+  esac
+}
+
+# -------------------------
+# Measurement
+# -------------------------
+
+# Run one mode over one fixture; prints "<seconds>\t<peak KiB>" (summed
+# time, maximum RSS over all files of the fixture).
+measure() {
+  local mode="$1" fixture="$2"
+  local total=0 peak=0
+  for rs in $(fixture_files "$fixture"); do
+    local stats
+    "$TIME" -f '%e %M' -o "$WORK_DIR/time.out" \
+      "$RUN_SMIR" "$mode" -Zno-codegen --out-dir "$WORK_DIR" "$rs" >/dev/null 2>&1 \
+      || die "$mode failed on $rs"
+    stats=$(tail -n 1 "$WORK_DIR/time.out")
+    total=$(awk -v a="$total" -v b="${stats% *}" 'BEGIN { printf "%.2f", a + b }')
+    [ "${stats#* }" -gt "$peak" ] && peak="${stats#* }"
+  done
+  printf '%s\t%s\n' "$total" "$peak"
+}
+
+printf 'fixture\tmode\tseconds\tpeak_kib\n' > "$OUT_FILE"
+for fixture in small medium large; do
+  for mode in --json --dot --d2 --svg; do
+    log "$fixture $mode"
+    printf '%s\t%s\t%s\n' "$fixture" "$mode" "$(measure "$mode" "$fixture")" >> "$OUT_FILE"
+  done
+done
+log "Results written to $OUT_FILE"
+
+# -------------------------
+# Regression check
+# -------------------------
+
+if [ -n "$BASELINE" ]; then
+  [ -f "$BASELINE" ] || die "baseline $BASELINE not found"
+  awk -F'\t' -v threshold="$THRESHOLD" '
+    NR == FNR { if (FNR > 1) base[$1 FS $2] = $3; next }
+    FNR > 1 && ($1 FS $2) in base && base[$1 FS $2] > 0 {
+      change = ($3 - base[$1 FS $2]) * 100 / base[$1 FS $2]
+      printf "%-7s %-7s %8.2fs (baseline %8.2fs, %+6.1f%%)\n", $1, $2, $3, base[$1 FS $2], change
+      if (change > threshold) failed = 1
+    }
+    END { if (failed) { print "Performance regression above " threshold "%"; exit 1 } }
+  ' "$BASELINE" "$OUT_FILE"
+fi